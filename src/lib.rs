@@ -1,6 +1,11 @@
-/// Main module of the CCS user library. Traits presented in this module
-/// are implementing handles for objects, channels and services and not
-/// direct information.
+//! Main module of the CCS user library. Traits presented in this module
+//! are implementing handles for objects, channels and services and not
+//! direct information.
+
+#[cfg(feature = "futures")]
+extern crate futures_core;
+#[cfg(feature = "futures")]
+extern crate futures_sink;
 
 pub mod meta;
 
@@ -9,3 +14,5 @@ pub mod master;
 pub mod local;
 
 pub mod tools;
+
+pub mod rpc;
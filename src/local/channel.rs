@@ -0,0 +1,903 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::{Arc, MutexGuard};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use super::*;
+use super::runtime::{Runtime, Registry, ChannelRecord, Delivery};
+use super::runtime::MemoryOwner;
+
+/// Channel of the local runtime. Each thread has its own instance
+/// of the channel and all operations are performed on behalf of that
+/// thread.
+pub struct MyChannel {
+
+    /// Runtime this channel belongs to.
+    runtime: Arc<Runtime>,
+
+    /// ID of the thread that uses this instance.
+    thread: usize,
+
+    /// Arch independent part.
+    channel: Channel,
+
+    /// Policy of the channel. Used when the channel is already closed.
+    policy: Policy,
+}
+
+impl MyChannel {
+
+    /// Create new channel instance of given thread.
+    pub(crate) fn new(runtime: Arc<Runtime>, reg: &Registry, thread: usize,
+            channel: usize) -> Self {
+        MyChannel {
+            runtime,
+            thread,
+            channel: reg.channel_handle(channel),
+            policy: reg.channels[&channel].policy,
+        }
+    }
+
+    /// ID of the thread that uses this instance of the channel.
+    pub fn thread_id(&self) -> usize {
+        self.thread
+    }
+
+    /// Lowest descriptor of this channel in the handle table
+    /// of the thread. None if the thread has no descriptor of it.
+    pub fn descriptor(&self) -> Option<Descriptor> {
+        let reg = self.runtime.lock();
+        let id = reg.channel_id(&self.channel).ok()?;
+        let rec = reg.threads.get(&self.thread)?;
+        rec.handles.iter()
+                .find(|&(_, &channel)| channel == id)
+                .map(|(&descriptor, _)| descriptor)
+    }
+
+    /// Take next message from the queue and wait for it if the queue is
+    /// empty. Thread is in waiting state meanwhile. None is returned
+    /// if deadline has passed. Cancelled thread does not wait.
+    fn wait_pop(&self, deadline: Option<Instant>)
+            -> ChannelResult<Option<Message>> {
+        let mut reg = self.runtime.lock();
+        let result = loop {
+            reg = self.runtime.park(reg, self.thread);
+            match self.try_pop(&mut reg) {
+                Ok(None) => (),
+                other => break other,
+            }
+
+            let cancelled = reg.threads.get(&self.thread)
+                    .is_some_and(|rec| rec.cancelled);
+            if cancelled {
+                break Err(ChannelError::Cancelled);
+            }
+
+            let now = Instant::now();
+            if deadline.is_some_and(|deadline| deadline <= now) {
+                break Ok(None);
+            }
+
+            let target = WaitTarget::single(self.channel.id());
+            self.set_state(&mut reg, ThreadState::Wait { target, deadline });
+            reg = match deadline {
+                Some(deadline) => {
+                    self.runtime.wait_timeout(reg, deadline - now).0
+                },
+                None => self.runtime.wait(reg),
+            };
+        };
+
+        self.set_state(&mut reg, ThreadState::Active);
+        self.runtime.notify(reg);
+        result
+    }
+
+    /// Runtime this channel belongs to.
+    pub(crate) fn runtime(&self) -> &Arc<Runtime> {
+        &self.runtime
+    }
+
+    /// Events of given kinds that are present in the channel now.
+    /// Members event appears if the count of members differs from given
+    /// one.
+    pub(crate) fn events(&self, reg: &mut Registry,
+            kinds: &BTreeSet<ChannelEvent>, members: usize)
+            -> Vec<ChannelEvent> {
+        // Length of the queue of this thread if it is a member.
+        let (count, queue, room, publish) = match self.record(reg) {
+            Ok(chan) => {
+                let queue = chan.members.get(&self.thread).map(|q| q.len());
+                let publish = chan.policy.publish_subscribe();
+                (chan.members.len(), queue, chan.has_room(self.thread),
+                        publish)
+            },
+            Err(_) => (0, None, false, false),
+        };
+
+        kinds.iter().cloned()
+                .filter(|&kind| match kind {
+                    ChannelEvent::Receive => {
+                        queue.is_some_and(|len| len > 0 || count < 2)
+                    },
+                    ChannelEvent::Send => {
+                        queue.is_some() && (count >= 2 || publish) && room
+                    },
+                    ChannelEvent::Members => count != members,
+                    ChannelEvent::Closed => queue.is_none(),
+                })
+                .collect()
+    }
+
+    /// Whether the thread has no other members to get events from.
+    /// It is so when the channel is closed or the thread left it.
+    pub(crate) fn is_alone(&self, reg: &mut Registry) -> bool {
+        match self.record(reg) {
+            Ok(chan) => {
+                !chan.members.contains_key(&self.thread)
+                        || chan.members.len() < 2
+            },
+            Err(_) => true,
+        }
+    }
+
+    /// Set the state of the thread that uses this instance if it is
+    /// still running.
+    pub(crate) fn set_state(&self, reg: &mut Registry, state: ThreadState) {
+        if let Some(rec) = reg.threads.get_mut(&self.thread) {
+            if !rec.state.is_finished() {
+                rec.state = state;
+            }
+        }
+    }
+
+    /// Record of this channel. Error is returned if the channel
+    /// was closed.
+    fn record<'a>(&self, reg: &'a mut Registry)
+            -> ChannelResult<&'a mut ChannelRecord> {
+        let id = reg.channel_id(&self.channel)?;
+        Ok(reg.channels.get_mut(&id).unwrap())
+    }
+
+    /// Record of this channel if the thread holds some descriptor
+    /// of it. Operations of the members go through the handle table.
+    fn member<'a>(&self, reg: &'a mut Registry)
+            -> ChannelResult<&'a mut ChannelRecord> {
+        let id = reg.channel_id(&self.channel)?;
+        let holds = reg.threads.get(&self.thread)
+                .is_some_and(|rec| rec.holds(id));
+        if !holds {
+            return Err(ChannelError::NotMember);
+        }
+        Ok(reg.channels.get_mut(&id).unwrap())
+    }
+
+    /// ID of the service of the thread that uses this instance.
+    fn service(&self, reg: &Registry) -> ChannelResult<usize> {
+        reg.threads.get(&self.thread)
+                .map(|rec| rec.service)
+                .ok_or(ChannelError::NotMember)
+    }
+
+    /// Take next message from the queue of this thread. Caller notifies
+    /// the runtime as senders may have room now.
+    fn try_pop(&self, reg: &mut Registry) -> ChannelResult<Option<Message>> {
+        let (alone, delivery) = {
+            let chan = self.member(reg)?;
+            let alone = chan.members.len() < 2;
+            let queue = chan.members.get_mut(&self.thread).unwrap();
+            (alone, queue.pop_front())
+        };
+
+        match delivery {
+            Some(Delivery::Message(msg, sender)) => {
+                let chan = self.record(reg)?;
+                let bounded = chan.policy.capacity().is_some();
+                if chan.policy.work_queue() || chan.policy.reliable() {
+                    // Message is delivered again if the thread dies
+                    // before acknowledging it.
+                    chan.unacked.entry(self.thread).or_default()
+                            .push((msg.clone(), sender));
+                } else {
+                    reg.release(sender, msg.data().len());
+                }
+                if bounded {
+                    reg.touch(self.channel.id(), &[ChannelEvent::Send]);
+                }
+                Ok(Some(msg))
+            },
+            Some(Delivery::Notice(notice)) => Err(ChannelError::Notice(notice)),
+            None if alone => Err(ChannelError::Disconnected),
+            None => Ok(None),
+        }
+    }
+
+    /// Connect the thread to the channel if the policy of the channel
+    /// or the invitation allows it.
+    fn try_join(&self, reg: &mut Registry) -> ChannelResult<()> {
+        let service = self.service(reg)?;
+        {
+            let chan = self.record(reg)?;
+            if chan.members.contains_key(&self.thread) {
+                return Err(ChannelError::AlreadyMember);
+            }
+
+            let invited = chan.invited.contains(&service);
+            if !invited && !chan.policy.join_by_handle() {
+                return Err(ChannelError::JoinForbiden);
+            }
+
+            let single = chan.policy.no_multiple_connectons();
+            if single && chan.members.len() >= 2 {
+                return Err(ChannelError::MultipleConnectionsForbiden);
+            }
+        }
+
+        reg.connect(self.thread, self.channel.id());
+        Ok(())
+    }
+
+    /// Queue the message to the other members of the channel.
+    fn deliver(&self, mut reg: MutexGuard<'_, Registry>, msg: &Message)
+            -> ChannelResult<()> {
+        // Reliable channel numbers the messages of each sender.
+        let stamped = self.record(&mut reg)?.stamp(self.thread, msg)?;
+        let msg = stamped.as_ref().unwrap_or(msg);
+
+        let (receivers, publish) = {
+            let chan = self.member(&mut reg)?;
+
+            // Publishing does not need subscribers.
+            let publish = chan.policy.publish_subscribe();
+            if chan.members.len() < 2 && !publish {
+                return Err(ChannelError::Disconnected);
+            }
+            (chan.targets(self.thread, msg), publish)
+        };
+
+        let valid = msg.grants().iter()
+                .all(|grant| reg.can_send_grant(grant.id(), self.thread));
+        if !valid {
+            return Err(ChannelError::InvalidGrant);
+        }
+
+        // Descriptors can be passed only to the single receiver.
+        let passed = msg.descriptors().to_vec();
+        if !passed.is_empty() {
+            if receivers.len() > 1 || publish {
+                return Err(ChannelError::MultipleConnectionsForbiden);
+            }
+            let receiver = *receivers.first()
+                    .ok_or(ChannelError::Disconnected)?;
+            reg.can_pass(self.thread, &passed, receiver)?;
+        }
+
+        let (room, overflow) = {
+            let chan = self.record(&mut reg)?;
+            (chan.fits(&receivers), chan.policy.overflow())
+        };
+        if !room && overflow != Overflow::DropOldest {
+            return Err(ChannelError::QueueFull);
+        }
+
+        // Each copy of the message is charged to the sender.
+        let owner = MemoryOwner::Thread(self.thread);
+        let bytes = msg.data().len().saturating_mul(receivers.len());
+        let mut reg = self.runtime.charge(reg, owner, bytes)
+                .map_err(|_| ChannelError::QuotaExceeded)?;
+
+        let mut copy = msg.clone();
+        if !passed.is_empty() {
+            match reg.pass(self.thread, &passed, receivers[0]) {
+                Ok(moved) => copy.set_descriptors(moved),
+                Err(err) => {
+                    reg.release(owner, bytes);
+                    return Err(err);
+                },
+            }
+        }
+
+        let (receivers, dropped) = {
+            let chan = self.record(&mut reg)?;
+            let receivers = chan.targets(self.thread, &copy);
+            if publish {
+                chan.remember(&copy);
+            }
+            if chan.policy.work_queue() {
+                chan.cursor = receivers.first().cloned().unwrap_or(chan.cursor);
+            }
+            chan.confirm(self.thread, &copy);
+
+            // Full queues lose their oldest messages.
+            let capacity = chan.policy.capacity().unwrap_or(usize::MAX);
+            let mut dropped = Vec::new();
+            for &id in &receivers {
+                if chan.occupancy(id) >= capacity {
+                    dropped.extend(chan.drop_oldest(id));
+                }
+                let queue = chan.members.get_mut(&id).unwrap();
+                queue.push_back(Delivery::Message(copy.clone(), owner));
+            }
+            (receivers, dropped)
+        };
+        for (sender, bytes) in dropped {
+            reg.release(sender, bytes);
+        }
+        for &receiver in &receivers {
+            reg.signal(receiver, self.channel.id());
+        }
+
+        for grant in msg.grants() {
+            let rec = reg.grants.get_mut(&grant.id()).unwrap();
+            rec.holders.extend(receivers.iter().cloned());
+            rec.sent = true;
+        }
+        self.runtime.notify(reg);
+        Ok(())
+    }
+
+    /// Whether the thread that uses this instance is suspended. Futures
+    /// of the suspended thread do not make progress.
+    fn is_suspended(&self, reg: &Registry) -> bool {
+        reg.threads.get(&self.thread)
+                .is_some_and(|rec| rec.suspended && !rec.state.is_finished())
+    }
+
+    /// Whether the sender must wait for the receivers to take their
+    /// messages. Errors are left to be reported by the delivery.
+    fn must_wait(&self, reg: &mut Registry) -> bool {
+        match self.record(reg) {
+            Ok(chan) => {
+                chan.members.contains_key(&self.thread)
+                        && chan.policy.overflow() == Overflow::Block
+                        && !chan.has_room(self.thread)
+            },
+            Err(_) => false,
+        }
+    }
+
+    /// Change the capacity of the queues of the channel. Only members
+    /// can change it and it cannot be zero. Senders waiting for room
+    /// are woken if the queues get larger. Messages queued over smaller
+    /// capacity stay in the queues.
+    pub fn set_capacity(&self, capacity: Option<usize>, overflow: Overflow)
+            -> ChannelResult<()> {
+        let mut reg = self.runtime.lock();
+        let chan = self.member(&mut reg)?;
+        chan.policy = chan.policy.with_capacity(capacity, overflow)?;
+        reg.touch(self.channel.id(), &[ChannelEvent::Send]);
+        self.runtime.notify(reg);
+        Ok(())
+    }
+
+    /// Acknowledge all the messages received from the work queue
+    /// or reliable channel so far. Acknowledgement is cumulative: single
+    /// messages cannot be acknowledged out of order. Acknowledged
+    /// messages are not delivered again to other members or
+    /// to the restarted thread anymore and their senders see them
+    /// acknowledged. Count of the acknowledged messages is returned.
+    pub fn acknowledge(&self) -> ChannelResult<usize> {
+        let mut reg = self.runtime.lock();
+        let unacked = {
+            let chan = self.member(&mut reg)?;
+            chan.unacked.remove(&self.thread).unwrap_or_default()
+        };
+
+        let count = unacked.len();
+        for (msg, sender) in unacked {
+            reg.release(sender, msg.data().len());
+        }
+        self.runtime.notify(reg);
+        Ok(count)
+    }
+
+    /// Sequence number of the last message this thread sent over
+    /// reliable channel. Runtime does not retransmit lost messages,
+    /// so keep the message with this number to send it again
+    /// if it may have been lost outside of the runtime.
+    pub fn last_sequence(&self) -> Option<u64> {
+        let mut reg = self.runtime.lock();
+        self.record(&mut reg).ok()?.sequences.get(&self.thread).cloned()
+    }
+
+    /// Sequence number up to which all the messages this thread sent
+    /// over reliable channel were acknowledged by their receivers.
+    /// Runtime delivers the messages with higher numbers again
+    /// if their receivers leave before acknowledging them.
+    pub fn acknowledged(&self) -> Option<u64> {
+        let mut reg = self.runtime.lock();
+        self.record(&mut reg).ok()?.acknowledged(self.thread)
+    }
+
+    /// Receive only the messages which topics match some of given
+    /// patterns. Messages without topic are still received. Empty list
+    /// of the patterns subscribes to all topics. Queued messages that
+    /// do not match anymore are dropped.
+    pub fn subscribe(&self, patterns: &[TopicPattern]) -> ChannelResult<()> {
+        let mut reg = self.runtime.lock();
+        let dropped = {
+            let chan = self.member(&mut reg)?;
+            if patterns.is_empty() {
+                chan.filters.remove(&self.thread);
+            } else {
+                chan.filters.insert(self.thread, patterns.to_vec());
+            }
+
+            let queue = ::std::mem::take(
+                    chan.members.get_mut(&self.thread).unwrap());
+            let mut dropped = Vec::new();
+            let mut kept = VecDeque::with_capacity(queue.len());
+            for delivery in queue {
+                match delivery {
+                    Delivery::Message(ref msg, sender)
+                            if !chan.accepts(self.thread, msg) => {
+                        dropped.push((sender, msg.data().len()));
+                    },
+                    delivery => kept.push_back(delivery),
+                }
+            }
+            chan.members.insert(self.thread, kept);
+            dropped
+        };
+        for (sender, bytes) in dropped {
+            reg.release(sender, bytes);
+        }
+        reg.touch(self.channel.id(), &[ChannelEvent::Send]);
+        self.runtime.notify(reg);
+        Ok(())
+    }
+
+    /// Try joining the channel. Future waits while the policy of the
+    /// channel forbids joining.
+    pub(crate) fn poll_join(&self, cx: &mut Context<'_>)
+            -> Poll<ChannelResult<()>> {
+        let mut reg = self.runtime.lock();
+        match self.try_join(&mut reg) {
+            Err(ChannelError::JoinForbiden)
+                    | Err(ChannelError::MultipleConnectionsForbiden) => {
+                let (id, event) = (self.channel.id(), ChannelEvent::Members);
+                self.runtime.register(&mut reg, id, event, cx.waker());
+                Poll::Pending
+            },
+            Ok(()) => {
+                self.runtime.notify(reg);
+                Poll::Ready(Ok(()))
+            },
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    }
+
+    /// Try sending the message. Future waits while the thread
+    /// is suspended or some receiver has no room for the message.
+    pub(crate) fn poll_send(&self, cx: &mut Context<'_>, msg: &Message)
+            -> Poll<ChannelResult<()>> {
+        let mut reg = self.runtime.lock();
+        if self.is_suspended(&reg) || self.must_wait(&mut reg) {
+            let (id, event) = (self.channel.id(), ChannelEvent::Send);
+            self.runtime.register(&mut reg, id, event, cx.waker());
+            return Poll::Pending;
+        }
+        Poll::Ready(self.deliver(reg, msg))
+    }
+
+    /// Try receiving the message. Future waits while the queue is empty
+    /// or the thread is suspended.
+    pub(crate) fn poll_receive(&self, cx: &mut Context<'_>)
+            -> Poll<ChannelResult<Message>> {
+        let mut reg = self.runtime.lock();
+        if !self.is_suspended(&reg) {
+            match self.try_pop(&mut reg) {
+                Ok(Some(msg)) => {
+                    self.runtime.notify(reg);
+                    return Poll::Ready(Ok(msg));
+                },
+                Ok(None) => (),
+                Err(err) => return Poll::Ready(Err(err)),
+            }
+
+            let cancelled = reg.threads.get(&self.thread)
+                    .is_some_and(|rec| rec.cancelled);
+            if cancelled {
+                return Poll::Ready(Err(ChannelError::Cancelled));
+            }
+        }
+
+        let (id, event) = (self.channel.id(), ChannelEvent::Receive);
+        self.runtime.register(&mut reg, id, event, cx.waker());
+        Poll::Pending
+    }
+
+    /// Wait until count of the channel members differs from given one.
+    /// New count is returned.
+    pub(crate) fn poll_members(&self, cx: &mut Context<'_>, count: usize)
+            -> Poll<ChannelResult<usize>> {
+        let mut reg = self.runtime.lock();
+        let members = match self.record(&mut reg) {
+            Ok(chan) => chan.members.len(),
+            Err(err) => return Poll::Ready(Err(err)),
+        };
+        if members != count {
+            return Poll::Ready(Ok(members));
+        }
+
+        let (id, event) = (self.channel.id(), ChannelEvent::Members);
+        self.runtime.register(&mut reg, id, event, cx.waker());
+        Poll::Pending
+    }
+}
+
+impl ChannelArchitecture for MyChannel {
+
+    type S = MyService;
+
+    fn leave(&self) -> ChannelResult<()> {
+        let mut reg = self.runtime.lock();
+        let channel = reg.channel_id(&self.channel)?;
+        reg.can_leave(self.thread, channel)?;
+        reg.disconnect(self.thread, channel);
+        self.runtime.notify(reg);
+        Ok(())
+    }
+
+    fn invite(&self, service: &MyService) -> ChannelResult<()> {
+        let mut reg = self.runtime.lock();
+        let chan = self.record(&mut reg)?;
+
+        let member = chan.members.contains_key(&self.thread);
+        if !member && !chan.policy.invitation_from_not_member() {
+            return Err(ChannelError::InvitationForbiden);
+        }
+
+        chan.invited.insert(service.handle().id());
+
+        // Invited threads may be waiting to join.
+        reg.touch(self.channel.id(), &[ChannelEvent::Members]);
+        self.runtime.notify(reg);
+        Ok(())
+    }
+
+    fn join(&self) -> ChannelResult<()> {
+        let mut reg = self.runtime.lock();
+        self.try_join(&mut reg)?;
+        self.runtime.notify(reg);
+        Ok(())
+    }
+
+    fn send(&self, msg: Message) -> ChannelResult<()> {
+        let mut reg = self.runtime.lock();
+        let mut waited = false;
+        loop {
+            reg = self.runtime.park(reg, self.thread);
+            if !self.must_wait(&mut reg) {
+                break;
+            }
+
+            let cancelled = reg.threads.get(&self.thread)
+                    .is_some_and(|rec| rec.cancelled);
+            if cancelled {
+                self.set_state(&mut reg, ThreadState::Active);
+                return Err(ChannelError::Cancelled);
+            }
+
+            // Wait for the receivers to take their messages.
+            let target = WaitTarget::single(self.channel.id());
+            let state = ThreadState::Wait { target, deadline: None };
+            self.set_state(&mut reg, state);
+            waited = true;
+            reg = self.runtime.wait(reg);
+        }
+
+        if waited {
+            self.set_state(&mut reg, ThreadState::Active);
+        }
+        self.deliver(reg, &msg)
+    }
+
+    fn receive(&self) -> ChannelResult<Message> {
+        // Without deadline the message is always received.
+        self.wait_pop(None).map(Option::unwrap)
+    }
+
+    fn receive_timeout(&self, timeout: Duration)
+            -> ChannelResult<Option<Message>> {
+        self.wait_pop(Some(Instant::now() + timeout))
+    }
+
+    fn try_receive(&self) -> ChannelResult<Option<Message>> {
+        let mut reg = self.runtime.park(self.runtime.lock(), self.thread);
+        let result = self.try_pop(&mut reg);
+        self.runtime.notify(reg);
+        result
+    }
+
+    fn occupancy(&self) -> usize {
+        let mut reg = self.runtime.lock();
+        self.record(&mut reg)
+                .map(|chan| chan.occupancy(self.thread))
+                .unwrap_or(0)
+    }
+
+    fn connections_count(&self) -> usize {
+        let mut reg = self.runtime.lock();
+        self.record(&mut reg).map(|chan| chan.members.len()).unwrap_or(0)
+    }
+
+    fn policy(&self) -> Policy {
+        let mut reg = self.runtime.lock();
+        match self.record(&mut reg) {
+            Ok(chan) => chan.policy,
+            Err(_) => self.policy,
+        }
+    }
+
+    fn handle(&self) -> &Channel {
+        &self.channel
+    }
+}
+
+impl PartialEq for MyChannel {
+
+    fn eq(&self, other: &MyChannel) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MyChannel {}
+
+impl PartialOrd for MyChannel {
+
+    fn partial_cmp(&self, other: &MyChannel) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MyChannel {
+
+    fn cmp(&self, other: &MyChannel) -> Ordering {
+        let id = self.channel.id().cmp(&other.channel.id());
+        if id != Ordering::Equal {
+            return id;
+        }
+
+        self.thread.cmp(&other.thread)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    /// Builder of the policy of the channel that threads join by its
+    /// handle.
+    fn builder() -> PolicyBuilder {
+        let mut builder = PolicyBuilder::new();
+        builder.invitation_from_not_member(false);
+        builder.origin_can_leave(true);
+        builder.join_by_handle(true);
+        builder.no_multiple_connectons(false);
+        builder
+    }
+
+    /// Policy of the channel with the features under test set by given
+    /// function.
+    fn policy<F: FnOnce(&mut PolicyBuilder)>(features: F) -> Policy {
+        let mut builder = builder();
+        features(&mut builder);
+        builder.build().unwrap()
+    }
+
+    /// Spawn the thread that gets the descriptor of the channel over
+    /// its start channel and runs given body with both channels. Start
+    /// channel is returned when the thread has joined the channel.
+    fn spawn_member<F>(master: &mut MyMaster, chan: &MyChannel, body: F)
+            -> MyChannel
+            where F: Fn(&MyChannel, &MyChannel) + Send + Sync + 'static {
+        let entry = MyServiceEntry::new(move |_: &Channel| {
+            let thread = MyMaster::current().unwrap().thread();
+            let start = thread.channel(Descriptor::new(0)).unwrap();
+            let msg = start.receive().unwrap();
+            let chan = thread.channel(msg.descriptors()[0]).unwrap();
+            start.send(Message::new(vec![])).unwrap();
+            body(&start, &chan);
+        });
+        let (_, start) = master.spawn_thread(entry).unwrap();
+
+        let dup = master.thread().duplicate(chan.descriptor().unwrap())
+                .unwrap();
+        start.send(Message::new(vec![]).with_descriptor(dup)).unwrap();
+        start.receive().unwrap();
+        start
+    }
+
+    #[test]
+    fn publish_subscribe() {
+        let mut master = MyMaster::new();
+        let topics = master.new_channel(policy(|b| b.publish_subscribe(2)));
+
+        // Nobody is subscribed yet but last messages are remembered.
+        let config = Path::parse("config.net").unwrap();
+        for i in 1..4 {
+            let msg = Message::new(vec![i]).with_topic(config.clone());
+            topics.send(msg).unwrap();
+        }
+
+        let start = spawn_member(&mut master, &topics, |start, topics| {
+            for _ in 0..2 {
+                start.send(topics.receive().unwrap()).unwrap();
+            }
+
+            let pattern = TopicPattern::parse("telemetry.**").unwrap();
+            topics.subscribe(&[pattern]).unwrap();
+            start.send(Message::new(vec![])).unwrap();
+            start.send(topics.receive().unwrap()).unwrap();
+        });
+        assert_eq!(start.receive().unwrap().data(), &[2]);
+        assert_eq!(start.receive().unwrap().data(), &[3]);
+        assert_eq!(start.receive().unwrap().data(), &[]);
+        assert_eq!(topics.connections_count(), 2);
+
+        // Only the subscribed topics are delivered.
+        let telemetry = Path::parse("telemetry.cpu").unwrap();
+        topics.send(Message::new(vec![4]).with_topic(config)).unwrap();
+        topics.send(Message::new(vec![5]).with_topic(telemetry)).unwrap();
+        let msg = start.receive().unwrap();
+        assert_eq!(msg.data(), &[5]);
+        assert_eq!(msg.topic().unwrap().to_string(), "telemetry.cpu");
+    }
+
+    #[test]
+    fn work_queue() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        static CRASHED: AtomicBool = AtomicBool::new(false);
+
+        // Published messages are broadcast to all subscribers.
+        let mut builder = builder();
+        builder.publish_subscribe(0);
+        builder.distribution(Distribution::LeastLoaded);
+        assert!(builder.build().is_err());
+
+        let mut master = MyMaster::new();
+        let policy = policy(|b| b.distribution(Distribution::RoundRobin));
+        let jobs = master.new_channel(policy);
+
+        // First worker that gets empty job dies without acknowledging it.
+        let workers: Vec<MyChannel> = (0..2)
+                .map(|_| spawn_member(&mut master, &jobs, |start, jobs| {
+                    while let Ok(job) = jobs.receive() {
+                        let crash = job.data().is_empty()
+                                && !CRASHED.swap(true, Ordering::SeqCst);
+                        if crash {
+                            return;
+                        }
+                        assert_eq!(jobs.acknowledge(), Ok(1));
+                        start.send(job).unwrap();
+                    }
+                }))
+                .collect();
+        assert_eq!(jobs.connections_count(), 3);
+
+        for i in 1..5 {
+            jobs.send(Message::new(vec![i])).unwrap();
+        }
+        assert_eq!(workers[0].receive().unwrap().data(), &[1]);
+        assert_eq!(workers[1].receive().unwrap().data(), &[2]);
+        assert_eq!(workers[0].receive().unwrap().data(), &[3]);
+        assert_eq!(workers[1].receive().unwrap().data(), &[4]);
+
+        // Job of the dead worker goes to the other one.
+        jobs.send(Message::new(vec![])).unwrap();
+        assert_eq!(workers[0].receive(), Err(ChannelError::Disconnected));
+        assert_eq!(workers[1].receive().unwrap().data(), &[]);
+        assert_eq!(jobs.connections_count(), 2);
+    }
+
+    #[test]
+    fn reliable_delivery() {
+        let mut master = MyMaster::new();
+        let reliable = master.new_channel(policy(|b| b.reliable(true)));
+        let start = spawn_member(&mut master, &reliable, |start, reliable| {
+            while let Ok(msg) = reliable.receive() {
+                start.send(msg).unwrap();
+                assert_eq!(reliable.acknowledge(), Ok(1));
+            }
+        });
+
+        assert_eq!(reliable.last_sequence(), None);
+        reliable.send(Message::new(vec![1])).unwrap();
+        assert_eq!(reliable.last_sequence(), Some(1));
+        let msg = start.receive().unwrap();
+        assert_eq!((msg.data(), msg.sequence()), (&[1][..], Some(1)));
+
+        // Message sent again is not received twice. Sender cannot skip
+        // the numbers.
+        reliable.send(Message::new(vec![1]).with_sequence(1)).unwrap();
+        for &sequence in &[0, 3] {
+            let msg = Message::new(vec![3]).with_sequence(sequence);
+            assert_eq!(reliable.send(msg),
+                    Err(ChannelError::InvalidSequence));
+        }
+        assert_eq!(reliable.last_sequence(), Some(1));
+        reliable.send(Message::new(vec![2]).with_sequence(2)).unwrap();
+        let msg = start.receive().unwrap();
+        assert_eq!((msg.data(), msg.sequence()), (&[2][..], Some(2)));
+    }
+
+    #[test]
+    fn reliable_redelivery() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        static CRASHED: AtomicBool = AtomicBool::new(false);
+
+        let mut master = MyMaster::new();
+        let jobs = master.new_channel(policy(|b| {
+            b.reliable(true);
+            b.distribution(Distribution::RoundRobin);
+        }));
+
+        // First job kills the worker before it is acknowledged.
+        let workers: Vec<MyChannel> = (0..2)
+                .map(|_| spawn_member(&mut master, &jobs, |start, jobs| {
+                    while let Ok(job) = jobs.receive() {
+                        if !CRASHED.swap(true, Ordering::SeqCst) {
+                            return;
+                        }
+                        assert_eq!(jobs.acknowledge(), Ok(1));
+                        start.send(job).unwrap();
+                    }
+                }))
+                .collect();
+
+        // Job left by the dead worker goes to the other one.
+        jobs.send(Message::new(vec![1])).unwrap();
+        assert_eq!(workers[0].receive(), Err(ChannelError::Disconnected));
+        let msg = workers[1].receive().unwrap();
+        assert_eq!((msg.data(), msg.sequence()), (&[1][..], Some(1)));
+
+        // Copy the sender sends again is dropped.
+        jobs.send(Message::new(vec![1]).with_sequence(1)).unwrap();
+        jobs.send(Message::new(vec![2])).unwrap();
+        let msg = workers[1].receive().unwrap();
+        assert_eq!((msg.data(), msg.sequence()), (&[2][..], Some(2)));
+        assert_eq!(jobs.acknowledged(), Some(2));
+    }
+
+    #[test]
+    fn reliable_orphans() {
+        let mut master = MyMaster::new();
+        let reliable = master.new_channel(policy(|b| {
+            b.reliable(true);
+            b.distribution(Distribution::RoundRobin);
+        }));
+
+        // First receiver drops the message and leaves when told.
+        let start = spawn_member(&mut master, &reliable, |start, reliable| {
+            start.send(reliable.receive().unwrap()).unwrap();
+            start.receive().unwrap();
+        });
+        for i in 1..3 {
+            reliable.send(Message::new(vec![i])).unwrap();
+        }
+        assert_eq!(start.receive().unwrap().data(), &[1]);
+        start.send(Message::new(vec![])).unwrap();
+        assert_eq!(start.receive(), Err(ChannelError::Disconnected));
+        assert_eq!(reliable.last_sequence(), Some(2));
+        assert_eq!(reliable.acknowledged(), None);
+
+        // Next receiver gets both messages exactly once.
+        let start = spawn_member(&mut master, &reliable, |start, reliable| {
+            while let Ok(msg) = reliable.receive() {
+                assert_eq!(reliable.acknowledge(), Ok(1));
+                start.send(msg).unwrap();
+            }
+        });
+        reliable.send(Message::new(vec![2]).with_sequence(2)).unwrap();
+        reliable.send(Message::new(vec![3])).unwrap();
+        for i in 1..4 {
+            let msg = start.receive().unwrap();
+            assert_eq!((msg.data(), msg.sequence()),
+                    (&[i][..], Some(u64::from(i))));
+        }
+        assert_eq!(reliable.acknowledged(), Some(3));
+    }
+}
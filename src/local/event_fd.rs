@@ -0,0 +1,173 @@
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::*;
+use super::runtime::{Registry, Runtime};
+
+/// File descriptor that becomes readable when receiving from
+/// the channel would not block. It can be added to the epoll, mio
+/// or other event loop which is not owned by the runtime.
+///
+/// Readiness is level-triggered until it gets
+/// [cleared](#method.clear). After the descriptor becomes readable
+/// the application clears it and takes the messages with
+/// [try_receive](../meta/trait.ChannelArchitecture.html#tymethod.try_receive)
+/// until none are left.
+///
+/// ```
+/// # use kobzar_ccs_usr::local::*;
+/// # use kobzar_ccs_usr::master::*;
+/// # use kobzar_ccs_usr::meta::*;
+/// let mut master = MyMaster::new();
+/// let entry = MyServiceEntry::new(|chan: &Channel| {
+///     let chan = MyMaster::current().unwrap().channel(chan).unwrap();
+///     while let Ok(msg) = chan.receive() {
+///         chan.send(msg).unwrap();
+///     }
+/// });
+/// let (_, chan) = master.spawn_thread(entry).unwrap();
+/// let fd = chan.event_fd().unwrap();
+/// assert!(!fd.is_ready());
+///
+/// chan.send(Message::new(vec![1])).unwrap();
+/// while !fd.is_ready() {
+///     std::thread::yield_now();
+/// }
+/// fd.clear();
+/// assert_eq!(chan.try_receive().unwrap().unwrap().data(), &[1]);
+/// ```
+pub struct MyEventFd {
+
+    /// Runtime the channel belongs to.
+    runtime: Arc<Runtime>,
+
+    /// ID of the thread that receives from the channel.
+    thread: usize,
+
+    /// Handle of the channel.
+    channel: Channel,
+
+    /// Signal shared with the runtime.
+    signal: Arc<ReadySignal>,
+
+    /// End of the socket that becomes readable.
+    reader: UnixStream,
+}
+
+/// Signal that makes the event descriptor readable.
+pub(crate) struct ReadySignal {
+
+    /// Whether the byte was written and not yet cleared.
+    raised: AtomicBool,
+
+    /// End of the socket the runtime writes to.
+    writer: UnixStream,
+}
+
+impl MyChannel {
+
+    /// Create the file descriptor that becomes readable when receiving
+    /// from this channel would not block.
+    pub fn event_fd(&self) -> io::Result<MyEventFd> {
+        let (reader, writer) = UnixStream::pair()?;
+        reader.set_nonblocking(true)?;
+        writer.set_nonblocking(true)?;
+
+        let signal = Arc::new(ReadySignal {
+            raised: AtomicBool::new(false),
+            writer,
+        });
+        let fd = MyEventFd {
+            runtime: self.runtime().clone(),
+            thread: self.thread_id(),
+            channel: self.handle().clone(),
+            signal: signal.clone(),
+            reader,
+        };
+
+        let mut reg = self.runtime().lock();
+        reg.signals.entry((fd.thread, fd.channel.id()))
+                .or_default()
+                .push(signal);
+        fd.raise_if_ready(&reg);
+        Ok(fd)
+    }
+}
+
+impl MyEventFd {
+
+    /// Whether the descriptor is readable now.
+    pub fn is_ready(&self) -> bool {
+        self.signal.raised.load(Ordering::Acquire)
+    }
+
+    /// Make the descriptor not readable. It becomes readable again
+    /// at once if receiving still would not block.
+    pub fn clear(&self) {
+        let mut buf = [0; 64];
+        while let Ok(n) = (&self.reader).read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+        }
+        self.signal.raised.store(false, Ordering::Release);
+
+        // Message may have arrived while the socket was drained.
+        let reg = self.runtime.lock();
+        self.raise_if_ready(&reg);
+    }
+
+    /// Raise the signal if receiving would not block. Closed channel
+    /// does not block either.
+    fn raise_if_ready(&self, reg: &Registry) {
+        let ready = match reg.channel_id(&self.channel) {
+            Ok(id) => reg.can_receive(self.thread, id),
+            Err(_) => true,
+        };
+        if ready {
+            self.signal.raise();
+        }
+    }
+}
+
+impl AsRawFd for MyEventFd {
+
+    fn as_raw_fd(&self) -> RawFd {
+        self.reader.as_raw_fd()
+    }
+}
+
+impl AsFd for MyEventFd {
+
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.reader.as_fd()
+    }
+}
+
+impl Drop for MyEventFd {
+
+    fn drop(&mut self) {
+        let mut reg = self.runtime.lock();
+        let key = (self.thread, self.channel.id());
+        if let Some(signals) = reg.signals.get_mut(&key) {
+            signals.retain(|signal| !Arc::ptr_eq(signal, &self.signal));
+            if signals.is_empty() {
+                reg.signals.remove(&key);
+            }
+        }
+    }
+}
+
+impl ReadySignal {
+
+    /// Make the descriptor readable. Byte is written only once until
+    /// the descriptor gets cleared.
+    pub fn raise(&self) {
+        if !self.raised.swap(true, Ordering::AcqRel) {
+            let _ = (&self.writer).write(&[1]);
+        }
+    }
+}
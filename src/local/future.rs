@@ -0,0 +1,204 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::task::{Context, Poll, Wake, Waker};
+
+use super::*;
+
+/// Future of joining the channel. See
+/// [join_async](struct.MyChannel.html#method.join_async).
+pub struct JoinFuture<'a> {
+
+    /// Channel to join.
+    channel: &'a MyChannel,
+}
+
+/// Future of sending the message. See
+/// [send_async](struct.MyChannel.html#method.send_async).
+pub struct SendFuture<'a> {
+
+    /// Channel to send the message to.
+    channel: &'a MyChannel,
+
+    /// Message to send. Taken when the future completes.
+    msg: Option<Message>,
+}
+
+/// Future of receiving the message. See
+/// [receive_async](struct.MyChannel.html#method.receive_async).
+pub struct ReceiveFuture<'a> {
+
+    /// Channel to receive the message from.
+    channel: &'a MyChannel,
+}
+
+/// Future of the change of channel members. See
+/// [members_changed](struct.MyChannel.html#method.members_changed).
+pub struct MembersFuture<'a> {
+
+    /// Channel which members are watched.
+    channel: &'a MyChannel,
+
+    /// Count of the members when the future was created.
+    count: usize,
+}
+
+/// Waker of the thread that runs [block_on](fn.block_on.html).
+struct ThreadWaker {
+
+    /// Whether the future was woken since it was last polled.
+    woken: Mutex<bool>,
+
+    /// Notified when the future gets woken.
+    changed: Condvar,
+}
+
+impl MyChannel {
+
+    /// Join the channel. Unlike blocking
+    /// [join](../meta/trait.ChannelArchitecture.html#tymethod.join)
+    /// the future waits while the service is not invited or
+    /// the channel has no room for more members.
+    pub fn join_async(&self) -> JoinFuture<'_> {
+        JoinFuture { channel: self }
+    }
+
+    /// Send the message to the other members of the channel. The future
+    /// waits while the thread is suspended or while some receiver
+    /// has no room for the message and the channel blocks on overflow.
+    pub fn send_async(&self, msg: Message) -> SendFuture<'_> {
+        SendFuture { channel: self, msg: Some(msg) }
+    }
+
+    /// Receive next message. The future waits until some message
+    /// arrives or until all other members leave the channel.
+    pub fn receive_async(&self) -> ReceiveFuture<'_> {
+        ReceiveFuture { channel: self }
+    }
+
+    /// Wait until some thread joins or leaves the channel. New count
+    /// of the members is returned. Error is returned if the channel
+    /// gets closed.
+    pub fn members_changed(&self) -> MembersFuture<'_> {
+        MembersFuture {
+            channel: self,
+            count: self.connections_count(),
+        }
+    }
+}
+
+impl<'a> Future for JoinFuture<'a> {
+
+    type Output = ChannelResult<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>)
+            -> Poll<ChannelResult<()>> {
+        self.channel.poll_join(cx)
+    }
+}
+
+impl<'a> Future for SendFuture<'a> {
+
+    type Output = ChannelResult<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>)
+            -> Poll<ChannelResult<()>> {
+        let result = {
+            let msg = self.msg.as_ref()
+                    .expect("future polled after completion");
+            self.channel.poll_send(cx, msg)
+        };
+        if result.is_ready() {
+            self.msg = None;
+        }
+        result
+    }
+}
+
+impl<'a> Future for ReceiveFuture<'a> {
+
+    type Output = ChannelResult<Message>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>)
+            -> Poll<ChannelResult<Message>> {
+        self.channel.poll_receive(cx)
+    }
+}
+
+impl<'a> Future for MembersFuture<'a> {
+
+    type Output = ChannelResult<usize>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>)
+            -> Poll<ChannelResult<usize>> {
+        self.channel.poll_members(cx, self.count)
+    }
+}
+
+impl Wake for ThreadWaker {
+
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        *self.woken.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        self.changed.notify_one();
+    }
+}
+
+/// Run the future on current thread until it completes. This is
+/// the simplest executor that blocks the thread while the future
+/// is pending. Futures of the channels do not depend on it and are
+/// woken only when the events of the channels they wait for may have
+/// appeared, so any executor can run them and thousands of channels
+/// can be served by a few threads. With `futures` feature channels
+/// also give streams and sinks of the messages.
+///
+/// This crate uses 2015 edition but the futures can be awaited
+/// by the services written in newer editions.
+///
+/// ```edition2021
+/// # use kobzar_ccs_usr::local::*;
+/// # use kobzar_ccs_usr::master::*;
+/// # use kobzar_ccs_usr::meta::*;
+/// let mut master = MyMaster::new();
+/// let entry = MyServiceEntry::new(|chan: &Channel| {
+///     let chan = MyMaster::current().unwrap().channel(chan).unwrap();
+///     block_on(async {
+///         while let Ok(msg) = chan.receive_async().await {
+///             chan.send_async(msg).await.unwrap();
+///         }
+///     });
+/// });
+/// let (_, chan) = master.spawn_thread(entry).unwrap();
+///
+/// let reply = block_on(async {
+///     chan.send_async(Message::from(&b"ping"[..])).await.unwrap();
+///     chan.receive_async().await
+/// });
+/// assert_eq!(reply.unwrap().data(), b"ping");
+/// ```
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let thread = Arc::new(ThreadWaker {
+        woken: Mutex::new(false),
+        changed: Condvar::new(),
+    });
+    let waker = Waker::from(thread.clone());
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+
+        let mut woken = thread.woken.lock()
+                .unwrap_or_else(PoisonError::into_inner);
+        while !*woken {
+            woken = thread.changed.wait(woken)
+                    .unwrap_or_else(PoisonError::into_inner);
+        }
+        *woken = false;
+    }
+}
@@ -0,0 +1,2029 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::*;
+use tools;
+use super::runtime::{self, Runtime, Registry, ThreadRecord};
+use super::runtime::{SupervisedKey, SupervisorRecord, MemoryOwner};
+
+/// Master of the local runtime. Each instance acts on behalf of some
+/// thread of the runtime. Master created by [new](#method.new) acts as
+/// the main thread of the application and masters of service threads
+/// can be acquired by [current](#method.current).
+///
+/// Threads are native threads of the system so they cannot be
+/// interrupted. Suspended thread stops at its next channel operation
+/// and cancelled thread gets
+/// [Cancelled](../meta/enum.ChannelError.html#variant.Cancelled) error
+/// instead of waiting for messages.
+///
+/// # Example
+/// ```
+/// # use kobzar_ccs_usr::local::*;
+/// # use kobzar_ccs_usr::master::*;
+/// # use kobzar_ccs_usr::meta::*;
+/// // Service that sends back all received messages.
+/// let mut echo = MyImplementer::new("org.example.echo").unwrap();
+/// echo.add_service("main", |chan: &Channel| {
+///     let master = MyMaster::current().unwrap();
+///     let chan = master.channel(chan).unwrap();
+///     while let Ok(msg) = chan.receive() {
+///         chan.send(msg).unwrap();
+///     }
+/// });
+///
+/// let mut source = MyImplementerSource::new();
+/// source.append(echo);
+///
+/// let mut master = MyMaster::new();
+/// master.add_implementer_source(source);
+///
+/// let path = Path::parse("org.example.echo.main").unwrap();
+/// let chan = master.request_service(ServiceRequest::Path(&path)).unwrap();
+/// chan.send(Message::from(&b"hello"[..])).unwrap();
+/// assert_eq!(chan.receive().unwrap().data(), b"hello");
+/// ```
+pub struct MyMaster {
+
+    /// Runtime this master belongs to.
+    runtime: Arc<Runtime>,
+
+    /// ID of the thread on behalf of which this master acts.
+    thread: usize,
+
+    /// Interface sources added to this master.
+    interface_sources: Vec<MyInterfaceSource>,
+
+    /// Implementer sources added to this master.
+    implementer_sources: Vec<MyImplementerSource>,
+}
+
+impl MyMaster {
+
+    /// Path of the object that represents the application itself.
+    pub const ROOT_PATH: &'static str = "app";
+
+    /// Name of the service which runs the main thread of the application.
+    pub const ROOT_SERVICE: &'static str = "main";
+
+    /// Create new runtime and the master of its main thread.
+    pub fn new() -> Self {
+        let mut root = MyImplementer::new(Self::ROOT_PATH).unwrap();
+        root.add_service(Self::ROOT_SERVICE, |_: &Channel| {});
+        let root = Arc::new(root);
+        let service = root.service_by_name(Self::ROOT_SERVICE).unwrap();
+
+        let runtime = Runtime::new();
+        let thread = {
+            let mut reg = runtime.lock();
+            reg.load(&root);
+            reg.objects.get_mut(&root.id()).unwrap()
+                    .state = ObjectState::Running;
+            let entry = reg.services[&service].entry.clone();
+            let thread = reg.thread_ids.allocate().0;
+            reg.add_thread(thread, ThreadRecord::new(service, entry, None));
+            thread
+        };
+
+        MyMaster {
+            runtime,
+            thread,
+            interface_sources: Default::default(),
+            implementer_sources: Default::default(),
+        }
+    }
+
+    /// Master of the thread that is currently running. Returns None
+    /// if current thread was not spawned by the local runtime.
+    ///
+    /// Interface sources are not shared between masters so the returned
+    /// master does not have any. Implementer sources are shared.
+    pub fn current() -> Option<Self> {
+        let (runtime, thread) = runtime::current()?;
+        Some(MyMaster {
+            runtime,
+            thread,
+            interface_sources: Default::default(),
+            implementer_sources: Default::default(),
+        })
+    }
+
+    /// Thread on behalf of which this master acts.
+    pub fn thread(&self) -> MyThread {
+        let mut reg = self.runtime.lock();
+        MyThread::new(self.runtime.clone(), &mut reg, self.thread)
+    }
+
+    /// Instance of the channel for the thread of this master.
+    /// None is returned if there is no such channel or the handle
+    /// is stale.
+    pub fn channel(&self, handle: &Channel) -> Option<MyChannel> {
+        let reg = self.runtime.lock();
+        let id = reg.channel_id(handle).ok()?;
+
+        let runtime = self.runtime.clone();
+        Some(MyChannel::new(runtime, &reg, self.thread, id))
+    }
+
+    /// Create new channel with given policy and connect the thread
+    /// of this master to it. Other threads join the channel by its handle
+    /// or get its descriptor passed with the message.
+    pub fn new_channel(&self, policy: Policy) -> MyChannel {
+        let mut reg = self.runtime.lock();
+        let origin = reg.threads[&self.thread].service;
+        let channel = reg.new_channel(origin, policy);
+        reg.connect(self.thread, channel);
+        MyChannel::new(self.runtime.clone(), &reg, self.thread, channel)
+    }
+
+    /// Memory of the loaded object. It counts the memory of all
+    /// the object threads.
+    pub fn object_memory(&self, object: &Object) -> ObjectResult<MyMemory> {
+        let id = self.runtime.lock().object_id(object)?;
+        let owner = MemoryOwner::Object(id);
+        Ok(MyMemory::new(self.runtime.clone(), owner))
+    }
+
+    /// IDs of the threads that wait for each other forever.
+    pub fn deadlocked_threads(&self) -> BTreeSet<usize> {
+        let reg = self.runtime.lock();
+        let waiting = reg.threads.iter()
+                .filter(|&(_, rec)| rec.state.deadline().is_none())
+                .filter_map(|(&id, rec)| {
+                    rec.state.wait_target().map(|target| (id, target.clone()))
+                })
+                .collect();
+        let members = reg.channels.iter()
+                .map(|(&id, rec)| (id, rec.members.keys().cloned().collect()))
+                .collect();
+        tools::deadlocked(&waiting, &members)
+    }
+
+    /// Spawn new thread of the service which runs given entry point.
+    /// New channel connects the thread with the thread of this master.
+    /// IDs of the thread and the channel are returned. Record of watched
+    /// thread is kept for its instance.
+    fn spawn(&self, service: usize, entry: MyServiceEntry, watched: bool)
+            -> Option<(usize, usize)> {
+        let channel = {
+            let mut reg = self.runtime.lock();
+            let origin = reg.threads[&self.thread].service;
+            let channel = reg.new_channel(origin, Self::request_policy());
+            reg.connect(self.thread, channel);
+            channel
+        };
+
+        match Runtime::spawn(&self.runtime, service, channel, entry,
+                watched) {
+            Some(thread) => Some((thread, channel)),
+            None => {
+                let mut reg = self.runtime.lock();
+                reg.disconnect(self.thread, channel);
+                reg.close(channel);
+                None
+            },
+        }
+    }
+
+    /// Record of the thread that is not finished yet.
+    fn running_thread<'a>(&self, reg: &'a mut Registry, thread: &Thread)
+            -> ThreadResult<&'a mut ThreadRecord> {
+        let id = reg.thread_id(thread)?;
+        let rec = reg.threads.get_mut(&id).unwrap();
+        if rec.state.is_finished() {
+            return Err(ThreadError::Finished);
+        }
+        Ok(rec)
+    }
+
+    /// Policy of the channels created on service requests.
+    fn request_policy() -> Policy {
+        let mut builder = PolicyBuilder::new();
+        builder.invitation_from_not_member(false);
+        builder.origin_can_leave(true);
+        builder.join_by_handle(false);
+        builder.no_multiple_connectons(true);
+        builder.build().unwrap()
+    }
+
+    /// Find the service with given path and the implementer of its
+    /// object. Implementers of loaded objects are checked first.
+    fn find_by_path(reg: &Registry, path: &Path)
+            -> ServiceRequestResult<(Arc<MyImplementer>, usize)> {
+        let path = path.to_string();
+        let loaded = reg.objects.values().map(|obj| &obj.implementer);
+        let sources = reg.implementer_sources.iter()
+                .filter_map(|src| src.find_service(&path));
+
+        loaded.chain(sources)
+                .filter_map(|imp| {
+                    imp.service_by_path(&path).map(|id| (imp.clone(), id))
+                })
+                .next()
+                .ok_or(ServiceRequestError::NotFound)
+    }
+
+    /// Find the service with given name of the object that implements
+    /// the interface. Loaded objects are checked first and then
+    /// implementer sources are asked.
+    fn find_by_interface(reg: &mut Registry, interface: &Interface,
+            name: &str)
+            -> ServiceRequestResult<(Arc<MyImplementer>, usize)> {
+        if !interface.services().contains(name) {
+            return Err(ServiceRequestError::NotDeclared);
+        }
+
+        let loaded = reg.objects.values()
+                .map(|obj| &obj.implementer)
+                .filter(|imp| imp.implements(interface))
+                .filter_map(|imp| {
+                    imp.service_by_name(name).map(|id| (imp.clone(), id))
+                })
+                .next();
+        if let Some(found) = loaded {
+            return Ok(found);
+        }
+
+        let mut interfaces = InterfaceSet::new();
+        interfaces.insert(interface.clone());
+        let interfaces = Shared::new(interfaces);
+
+        for src in reg.implementer_sources.iter_mut() {
+            let requirements = ImplementerRequirements::new(interfaces.clone());
+            let objects = src.lookup(requirements);
+            let found = objects.iter()
+                    .map(|obj| obj.implementer())
+                    .filter_map(|imp| {
+                        imp.service_by_name(name).map(|id| (imp.clone(), id))
+                    })
+                    .next();
+            if let Some(found) = found {
+                return Ok(found);
+            }
+        }
+
+        Err(ServiceRequestError::NoImplementer)
+    }
+
+    /// Make sure the object of given implementer is running. Object
+    /// gets created and started if it is not loaded yet.
+    fn ensure_running(&self, implementer: &Arc<MyImplementer>)
+            -> ServiceRequestResult<()> {
+        let state = self.runtime.lock().objects.get(&implementer.id())
+                .map(|obj| obj.state);
+        match state {
+            Some(ObjectState::Running) => Ok(()),
+            Some(_) => Err(ServiceRequestError::NotRunning),
+            None => {
+                self.create(implementer.clone())
+                        .and_then(|_| self.start(implementer.id()))
+                        .map_err(|_| ServiceRequestError::NotRunning)
+            },
+        }
+    }
+
+    /// Load and initialize the object of given implementer.
+    fn create(&self, implementer: Arc<MyImplementer>)
+            -> ObjectResult<MyObject> {
+        let generation = {
+            let mut reg = self.runtime.lock();
+            if reg.objects.contains_key(&implementer.id()) {
+                return Err(ObjectError::AlreadyLoaded);
+            }
+            reg.load(&implementer);
+            reg.switch(implementer.id(), ObjectState::Initializing)?;
+            reg.instances.generation(implementer.id()).unwrap()
+        };
+
+        let object = MyObject::with_generation(implementer, generation);
+        let result = object.on_initialize();
+        self.finish_hook(object.handle().id(), ObjectState::Stopped, result)?;
+        Ok(object)
+    }
+
+    /// Start stopped object.
+    fn start(&self, id: usize) -> ObjectResult<()> {
+        let implementer = {
+            let reg = self.runtime.lock();
+            let obj = reg.objects.get(&id).ok_or(ObjectError::NotFound)?;
+            obj.state.switch(ObjectState::Running)?;
+            obj.implementer.clone()
+        };
+
+        let result = MyObject::new(implementer).on_start();
+        self.finish_hook(id, ObjectState::Running, result)
+    }
+
+    /// Stop running object. Services are stopped one by one in reverse
+    /// order of their declaration. Threads must finish by given time
+    /// and within the deadline of the implementer if it has one. Object
+    /// fails if its threads do not finish in time.
+    fn stop(&self, id: usize, due: Option<Instant>) -> ObjectResult<()> {
+        let start = Instant::now();
+        let implementer = {
+            let mut reg = self.runtime.lock();
+            reg.switch(id, ObjectState::Stopping)?;
+            reg.objects[&id].implementer.clone()
+        };
+
+        let own = implementer.stop_deadline().map(|deadline| start + deadline);
+        let due = match (own, due) {
+            (Some(own), Some(due)) => Some(own.min(due)),
+            (own, due) => own.or(due),
+        };
+        let mut in_time = true;
+        for &(service, _, _) in implementer.services().iter().rev() {
+            in_time &= self.stop_service(service, due);
+        }
+        if !in_time {
+            self.runtime.lock().switch(id, ObjectState::Failed)?;
+            return Err(ObjectError::DeadlineExceeded);
+        }
+
+        MyObject::new(implementer).on_stop();
+        self.runtime.lock().switch(id, ObjectState::Stopped)
+    }
+
+    /// Stop the threads of the service. They get cancelled and other
+    /// members of their channels get shutdown notice. When the threads
+    /// finish or the time is due, threads that still run get
+    /// disconnected and channels originated by the service get closed.
+    /// Returns whether all the threads finished in time. Thread of this
+    /// master may belong to the service so it is not waited for.
+    fn stop_service(&self, service: usize, due: Option<Instant>) -> bool {
+        let mut reg = self.runtime.lock();
+        let threads: Vec<usize> = reg.threads.iter()
+                .filter(|&(_, rec)| rec.service == service)
+                .filter(|&(_, rec)| !rec.state.is_finished())
+                .map(|(&id, _)| id)
+                .collect();
+        for &thread in &threads {
+            reg.notify_peers(thread, ChannelNotice::Shutdown(thread));
+            reg.cancel(thread);
+        }
+        self.runtime.notify(reg);
+
+        let mut reg = self.runtime.lock();
+        let mut in_time = true;
+        loop {
+            let running = threads.iter()
+                    .filter(|&&thr| thr != self.thread)
+                    .any(|thr| {
+                        reg.threads.get(thr)
+                                .is_some_and(|rec| !rec.state.is_finished())
+                    });
+            if !running {
+                break;
+            }
+
+            reg = match due {
+                Some(due) => {
+                    let now = Instant::now();
+                    if now >= due {
+                        in_time = false;
+                        break;
+                    }
+                    self.runtime.wait_timeout(reg, due - now).0
+                },
+                None => self.runtime.wait(reg),
+            };
+        }
+
+        for &thread in &threads {
+            let channels = match reg.threads.get(&thread) {
+                Some(rec) => rec.channels.clone(),
+                None => continue,
+            };
+            for channel in channels {
+                reg.disconnect(thread, channel);
+            }
+        }
+        let channels: Vec<usize> = reg.channels.iter()
+                .filter(|&(_, rec)| rec.origin == service)
+                .map(|(&id, _)| id)
+                .collect();
+        for channel in channels {
+            reg.close(channel);
+        }
+        self.runtime.notify(reg);
+        in_time
+    }
+
+    /// Current state of the object.
+    fn object_state_locked(&self, reg: &Registry, id: usize)
+            -> ObjectResult<ObjectState> {
+        reg.objects.get(&id)
+                .map(|obj| obj.state)
+                .ok_or(ObjectError::NotFound)
+    }
+
+    /// Switch the object state after the hook. If hook failed
+    /// the object fails.
+    fn finish_hook(&self, id: usize, to: ObjectState, result: ObjectResult<()>)
+            -> ObjectResult<()> {
+        let mut reg = self.runtime.lock();
+        match result {
+            Ok(_) => reg.switch(id, to),
+            Err(_) => {
+                reg.switch(id, ObjectState::Failed)?;
+                Err(ObjectError::HookFailed)
+            },
+        }
+    }
+}
+
+impl Default for MyMaster {
+
+    fn default() -> Self {
+        MyMaster::new()
+    }
+}
+
+impl Master for MyMaster {
+
+    type Chan = MyChannel;
+
+    type IntSrc = MyInterfaceSource;
+
+    type ImpSrc = MyImplementerSource;
+
+    type Obj = MyObject;
+
+    type Srv = MyService;
+
+    type Thr = MyThread;
+
+    fn add_interface_source(&mut self, isrc: MyInterfaceSource)
+            -> &MyInterfaceSource {
+        self.interface_sources.push(isrc);
+        self.interface_sources.last().unwrap()
+    }
+
+    fn add_implementer_source(&mut self, isrc: MyImplementerSource)
+            -> &MyImplementerSource {
+        self.runtime.lock().implementer_sources.push(isrc.clone());
+        self.implementer_sources.push(isrc);
+        self.implementer_sources.last().unwrap()
+    }
+
+    fn request_service(&mut self, request: ServiceRequest)
+            -> ServiceRequestResult<MyChannel> {
+        let (implementer, service) = {
+            let mut reg = self.runtime.lock();
+            match request {
+                ServiceRequest::Path(path) => {
+                    Self::find_by_path(&reg, path)?
+                },
+                ServiceRequest::Interface { interface, name } => {
+                    Self::find_by_interface(&mut reg, interface, name)?
+                },
+            }
+        };
+        self.ensure_running(&implementer)?;
+
+        let entry = self.runtime.lock().services[&service].entry.clone();
+        let (_, channel) = self.spawn(service, entry, false)
+                .ok_or(ServiceRequestError::SpawnFailed)?;
+
+        let reg = self.runtime.lock();
+        Ok(MyChannel::new(self.runtime.clone(), &reg, self.thread, channel))
+    }
+
+    fn create_object(&mut self, path: &Path) -> ObjectResult<MyObject> {
+        let path = path.to_string();
+        let implementer = self.runtime.lock().implementer_sources.iter()
+                .filter_map(|src| src.find_object(&path))
+                .next()
+                .cloned();
+
+        match implementer {
+            Some(imp) => self.create(imp),
+            None => Err(ObjectError::NotFound),
+        }
+    }
+
+    fn start_object(&mut self, object: &Object) -> ObjectResult<()> {
+        let id = self.runtime.lock().object_id(object)?;
+        self.start(id)
+    }
+
+    fn stop_object(&mut self, object: &Object) -> ObjectResult<()> {
+        let id = self.runtime.lock().object_id(object)?;
+        self.stop(id, None)
+    }
+
+    fn destroy_object(&mut self, object: &Object) -> ObjectResult<()> {
+        let mut reg = self.runtime.lock();
+        let id = reg.object_id(object)?;
+        let state = self.object_state_locked(&reg, id)?;
+        if state != ObjectState::Stopped && state != ObjectState::Failed {
+            return Err(ObjectError::InvalidTransition {
+                from: state,
+                to: ObjectState::Stopped,
+            });
+        }
+
+        reg.unload(id);
+        self.runtime.notify(reg);
+        Ok(())
+    }
+
+    fn object_state(&self, object: &Object) -> ObjectResult<ObjectState> {
+        let reg = self.runtime.lock();
+        let id = reg.object_id(object)?;
+        self.object_state_locked(&reg, id)
+    }
+
+    fn shutdown(&mut self, deadline: Duration) -> ShutdownReport {
+        let order = {
+            let reg = self.runtime.lock();
+            let running: Vec<&Arc<MyImplementer>> = reg.objects.values()
+                    .filter(|obj| obj.state == ObjectState::Running)
+                    .map(|obj| &obj.implementer)
+                    .filter(|imp| imp.path() != Self::ROOT_PATH)
+                    .collect();
+
+            let mut deps = BTreeMap::new();
+            for imp in &running {
+                let imp_deps: BTreeSet<usize> = running.iter()
+                        .filter(|other| imp.depends_on(other))
+                        .map(|other| other.id())
+                        .collect();
+                deps.insert(imp.id(), imp_deps);
+            }
+            tools::stop_order(&deps)
+        };
+
+        let due = Instant::now() + deadline;
+        let mut report = ShutdownReport::new();
+        for id in order {
+            let result = self.stop(id, Some(due));
+
+            let reg = self.runtime.lock();
+            let object = reg.object_handle(id).as_ref().clone();
+            match result {
+                Ok(_) => report.add_stopped(object),
+                Err(_) => {
+                    let threads = reg.object_threads(id).into_iter()
+                            .map(|thr| reg.thread_handle(thr))
+                            .collect();
+                    report.add_overdue(object, threads);
+                },
+            }
+        }
+        report
+    }
+
+    fn supervise(&mut self, target: SupervisionTarget,
+            supervisor: Supervisor) -> ObjectResult<()> {
+        let mut reg = self.runtime.lock();
+        let key = match target {
+            SupervisionTarget::Object(obj) => {
+                SupervisedKey::Object(reg.object_id(obj)?)
+            },
+            SupervisionTarget::Service(srv) => {
+                SupervisedKey::Service(reg.service_id(srv)?)
+            },
+        };
+
+        reg.supervisors.insert(key, SupervisorRecord {
+            supervisor,
+            restarts: Default::default(),
+        });
+        Ok(())
+    }
+
+    fn spawn_thread(&mut self, entry: MyServiceEntry)
+            -> ThreadResult<(MyThread, MyChannel)> {
+        let service = self.runtime.lock().threads[&self.thread].service;
+        let (thread, channel) = self.spawn(service, entry, true)
+                .ok_or(ThreadError::SpawnFailed)?;
+
+        let reg = self.runtime.lock();
+        let chan = MyChannel::new(self.runtime.clone(), &reg, self.thread,
+                channel);
+        Ok((MyThread::watched(self.runtime.clone(), &reg, thread), chan))
+    }
+
+    fn join_thread(&mut self, thread: &Thread, timeout: Option<Duration>)
+            -> ThreadResult<ThreadState> {
+        if thread.id() == self.thread {
+            return Err(ThreadError::OwnThread);
+        }
+
+        let start = Instant::now();
+        let mut reg = self.runtime.lock();
+        loop {
+            let id = reg.thread_id(thread)?;
+            let state = reg.threads[&id].state.clone();
+            if state.is_finished() {
+                return Ok(state);
+            }
+
+            reg = match timeout {
+                Some(timeout) => {
+                    let passed = start.elapsed();
+                    if passed >= timeout {
+                        return Err(ThreadError::TimedOut);
+                    }
+                    self.runtime.wait_timeout(reg, timeout - passed).0
+                },
+                None => self.runtime.wait(reg),
+            };
+        }
+    }
+
+    fn cancel_thread(&mut self, thread: &Thread) -> ThreadResult<()> {
+        let mut reg = self.runtime.lock();
+        self.running_thread(&mut reg, thread)?;
+        reg.cancel(thread.id());
+        self.runtime.notify(reg);
+        Ok(())
+    }
+
+    fn suspend_thread(&mut self, thread: &Thread) -> ThreadResult<()> {
+        if thread.id() == self.thread {
+            return Err(ThreadError::OwnThread);
+        }
+
+        let mut reg = self.runtime.lock();
+        self.running_thread(&mut reg, thread)?.suspended = true;
+        reg.touch_thread(thread.id());
+        self.runtime.notify(reg);
+        Ok(())
+    }
+
+    fn resume_thread(&mut self, thread: &Thread) -> ThreadResult<()> {
+        let mut reg = self.runtime.lock();
+        self.running_thread(&mut reg, thread)?.suspended = false;
+        reg.touch_thread(thread.id());
+        self.runtime.notify(reg);
+        Ok(())
+    }
+
+    fn threads(&self) -> Vec<MyThread> {
+        let mut reg = self.runtime.lock();
+        let ids: Vec<usize> = reg.threads.keys().cloned().collect();
+        ids.into_iter()
+                .map(|id| MyThread::new(self.runtime.clone(), &mut reg, id))
+                .collect()
+    }
+
+    fn thread_dump(&self) -> ThreadDump {
+        let reg = self.runtime.lock();
+        let threads = reg.threads.iter()
+                .map(|(&id, rec)| {
+                    ThreadInfo::new(id, reg.service_path(rec.service),
+                            rec.state.clone(), rec.channels.clone())
+                })
+                .collect();
+        ThreadDump::new(threads)
+    }
+
+    fn service_iter(&self, mode: IterMode) -> ServiceIterator<'_> {
+        HandleIterator::new(self, mode)
+    }
+
+    fn channel_iter(&self, mode: IterMode) -> ChannelIterator<'_> {
+        HandleIterator::new(self, mode)
+    }
+}
+
+impl HandleSource<Service> for MyMaster {
+
+    fn handles(&self) -> Shared<BTreeMap<usize, Service>> {
+        let reg = self.runtime.lock();
+        let map = reg.services.keys()
+                .map(|&id| (id, (*reg.service_handle(id)).clone()))
+                .collect();
+        Shared::new(map)
+    }
+
+    fn find(&self, range: HandleRange, from_back: bool)
+            -> Option<(usize, Service)> {
+        let reg = self.runtime.lock();
+        let mut ids = reg.services.range(range).map(|(&id, _)| id);
+        let id = if from_back { ids.next_back()? } else { ids.next()? };
+        Some((id, (*reg.service_handle(id)).clone()))
+    }
+}
+
+impl HandleSource<Channel> for MyMaster {
+
+    fn handles(&self) -> Shared<BTreeMap<usize, Channel>> {
+        let reg = self.runtime.lock();
+        let map = reg.channels.keys()
+                .map(|&id| (id, reg.channel_handle(id)))
+                .collect();
+        Shared::new(map)
+    }
+
+    fn find(&self, range: HandleRange, from_back: bool)
+            -> Option<(usize, Channel)> {
+        let reg = self.runtime.lock();
+        let mut ids = reg.channels.range(range).map(|(&id, _)| id);
+        let id = if from_back { ids.next_back()? } else { ids.next()? };
+        Some((id, reg.channel_handle(id)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::collections::BTreeSet;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Context, Wake, Waker};
+
+    fn echo(chan: &Channel) {
+        let master = MyMaster::current().unwrap();
+        let chan = master.channel(chan).unwrap();
+        while let Ok(msg) = chan.receive() {
+            chan.send(msg).unwrap();
+        }
+    }
+
+    fn interface() -> Interface {
+        let mut services = BTreeSet::new();
+        services.insert("echo".to_string());
+
+        let vendor = Path::parse("org.example.echo").unwrap();
+        Interface::new(vendor, Version::new(1, 0, 0),
+                services, Default::default())
+    }
+
+    fn master() -> MyMaster {
+        let mut implementer = MyImplementer::new("org.example.impl").unwrap();
+        implementer.implement(&interface());
+        implementer.add_service("echo", echo);
+
+        let mut source = MyImplementerSource::new();
+        source.append(implementer);
+
+        let mut master = MyMaster::new();
+        master.add_implementer_source(source);
+        master
+    }
+
+    #[test]
+    fn request_by_interface() {
+        let mut master = master();
+        let interface = interface();
+        let request = ServiceRequest::Interface {
+            interface: &interface,
+            name: "echo",
+        };
+        let chan = master.request_service(request).unwrap();
+
+        chan.send(Message::new(vec![1, 2, 3])).unwrap();
+        assert_eq!(chan.receive().unwrap().data(), &[1, 2, 3]);
+        assert_eq!(chan.connections_count(), 2);
+
+        let request = ServiceRequest::Interface {
+            interface: &interface,
+            name: "unknown",
+        };
+        let result = master.request_service(request);
+        assert_eq!(result.err(), Some(ServiceRequestError::NotDeclared));
+    }
+
+    #[test]
+    fn service_finish_disconnects() {
+        let mut master = master();
+        let path = Path::parse("org.example.impl.echo").unwrap();
+        let chan = master.request_service(ServiceRequest::Path(&path))
+                .unwrap();
+
+        let service = chan.handle().origin().path().to_string();
+        assert_eq!(service, "app.main");
+
+        chan.leave().unwrap();
+        assert_eq!(chan.receive(), Err(ChannelError::NotMember));
+
+        let path = Path::parse("org.example.impl.none").unwrap();
+        let result = master.request_service(ServiceRequest::Path(&path));
+        assert_eq!(result.err(), Some(ServiceRequestError::NotFound));
+    }
+
+    #[test]
+    fn registry_iterators() {
+        let mut master = master();
+        let path = Path::parse("org.example.impl.echo").unwrap();
+        let first = master.request_service(ServiceRequest::Path(&path))
+                .unwrap();
+        let services: Vec<String> = master.service_iter(IterMode::Live)
+                .map(|srv| srv.name().to_string())
+                .collect();
+        assert!(services.contains(&"echo".to_string()));
+
+        let snapshot = master.channel_iter(IterMode::Snapshot)
+                .into_snapshot().unwrap();
+        let mut live = master.channel_iter(IterMode::Live);
+        assert_eq!(live.next().map(|chan| chan.id()),
+                Some(first.handle().id()));
+
+        // Live iterator sees the channel created after it has started.
+        let second = master.new_channel(MyMaster::request_policy());
+        assert_eq!(live.next().map(|chan| chan.id()),
+                Some(second.handle().id()));
+        assert!(live.next().is_none());
+        assert_eq!(snapshot.len(), 1);
+    }
+
+    #[test]
+    fn object_lifecycle() {
+        let mut master = master();
+        let path = Path::parse("org.example.impl").unwrap();
+        let object = master.create_object(&path).unwrap();
+        let object = object.handle();
+        assert_eq!(master.object_state(object), Ok(ObjectState::Stopped));
+
+        let service = Path::parse("org.example.impl.echo").unwrap();
+        let result = master.request_service(ServiceRequest::Path(&service));
+        assert_eq!(result.err(), Some(ServiceRequestError::NotRunning));
+
+        master.start_object(object).unwrap();
+        let result = master.start_object(object);
+        assert_eq!(result, Err(ObjectError::InvalidTransition {
+            from: ObjectState::Running,
+            to: ObjectState::Running,
+        }));
+
+        let chan = master.request_service(ServiceRequest::Path(&service))
+                .unwrap();
+        let result = master.destroy_object(object);
+        assert!(result.is_err());
+
+        master.stop_object(object).unwrap();
+        match chan.receive() {
+            Err(ChannelError::Notice(ChannelNotice::Shutdown(_))) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(chan.receive(), Err(ChannelError::Disconnected));
+
+        master.destroy_object(object).unwrap();
+        assert_eq!(master.object_state(object),
+                Err(ObjectError::StaleHandle));
+    }
+
+    #[test]
+    fn stop_order() {
+        use std::sync::Mutex;
+        use std::thread;
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut implementer = MyImplementer::new("org.example.pair").unwrap();
+        for &name in &["first", "second"] {
+            let log = log.clone();
+            implementer.add_service(name, move |chan: &Channel| {
+                let chan = MyMaster::current().unwrap().channel(chan)
+                        .unwrap();
+                while chan.receive().is_ok() {}
+
+                // Later service takes its time to drain.
+                if name == "second" {
+                    thread::sleep(Duration::from_millis(20));
+                }
+                log.lock().unwrap().push(name);
+            });
+        }
+        let mut source = MyImplementerSource::new();
+        source.append(implementer);
+        let mut master = MyMaster::new();
+        master.add_implementer_source(source);
+        let path = Path::parse("org.example.pair").unwrap();
+        let object = master.create_object(&path).unwrap().handle().clone();
+        master.start_object(&object).unwrap();
+
+        let mut chans = Vec::new();
+        for name in &["first", "second"] {
+            let path = format!("org.example.pair.{}", name);
+            let path = Path::parse(&path).unwrap();
+            chans.push(master.request_service(ServiceRequest::Path(&path))
+                    .unwrap());
+        }
+
+        // Later declared service finishes before the earlier one
+        // is told to stop.
+        master.stop_object(&object).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["second", "first"]);
+        for chan in &chans {
+            match chan.receive() {
+                Err(ChannelError::Notice(ChannelNotice::Shutdown(_))) => (),
+                other => panic!("unexpected {:?}", other),
+            }
+            assert_eq!(chan.receive(), Err(ChannelError::Disconnected));
+        }
+    }
+
+    #[test]
+    fn stale_handles() {
+        let mut master = master();
+        let path = Path::parse("org.example.impl").unwrap();
+        let old = master.create_object(&path).unwrap().handle().clone();
+        master.destroy_object(&old).unwrap();
+
+        // Object loaded again has the same ID but the next generation.
+        let new = master.create_object(&path).unwrap().handle().clone();
+        assert_eq!(new.id(), old.id());
+        assert_eq!(master.start_object(&old), Err(ObjectError::StaleHandle));
+        master.start_object(&new).unwrap();
+
+        let service = Path::parse("org.example.impl.echo").unwrap();
+        let chan = master.request_service(ServiceRequest::Path(&service))
+                .unwrap();
+        let handle = chan.handle().clone();
+        chan.leave().unwrap();
+        master.runtime.lock().close(handle.id());
+
+        // Closed channel ID gets reused by the next channel.
+        let next = master.request_service(ServiceRequest::Path(&service))
+                .unwrap();
+        assert_eq!(next.handle().id(), handle.id());
+        assert!(master.channel(&handle).is_none());
+        assert_eq!(chan.send(Message::new(vec![])),
+                Err(ChannelError::StaleHandle));
+        next.send(Message::from(&b"ping"[..])).unwrap();
+        assert_eq!(next.receive().unwrap().data(), b"ping");
+    }
+
+    #[test]
+    fn failed_hook() {
+        let mut implementer = MyImplementer::new("org.example.bad").unwrap();
+        implementer.set_start_hook(|| Err(ObjectError::HookFailed));
+
+        let mut source = MyImplementerSource::new();
+        source.append(implementer);
+
+        let mut master = MyMaster::new();
+        master.add_implementer_source(source);
+
+        let path = Path::parse("org.example.bad").unwrap();
+        let object = master.create_object(&path).unwrap();
+        let result = master.start_object(object.handle());
+        assert_eq!(result, Err(ObjectError::HookFailed));
+
+        let state = master.object_state(object.handle());
+        assert_eq!(state, Ok(ObjectState::Failed));
+        master.destroy_object(object.handle()).unwrap();
+    }
+
+    #[test]
+    fn shutdown_order() {
+        use std::sync::Mutex;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::thread;
+
+        // Stuck object is stopped last so others stop within the budget.
+        let base = Interface::new(Path::parse("org.example.base").unwrap(),
+                Version::new(1, 0, 0), Default::default(), Default::default());
+        let mut services = BTreeSet::new();
+        services.insert("echo".to_string());
+        let mut deps = BTreeSet::new();
+        deps.insert(Shared::new(base.clone()));
+        let storage = Interface::new(Path::parse("org.example.echo").unwrap(),
+                Version::new(1, 0, 0), services.clone(), deps);
+        let mut deps = BTreeSet::new();
+        deps.insert(Shared::new(storage.clone()));
+        let player = Interface::new(Path::parse("org.example.player").unwrap(),
+                Version::new(1, 0, 0), services, deps);
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let release = Arc::new(AtomicBool::new(false));
+
+        let mut source = MyImplementerSource::new();
+        for &(path, int) in &[("storage", &storage), ("player", &player)] {
+            let mut imp = MyImplementer::new(path).unwrap();
+            imp.implement(int);
+            imp.add_service("echo", echo);
+            let log = log.clone();
+            imp.set_stop_hook(move || log.lock().unwrap().push(path));
+            source.append(imp);
+        }
+        let mut stuck = MyImplementer::new("stuck").unwrap();
+        stuck.implement(&base);
+        let flag = release.clone();
+        stuck.add_service("main", move |_: &Channel| {
+            while !flag.load(Ordering::SeqCst) {
+                thread::yield_now();
+            }
+        });
+        source.append(stuck);
+
+        let mut master = MyMaster::new();
+        master.add_implementer_source(source);
+
+        let mut chans = Vec::new();
+        for path in &["storage.echo", "player.echo", "stuck.main"] {
+            let path = Path::parse(path).unwrap();
+            chans.push(master.request_service(ServiceRequest::Path(&path))
+                    .unwrap());
+        }
+
+        let report = master.shutdown(Duration::from_millis(50));
+        assert_eq!(*log.lock().unwrap(), vec!["player", "storage"]);
+        assert_eq!(report.stopped().len(), 2);
+        assert_eq!(report.overdue().len(), 1);
+        assert_eq!(report.overdue()[0].name(), "stuck");
+        assert_eq!(report.overdue_threads().len(), 1);
+
+        match chans[0].receive() {
+            Err(ChannelError::Notice(ChannelNotice::Shutdown(_))) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(chans[0].receive(), Err(ChannelError::Disconnected));
+        release.store(true, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn shutdown_drains_dependents() {
+        use std::sync::Mutex;
+        use std::sync::atomic::AtomicBool;
+
+        let storage = interface();
+        let mut services = BTreeSet::new();
+        services.insert("main".to_string());
+        let mut deps = BTreeSet::new();
+        deps.insert(Shared::new(storage.clone()));
+        let player = Interface::new(Path::parse("org.example.player").unwrap(),
+                Version::new(1, 0, 0), services, deps);
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut store = MyImplementer::new("storage").unwrap();
+        store.implement(&storage);
+        let store_log = log.clone();
+        store.add_service("echo", move |chan: &Channel| {
+            let chan = MyMaster::current().unwrap().channel(chan).unwrap();
+            loop {
+                let entry = match chan.receive() {
+                    Ok(msg) => msg.data().to_vec(),
+                    Err(ChannelError::Notice(_)) => b"notice".to_vec(),
+                    Err(_) => break,
+                };
+                store_log.lock().unwrap().push(entry);
+            }
+        });
+        let flushed = Arc::new(AtomicBool::new(false));
+        let mut play = MyImplementer::new("player").unwrap();
+        play.implement(&player);
+        let play_flushed = flushed.clone();
+        play.add_service("main", move |chan: &Channel| {
+            let mut master = MyMaster::current().unwrap();
+            let chan = master.channel(chan).unwrap();
+            let path = Path::parse("storage.echo").unwrap();
+            let store = master.request_service(ServiceRequest::Path(&path))
+                    .unwrap();
+            chan.send(Message::new(vec![])).unwrap();
+            while chan.receive().is_ok() {}
+
+            // Dependency still serves while this object stops.
+            if store.send(Message::from(&b"flush"[..])).is_ok() {
+                play_flushed.store(true, Ordering::SeqCst);
+            }
+        });
+        let mut source = MyImplementerSource::new();
+        source.append(store);
+        source.append(play);
+
+        let mut master = MyMaster::new();
+        master.add_implementer_source(source);
+        let path = Path::parse("player.main").unwrap();
+        let chan = master.request_service(ServiceRequest::Path(&path))
+                .unwrap();
+        chan.receive().unwrap();
+
+        assert!(master.shutdown(Duration::from_secs(5)).is_clean());
+        match chan.receive() {
+            Err(ChannelError::Notice(ChannelNotice::Shutdown(_))) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(flushed.load(Ordering::SeqCst));
+        assert_eq!(*log.lock().unwrap(),
+                vec![b"notice".to_vec(), b"flush".to_vec()]);
+    }
+
+    #[test]
+    fn unload_running_thread() {
+        use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+        use std::thread;
+
+        let release = Arc::new(AtomicBool::new(false));
+        let stuck_id = Arc::new(AtomicUsize::new(0));
+        let mut stuck = MyImplementer::new("stuck").unwrap();
+        let (flag, id) = (release.clone(), stuck_id.clone());
+        stuck.add_service("main", move |_: &Channel| {
+            let thread = MyMaster::current().unwrap().thread();
+            id.store(thread.unarch().id(), Ordering::SeqCst);
+            while !flag.load(Ordering::SeqCst) {
+                thread::yield_now();
+            }
+        });
+        stuck.set_stop_deadline(Duration::from_millis(20));
+        let mut source = MyImplementerSource::new();
+        source.append(stuck);
+
+        let mut master = MyMaster::new();
+        master.add_implementer_source(source);
+        let path = Path::parse("stuck").unwrap();
+        let object = master.create_object(&path).unwrap().handle().clone();
+        master.start_object(&object).unwrap();
+        let path = Path::parse("stuck.main").unwrap();
+        master.request_service(ServiceRequest::Path(&path)).unwrap();
+        while stuck_id.load(Ordering::SeqCst) == 0 {
+            thread::yield_now();
+        }
+
+        // Thread still runs after its object is gone.
+        let result = master.stop_object(&object);
+        assert_eq!(result, Err(ObjectError::DeadlineExceeded));
+        master.destroy_object(&object).unwrap();
+
+        // Its ID is not given to other thread until it exits.
+        let (thread, chan) = master.spawn_thread(MyServiceEntry::new(echo))
+                .unwrap();
+        assert_ne!(thread.unarch().id(), stuck_id.load(Ordering::SeqCst));
+        release.store(true, Ordering::SeqCst);
+        while !master.runtime.lock().detached.is_empty() {
+            thread::yield_now();
+        }
+
+        chan.send(Message::from(&b"ping"[..])).unwrap();
+        assert_eq!(chan.receive().unwrap().data(), b"ping");
+        assert!(!thread.state().is_finished());
+    }
+
+    #[test]
+    fn supervised_restart() {
+        let mut master = fragile_master();
+        let path = Path::parse("org.example.fragile.main").unwrap();
+        let chan = master.request_service(ServiceRequest::Path(&path))
+                .unwrap();
+
+        let object = Path::parse("org.example.fragile").unwrap();
+        let id = Id::new(object_id(&master), 0);
+        let object = Shared::new(Object::new(object, id));
+        let id = Id::new(service_id(&master), 0);
+        let service = Service::new(object, "main", id).unwrap();
+        let mut supervisor = Supervisor::new(RestartStrategy::OneForOne);
+        supervisor.set_intensity(1, Duration::from_secs(60));
+        master.supervise(SupervisionTarget::Service(&service), supervisor)
+                .unwrap();
+
+        chan.send(Message::from(&b"crash"[..])).unwrap();
+        match chan.receive() {
+            Err(ChannelError::Notice(ChannelNotice::PeerFault(_))) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        match chan.receive() {
+            Err(ChannelError::Notice(ChannelNotice::Restarted { .. })) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        chan.send(Message::from(&b"ping"[..])).unwrap();
+        assert_eq!(chan.receive().unwrap().data(), b"ping");
+
+        // Intensity is exceeded so the service is not restarted.
+        chan.send(Message::from(&b"crash"[..])).unwrap();
+        match chan.receive() {
+            Err(ChannelError::Notice(ChannelNotice::PeerFault(_))) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(chan.receive(), Err(ChannelError::Disconnected));
+    }
+
+    #[test]
+    fn rest_for_one_restart() {
+        let mut master = fragile_master();
+        let mut implementer = MyImplementer::new("org.example.impl").unwrap();
+        implementer.add_service("echo", echo);
+        let mut source = MyImplementerSource::new();
+        source.append(implementer);
+        master.add_implementer_source(source);
+
+        // Threads of the unloaded object leave free IDs behind so later
+        // thread gets lower ID.
+        let echo = Path::parse("org.example.impl").unwrap();
+        let object = master.create_object(&echo).unwrap().handle().clone();
+        master.start_object(&object).unwrap();
+        let service = Path::parse("org.example.impl.echo").unwrap();
+        for _ in 0..2 {
+            master.request_service(ServiceRequest::Path(&service)).unwrap();
+        }
+        let path = Path::parse("org.example.fragile.main").unwrap();
+        let first = master.request_service(ServiceRequest::Path(&path))
+                .unwrap();
+        master.stop_object(&object).unwrap();
+        master.destroy_object(&object).unwrap();
+        let second = master.request_service(ServiceRequest::Path(&path))
+                .unwrap();
+
+        let object = Path::parse("org.example.fragile").unwrap();
+        let id = Id::new(object_id(&master), 0);
+        let object = Shared::new(Object::new(object, id));
+        let id = Id::new(service_id(&master), 0);
+        let service = Service::new(object, "main", id).unwrap();
+        let supervisor = Supervisor::new(RestartStrategy::RestForOne);
+        master.supervise(SupervisionTarget::Service(&service), supervisor)
+                .unwrap();
+
+        // Thread started after the failed one is restarted too.
+        first.send(Message::from(&b"crash"[..])).unwrap();
+        match first.receive() {
+            Err(ChannelError::Notice(ChannelNotice::PeerFault(_))) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        for chan in &[&first, &second] {
+            match chan.receive_timeout(Duration::from_secs(5)) {
+                Err(ChannelError::Notice(ChannelNotice::Restarted {
+                    ..
+                })) => (),
+                other => panic!("unexpected {:?}", other),
+            }
+            chan.send(Message::from(&b"ping"[..])).unwrap();
+            assert_eq!(chan.receive().unwrap().data(), b"ping");
+        }
+    }
+
+    #[test]
+    fn stubborn_sibling_restart() {
+        use std::sync::atomic::AtomicBool;
+        use std::thread;
+
+        let mut master = MyMaster::new();
+        let release = Arc::new(AtomicBool::new(false));
+        let starts = Arc::new(AtomicUsize::new(0));
+        let mut implementer = MyImplementer::new("org.example.pair").unwrap();
+        implementer.add_service("fragile", |chan: &Channel| {
+            let chan = MyMaster::current().unwrap().channel(chan).unwrap();
+            if chan.receive().is_ok() {
+                panic!("crash requested");
+            }
+        });
+        let (flag, count) = (release.clone(), starts.clone());
+        implementer.add_service("stubborn", move |_: &Channel| {
+            // Thread never checks whether it was cancelled.
+            count.fetch_add(1, Ordering::SeqCst);
+            while !flag.load(Ordering::SeqCst) {
+                thread::yield_now();
+            }
+        });
+        let mut source = MyImplementerSource::new();
+        source.append(implementer);
+        master.add_implementer_source(source);
+
+        let path = Path::parse("org.example.pair").unwrap();
+        let object = master.create_object(&path).unwrap().handle().clone();
+        master.start_object(&object).unwrap();
+        let mut supervisor = Supervisor::new(RestartStrategy::OneForAll);
+        supervisor.set_shutdown(Duration::from_millis(20));
+        master.supervise(SupervisionTarget::Object(&object), supervisor)
+                .unwrap();
+
+        let path = Path::parse("org.example.pair.stubborn").unwrap();
+        master.request_service(ServiceRequest::Path(&path)).unwrap();
+        let path = Path::parse("org.example.pair.fragile").unwrap();
+        let fragile = master.request_service(ServiceRequest::Path(&path))
+                .unwrap();
+        while starts.load(Ordering::SeqCst) == 0 {
+            thread::yield_now();
+        }
+
+        // Replacement of the stubborn sibling starts when its shutdown
+        // time passes.
+        fragile.send(Message::new(vec![])).unwrap();
+        let wait = Instant::now();
+        while starts.load(Ordering::SeqCst) < 2 {
+            assert!(wait.elapsed() < Duration::from_secs(5));
+            thread::yield_now();
+        }
+        release.store(true, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn panic_isolation() {
+        let mut master = fragile_master();
+        let path = Path::parse("org.example.fragile.main").unwrap();
+        let chan = master.request_service(ServiceRequest::Path(&path))
+                .unwrap();
+        let worker = worker(&master);
+
+        chan.send(Message::from(&b"crash"[..])).unwrap();
+        let thread = match chan.receive() {
+            Err(ChannelError::Notice(ChannelNotice::PeerFault(id))) => id,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(chan.receive(), Err(ChannelError::Disconnected));
+
+        assert_eq!(worker.unarch().id(), thread);
+        let reason = "crash requested".to_string();
+        assert_eq!(worker.state(), ThreadState::Faulted(reason));
+    }
+
+    #[test]
+    fn wait_state() {
+        let mut first = MyImplementer::new("org.example.first").unwrap();
+        first.add_service("main", |chan: &Channel| {
+            let mut master = MyMaster::current().unwrap();
+            let path = Path::parse("org.example.second.main").unwrap();
+            let second = master.request_service(ServiceRequest::Path(&path))
+                    .unwrap();
+
+            // Wait for the app and then for the second service that
+            // waits too.
+            let chan = master.channel(chan).unwrap();
+            chan.receive().unwrap();
+            let _ = second.receive();
+        });
+        let mut second = MyImplementer::new("org.example.second").unwrap();
+        second.add_service("main", |chan: &Channel| {
+            let master = MyMaster::current().unwrap();
+            let _ = master.channel(chan).unwrap().receive();
+        });
+        let mut source = MyImplementerSource::new();
+        source.append(first);
+        source.append(second);
+
+        let mut master = MyMaster::new();
+        master.add_implementer_source(source);
+        let path = Path::parse("org.example.first.main").unwrap();
+        let chan = master.request_service(ServiceRequest::Path(&path))
+                .unwrap();
+
+        let thread = {
+            let reg = master.runtime.lock();
+            let members = &reg.channels[&chan.handle().id()].members;
+            *members.keys().find(|&&id| id != master.thread).unwrap()
+        };
+        let target = WaitTarget::single(chan.handle().id());
+        let waiting = ThreadState::Wait { target, deadline: None };
+        while master.runtime.lock().threads[&thread].state != waiting {
+            ::std::thread::yield_now();
+        }
+        assert!(master.deadlocked_threads().is_empty());
+
+        // Now both services wait for each other.
+        chan.send(Message::from(&b"go"[..])).unwrap();
+        while master.deadlocked_threads().len() < 2 {
+            ::std::thread::yield_now();
+        }
+        assert!(master.deadlocked_threads().contains(&thread));
+        assert_eq!(chan.receive_timeout(Duration::from_millis(1)), Ok(None));
+
+        assert!(master.shutdown(Duration::from_secs(1)).is_clean());
+    }
+
+    #[test]
+    fn thread_management() {
+        let mut master = MyMaster::new();
+        let entry = MyServiceEntry::new(|chan: &Channel| {
+            let master = MyMaster::current().unwrap();
+            let chan = master.channel(chan).unwrap();
+            while let Ok(msg) = chan.receive() {
+                chan.send(msg).unwrap();
+            }
+            assert!(master.thread().is_cancelled());
+        });
+        let (thread, chan) = master.spawn_thread(entry).unwrap();
+        let worker = thread.unarch().clone();
+
+        chan.send(Message::from(&b"ping"[..])).unwrap();
+        assert_eq!(chan.receive().unwrap().data(), b"ping");
+
+        let timeout = Some(Duration::from_millis(1));
+        assert_eq!(master.join_thread(&worker, timeout),
+                Err(ThreadError::TimedOut));
+
+        // Suspended worker does not reply until it is resumed.
+        master.suspend_thread(&worker).unwrap();
+        chan.send(Message::from(&b"ping"[..])).unwrap();
+        let wait = Duration::from_millis(20);
+        assert_eq!(chan.receive_timeout(wait), Ok(None));
+        assert_eq!(thread.state(), ThreadState::Suspended);
+        master.resume_thread(&worker).unwrap();
+        assert_eq!(chan.receive().unwrap().data(), b"ping");
+
+        master.cancel_thread(&worker).unwrap();
+        assert_eq!(master.join_thread(&worker, None), Ok(ThreadState::Dead));
+        assert_eq!(master.cancel_thread(&worker), Err(ThreadError::Finished));
+    }
+
+    #[test]
+    fn memory_quota() {
+        let mut implementer = MyImplementer::new("org.example.greedy")
+                .unwrap();
+        implementer.set_memory_quota(Some(16), Some(32), QuotaPolicy::Fail);
+        implementer.add_service("main", |chan: &Channel| {
+            let master = MyMaster::current().unwrap();
+            let chan = master.channel(chan).unwrap();
+            let thread = master.thread();
+            let memory = thread.memory();
+
+            let block = memory.allocate(10).unwrap();
+            let denied = memory.allocate(10).is_err()
+                    && chan.send(Message::from(&b"too long"[..])).is_err();
+            if denied {
+                // Message is charged until it gets received.
+                chan.send(Message::from(&b"denied"[..])).unwrap();
+            }
+
+            let _ = chan.receive();
+            drop(block);
+            let _block = memory.allocate(14).unwrap();
+            chan.send(Message::from(&b"ok"[..])).unwrap();
+            let _ = chan.receive();
+        });
+        let mut source = MyImplementerSource::new();
+        source.append(implementer);
+
+        let mut master = MyMaster::new();
+        master.add_implementer_source(source);
+        let path = Path::parse("org.example.greedy.main").unwrap();
+        let chan = master.request_service(ServiceRequest::Path(&path))
+                .unwrap();
+
+        assert_eq!(chan.receive().unwrap().data(), b"denied");
+        chan.send(Message::from(&b"go"[..])).unwrap();
+        assert_eq!(chan.receive().unwrap().data(), b"ok");
+
+        let object = Path::parse("org.example.greedy").unwrap();
+        let id = master.runtime.lock().objects.values()
+                .find(|obj| obj.implementer.path() == "org.example.greedy")
+                .unwrap()
+                .implementer.id();
+        let memory = master.object_memory(&Object::new(object, Id::new(id, 0)))
+                .unwrap();
+        assert_eq!(memory.usage(), 14);
+        assert_eq!(memory.peak(), 16);
+        assert_eq!(memory.limit(), Some(32));
+        chan.send(Message::from(&b"bye"[..])).unwrap();
+    }
+
+    #[test]
+    fn memory_quota_fault() {
+        let mut implementer = MyImplementer::new("org.example.greedy")
+                .unwrap();
+        implementer.set_memory_quota(Some(16), None, QuotaPolicy::Fault);
+        implementer.add_service("main", |chan: &Channel| {
+            let master = MyMaster::current().unwrap();
+            master.channel(chan).unwrap().receive().unwrap();
+            let thread = master.thread();
+            let _block = thread.memory().allocate(10);
+
+            // Thread is not panicked but faults when it returns.
+            assert!(thread.memory().allocate(10).is_err());
+            assert!(thread.is_cancelled());
+        });
+        let mut source = MyImplementerSource::new();
+        source.append(implementer);
+
+        let mut master = MyMaster::new();
+        master.add_implementer_source(source);
+        let path = Path::parse("org.example.greedy.main").unwrap();
+        let chan = master.request_service(ServiceRequest::Path(&path))
+                .unwrap();
+        let worker = worker(&master);
+        chan.send(Message::new(vec![])).unwrap();
+
+        match chan.receive() {
+            Err(ChannelError::Notice(ChannelNotice::PeerFault(_))) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        let reason = "memory quota exceeded".to_string();
+        assert_eq!(worker.state(), ThreadState::Faulted(reason));
+    }
+
+    #[test]
+    fn finished_threads_removed() {
+        use std::thread;
+
+        let mut master = master();
+        let path = Path::parse("org.example.impl.echo").unwrap();
+        for _ in 0..32 {
+            let chan = master.request_service(ServiceRequest::Path(&path))
+                    .unwrap();
+            chan.send(Message::from(&b"ping"[..])).unwrap();
+            assert_eq!(chan.receive().unwrap().data(), b"ping");
+
+            // Echo thread exits when it is left alone.
+            chan.leave().unwrap();
+            while master.threads().len() > 1 {
+                thread::yield_now();
+            }
+        }
+        assert_eq!(master.thread_dump().threads().len(), 1);
+
+        // Instance keeps the record of the thread until it is dropped.
+        let entry = MyServiceEntry::new(echo);
+        let (worker, chan) = master.spawn_thread(entry).unwrap();
+        chan.leave().unwrap();
+        let id = worker.unarch().clone();
+        assert_eq!(master.join_thread(&id, None), Ok(ThreadState::Dead));
+        assert_eq!(master.threads().len(), 2);
+        drop(worker);
+        assert_eq!(master.threads().len(), 1);
+        assert_eq!(master.join_thread(&id, None),
+                Err(ThreadError::StaleHandle));
+    }
+
+    #[test]
+    fn region_grants() {
+        let mut master = MyMaster::new();
+        let entry = MyServiceEntry::new(|chan: &Channel| {
+            let master = MyMaster::current().unwrap();
+            let chan = master.channel(chan).unwrap();
+            let thread = master.thread();
+            let mut mapped = None;
+            while let Ok(msg) = chan.receive() {
+                if let Some(grant) = msg.grants().first() {
+                    mapped = Some(thread.memory().map(grant).unwrap());
+                }
+
+                // Reply with the sum of bytes, plus 100 if it was
+                // possible to write.
+                let region: &MyRegion = mapped.as_ref().unwrap();
+                let reply = match region.read(|d| d.iter().sum::<u8>()) {
+                    Ok(sum) if region.write(|d| d[0] = 0).is_ok() => sum + 100,
+                    Ok(sum) => sum,
+                    Err(err) => {
+                        assert_eq!(err, MemoryError::Revoked);
+                        0
+                    },
+                };
+                chan.send(Message::new(vec![reply])).unwrap();
+            }
+        });
+        let (worker, chan) = master.spawn_thread(entry).unwrap();
+
+        let thread = master.thread();
+        let region = thread.memory().allocate_region(4).unwrap();
+        region.write(|data| data.copy_from_slice(&[1, 2, 3, 4])).unwrap();
+        assert_eq!(thread.memory().usage(), 4);
+
+        let grant = region.grant(Access::ReadOnly).unwrap();
+        chan.send(Message::new(vec![]).with_grant(grant)).unwrap();
+        assert_eq!(chan.receive().unwrap().data(), &[10]);
+
+        region.revoke(&grant).unwrap();
+        chan.send(Message::new(vec![])).unwrap();
+        assert_eq!(chan.receive().unwrap().data(), &[0]);
+
+        // Grant is released when its receiver dies.
+        let grant = region.grant(Access::ReadWrite).unwrap();
+        chan.send(Message::new(vec![]).with_grant(grant)).unwrap();
+        assert_eq!(chan.receive().unwrap().data(), &[110]);
+        assert_eq!(region.read(|data| data[0]), Ok(0));
+        master.cancel_thread(worker.unarch()).unwrap();
+        master.join_thread(worker.unarch(), None).unwrap();
+        assert!(master.runtime.lock().grants.is_empty());
+        assert_eq!(region.revoke(&grant), Err(MemoryError::Revoked));
+
+        drop(region);
+        assert_eq!(thread.memory().usage(), 0);
+    }
+
+    #[test]
+    fn handle_table() {
+        let mut master = MyMaster::new();
+        let relay = MyServiceEntry::new(|_: &Channel| {
+            let thread = MyMaster::current().unwrap().thread();
+            let start = thread.channel(Descriptor::new(0)).unwrap();
+            start.receive().unwrap();
+
+            // Channel transfered by the app.
+            let other = thread.channel(Descriptor::new(1)).unwrap();
+            other.send(Message::from(&b"ping"[..])).unwrap();
+            start.send(other.receive().unwrap()).unwrap();
+        });
+        let echo = MyServiceEntry::new(|chan: &Channel| {
+            let chan = MyMaster::current().unwrap().channel(chan).unwrap();
+            while let Ok(msg) = chan.receive() {
+                chan.send(msg).unwrap();
+            }
+        });
+        let (relay, relay_chan) = master.spawn_thread(relay).unwrap();
+        let (_, echo_chan) = master.spawn_thread(echo).unwrap();
+
+        let thread = master.thread();
+        assert_eq!(relay_chan.descriptor(), Some(Descriptor::new(0)));
+        assert_eq!(echo_chan.descriptor(), Some(Descriptor::new(1)));
+
+        let dup = thread.duplicate(Descriptor::new(1)).unwrap();
+        assert_eq!(dup, Descriptor::new(2));
+        thread.close(Descriptor::new(1)).unwrap();
+        assert_eq!(echo_chan.descriptor(), Some(dup));
+        assert_eq!(thread.descriptors(), vec![Descriptor::new(0), dup]);
+
+        let moved = thread.transfer(dup, relay.unarch()).unwrap();
+        assert_eq!(moved, Descriptor::new(1));
+        assert!(thread.channel(dup).is_err());
+        assert_eq!(echo_chan.send(Message::new(vec![])),
+                Err(ChannelError::NotMember));
+
+        relay_chan.send(Message::from(&b"go"[..])).unwrap();
+        assert_eq!(relay_chan.receive().unwrap().data(), b"ping");
+
+        // Descriptor of the left channel stays taken until it is closed.
+        relay_chan.leave().unwrap();
+        let result = thread.channel(Descriptor::new(0)).err();
+        assert_eq!(result, Some(ChannelError::Disconnected));
+        assert!(thread.descriptors().is_empty());
+        thread.close(Descriptor::new(0)).unwrap();
+        let result = thread.channel(Descriptor::new(0)).err();
+        assert_eq!(result, Some(ChannelError::BadDescriptor));
+    }
+
+    #[test]
+    fn pass_descriptors() {
+        let mut master = fragile_master();
+        let path = Path::parse("org.example.fragile.main").unwrap();
+        let backend = master.request_service(ServiceRequest::Path(&path))
+                .unwrap();
+        let client = MyServiceEntry::new(|_: &Channel| {
+            let thread = MyMaster::current().unwrap().thread();
+            let start = thread.channel(Descriptor::new(0)).unwrap();
+            let msg = start.receive().unwrap();
+            assert_eq!(msg.descriptors(), &[Descriptor::new(1)]);
+
+            let backend = thread.channel(msg.descriptors()[0]).unwrap();
+            backend.send(Message::from(&b"ping"[..])).unwrap();
+            start.send(backend.receive().unwrap()).unwrap();
+        });
+        let (_, client) = master.spawn_thread(client).unwrap();
+
+        // Backend channel allows single connection only.
+        let thread = master.thread();
+        let descriptor = backend.descriptor().unwrap();
+        let dup = thread.duplicate(descriptor).unwrap();
+        let msg = Message::new(vec![]).with_descriptor(dup);
+        assert_eq!(client.send(msg),
+                Err(ChannelError::MultipleConnectionsForbiden));
+        thread.close(dup).unwrap();
+
+        let msg = Message::new(vec![0; 8]).with_descriptor(Descriptor::new(7));
+        assert_eq!(client.send(msg), Err(ChannelError::BadDescriptor));
+        assert_eq!(thread.memory().usage(), 0);
+
+        let msg = Message::new(vec![]).with_descriptor(descriptor);
+        client.send(msg).unwrap();
+        assert_eq!(backend.send(Message::new(vec![])),
+                Err(ChannelError::NotMember));
+        assert_eq!(client.receive().unwrap().data(), b"ping");
+    }
+
+    #[test]
+    fn select_channels() {
+        let mut master = MyMaster::new();
+        let selector = MyServiceEntry::new(|_: &Channel| {
+            let thread = MyMaster::current().unwrap().thread();
+            let control = thread.channel(Descriptor::new(0)).unwrap();
+            control.receive().unwrap();
+
+            // Data channel transfered by the app.
+            let data = thread.channel(Descriptor::new(1)).unwrap();
+            let mut select = MySelect::new();
+            select.add(&control, &[ChannelEvent::Receive]);
+            select.add(&data, &[ChannelEvent::Receive, ChannelEvent::Send]);
+            assert_eq!(select.wait(), Ok(vec![(1, ChannelEvent::Send)]));
+
+            data.send(Message::from(&b"ping"[..])).unwrap();
+            let mut select = MySelect::new();
+            select.add(&control, &[ChannelEvent::Receive]);
+            select.add(&data, &[ChannelEvent::Receive]);
+            assert_eq!(select.wait(), Ok(vec![(1, ChannelEvent::Receive)]));
+            control.send(data.receive().unwrap()).unwrap();
+
+            assert_eq!(select.wait(), Ok(vec![(0, ChannelEvent::Receive)]));
+            control.send(control.receive().unwrap()).unwrap();
+        });
+        let echo = MyServiceEntry::new(|chan: &Channel| {
+            let chan = MyMaster::current().unwrap().channel(chan).unwrap();
+            while let Ok(msg) = chan.receive() {
+                chan.send(msg).unwrap();
+            }
+        });
+        let (selector, control) = master.spawn_thread(selector).unwrap();
+        let (_, data) = master.spawn_thread(echo).unwrap();
+        let channels = vec![control.handle().id(), data.handle().id()];
+
+        let thread = master.thread();
+        let descriptor = data.descriptor().unwrap();
+        thread.transfer(descriptor, selector.unarch()).unwrap();
+        control.send(Message::new(vec![])).unwrap();
+        assert_eq!(control.receive().unwrap().data(), b"ping");
+
+        // Selector waits for any of its channels.
+        let target = WaitTarget::Any(channels.into_iter().collect());
+        let waiting = ThreadState::Wait { target, deadline: None };
+        while selector.state() != waiting {
+            ::std::thread::yield_now();
+        }
+        control.send(Message::from(&b"stop"[..])).unwrap();
+        assert_eq!(control.receive().unwrap().data(), b"stop");
+    }
+
+    #[test]
+    fn select_all_channels() {
+        let mut master = MyMaster::new();
+        let echo = MyServiceEntry::new(|chan: &Channel| {
+            let chan = MyMaster::current().unwrap().channel(chan).unwrap();
+            while let Ok(msg) = chan.receive() {
+                chan.send(msg).unwrap();
+            }
+        });
+        let (_, first) = master.spawn_thread(echo.clone()).unwrap();
+        let (_, second) = master.spawn_thread(echo).unwrap();
+
+        let mut select = MySelect::new();
+        select.add(&first, &[ChannelEvent::Receive]);
+        select.add(&second, &[ChannelEvent::Receive]);
+
+        // Event of single channel is not enough.
+        first.send(Message::new(vec![1])).unwrap();
+        assert_eq!(select.wait(), Ok(vec![(0, ChannelEvent::Receive)]));
+        let timeout = Duration::from_millis(50);
+        assert_eq!(select.wait_all_timeout(timeout),
+                Ok(vec![(0, ChannelEvent::Receive)]));
+
+        second.send(Message::new(vec![2])).unwrap();
+        assert_eq!(select.wait_all(), Ok(vec![
+            (0, ChannelEvent::Receive),
+            (1, ChannelEvent::Receive),
+        ]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn event_fd_readiness() {
+        use std::io::Read;
+        use std::os::unix::io::AsFd;
+        use std::os::unix::net::UnixStream;
+
+        let mut master = MyMaster::new();
+        let entry = MyServiceEntry::new(|chan: &Channel| {
+            let chan = MyMaster::current().unwrap().channel(chan).unwrap();
+            chan.receive().unwrap();
+        });
+        let (_, chan) = master.spawn_thread(entry).unwrap();
+        let fd = chan.event_fd().unwrap();
+        let owned = fd.as_fd().try_clone_to_owned().unwrap();
+        let mut socket = UnixStream::from(owned);
+        let mut buf = [0; 8];
+        assert!(socket.read(&mut buf).is_err());
+
+        // Thread finishes and leaves the app alone in the channel.
+        chan.send(Message::new(vec![])).unwrap();
+        while !fd.is_ready() {
+            ::std::thread::yield_now();
+        }
+        assert_eq!(socket.read(&mut buf).unwrap(), 1);
+
+        fd.clear();
+        assert!(fd.is_ready());
+        assert_eq!(chan.try_receive(), Err(ChannelError::Disconnected));
+    }
+
+    #[test]
+    fn bounded_channel() {
+        let mut master = MyMaster::new();
+        let echo = MyServiceEntry::new(|chan: &Channel| {
+            let chan = MyMaster::current().unwrap().channel(chan).unwrap();
+            while let Ok(msg) = chan.receive() {
+                chan.send(msg).unwrap();
+            }
+        });
+        let (echo, chan) = master.spawn_thread(echo).unwrap();
+        master.suspend_thread(echo.unarch()).unwrap();
+
+        assert_eq!(chan.set_capacity(Some(0), Overflow::Fail),
+                Err(ChannelError::ZeroCapacity));
+        chan.set_capacity(Some(2), Overflow::Fail).unwrap();
+        assert_eq!(chan.policy().capacity(), Some(2));
+        chan.send(Message::new(vec![1])).unwrap();
+        chan.send(Message::new(vec![2])).unwrap();
+        assert_eq!(chan.send(Message::new(vec![3])),
+                Err(ChannelError::QueueFull));
+
+        // Oldest message makes room for the new one.
+        chan.set_capacity(Some(2), Overflow::DropOldest).unwrap();
+        chan.send(Message::new(vec![3])).unwrap();
+
+        // Sender waits until suspended receiver takes the messages.
+        chan.set_capacity(Some(2), Overflow::Block).unwrap();
+        let mut send = chan.send_async(Message::new(vec![4]));
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(Pin::new(&mut send).poll(&mut cx).is_pending());
+        master.resume_thread(echo.unarch()).unwrap();
+        block_on(send).unwrap();
+
+        for i in 2..5 {
+            assert_eq!(chan.receive().unwrap().data(), &[i]);
+        }
+        assert_eq!(chan.occupancy(), 0);
+    }
+
+    #[test]
+    fn rpc_calls() {
+        use rpc::*;
+
+        fn rpc_interface() -> Interface {
+            let mut interface = interface();
+            for &name in &["double", "fail", "slow", "none"] {
+                Method::<u32, u32>::declare(&mut interface, "echo", name)
+                        .unwrap();
+            }
+            interface
+        }
+
+        fn method(name: &str) -> Method<u32, u32> {
+            Method::new(&rpc_interface(), "echo", name).unwrap()
+        }
+
+        let mut master = MyMaster::new();
+        let entry = MyServiceEntry::new(|chan: &Channel| {
+            let chan = MyMaster::current().unwrap().channel(chan).unwrap();
+            let mut server = RpcServer::new(&chan);
+            server.handle(&method("double"), |x| Ok(x * 2));
+            server.handle(&method("fail"), |_| Err("failed".to_string()));
+            server.handle(&method("slow"), |x| {
+                ::std::thread::sleep(Duration::from_millis(50));
+                Ok(x)
+            });
+            server.serve().unwrap();
+        });
+        let (_, chan) = master.spawn_thread(entry).unwrap();
+        let mut interface = rpc_interface();
+        assert!(Method::<u32, u32>::new(&interface, "echo", "x").is_none());
+        assert!(Method::<u8, u32>::new(&interface, "echo", "fail").is_none());
+        assert!(Method::<u32, u32>::declare(&mut interface, "none", "x")
+                .is_none());
+        assert!(Method::<u32, u32>::declare(&mut interface, "echo", "fail")
+                .is_none());
+
+        // Calls of different clients never share correlation IDs.
+        let mut other = RpcClient::new(&chan);
+        let mut client = RpcClient::new(&chan);
+        let first = other.start(&method("double"), &1, None).unwrap();
+        let second = client.start(&method("double"), &1, None).unwrap();
+        assert_ne!(first.id(), second.id());
+        other.cancel(first).unwrap();
+        client.cancel(second).unwrap();
+
+        assert_eq!(client.call(&method("double"), &2, None), Ok(4));
+        assert_eq!(client.call(&method("fail"), &0, None),
+                Err(RpcError::Remote("failed".to_string())));
+        assert_eq!(client.call(&method("none"), &0, None),
+                Err(RpcError::UnknownMethod));
+
+        // Call queued behind the slow one gets cancelled.
+        let slow = client.start(&method("slow"), &1, None).unwrap();
+        let cancelled = client.start(&method("double"), &3, None).unwrap();
+        client.cancel(cancelled).unwrap();
+        let timeout = Some(Duration::from_millis(1));
+        assert_eq!(client.call(&method("slow"), &2, timeout),
+                Err(RpcError::TimedOut));
+        assert_eq!(client.in_flight(), 1);
+        assert_eq!(client.wait(slow), Ok(1));
+        assert_eq!(client.call(&method("double"), &5, None), Ok(10));
+    }
+
+    #[test]
+    fn async_operations() {
+        let mut master = MyMaster::new();
+        let entry = MyServiceEntry::new(|chan: &Channel| {
+            let chan = MyMaster::current().unwrap().channel(chan).unwrap();
+            chan.receive().unwrap();
+        });
+        let (_, chan) = master.spawn_thread(entry).unwrap();
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let mut receive = chan.receive_async();
+        assert!(Pin::new(&mut receive).poll(&mut cx).is_pending());
+        let mut changed = chan.members_changed();
+        assert!(Pin::new(&mut changed).poll(&mut cx).is_pending());
+        assert_eq!(block_on(chan.join_async()),
+                Err(ChannelError::AlreadyMember));
+
+        // Thread finishes after the message and leaves the channel.
+        block_on(chan.send_async(Message::new(vec![]))).unwrap();
+        assert_eq!(block_on(changed), Ok(1));
+        assert_eq!(block_on(receive), Err(ChannelError::Disconnected));
+    }
+
+    #[test]
+    fn selective_wakeup() {
+        struct Counter(AtomicUsize);
+
+        impl Wake for Counter {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut master = MyMaster::new();
+        let entry = MyServiceEntry::new(echo);
+        let (_, first) = master.spawn_thread(entry.clone()).unwrap();
+        let (_, second) = master.spawn_thread(entry).unwrap();
+        let counters: Vec<Arc<Counter>> = (0..2)
+                .map(|_| Arc::new(Counter(AtomicUsize::new(0))))
+                .collect();
+
+        let mut futures = Vec::new();
+        for (chan, counter) in [&first, &second].iter().zip(&counters) {
+            let waker = Waker::from(counter.clone());
+            let mut cx = Context::from_waker(&waker);
+            let mut receive = Box::pin(chan.receive_async());
+            assert!(receive.as_mut().poll(&mut cx).is_pending());
+            futures.push(receive);
+        }
+
+        // Only the future of the channel that got the reply is woken.
+        first.send(Message::new(vec![1])).unwrap();
+        while counters[0].0.load(Ordering::SeqCst) == 0 {
+            ::std::thread::yield_now();
+        }
+        assert_eq!(block_on(futures.remove(0)).unwrap().data(), &[1]);
+        assert_eq!(counters[1].0.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "futures")]
+    fn message_stream() {
+        use futures_core::Stream;
+        use futures_sink::Sink;
+        use std::future::poll_fn;
+
+        let mut master = MyMaster::new();
+        let entry = MyServiceEntry::new(echo);
+        let (thread, chan) = master.spawn_thread(entry).unwrap();
+        let mut sink = chan.sink();
+        let mut stream = chan.stream();
+
+        for i in 0..3 {
+            block_on(poll_fn(|cx| Pin::new(&mut sink).poll_ready(cx)))
+                    .unwrap();
+            Pin::new(&mut sink).start_send(Message::new(vec![i])).unwrap();
+        }
+        block_on(poll_fn(|cx| Pin::new(&mut sink).poll_flush(cx))).unwrap();
+
+        let mut next = || {
+            block_on(poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)))
+        };
+        for i in 0..3 {
+            assert_eq!(next().unwrap().unwrap().data(), &[i]);
+        }
+
+        // Stream ends when the echo thread leaves.
+        master.cancel_thread(thread.unarch()).unwrap();
+        assert!(next().is_none());
+    }
+
+    /// Master with the service that echoes messages and panics
+    /// on "crash" message.
+    fn fragile_master() -> MyMaster {
+        let mut implementer = MyImplementer::new("org.example.fragile")
+                .unwrap();
+        implementer.add_service("main", |chan: &Channel| {
+            let master = MyMaster::current().unwrap();
+            let chan = master.channel(chan).unwrap();
+            while let Ok(msg) = chan.receive() {
+                if msg.data() == b"crash" {
+                    panic!("crash requested");
+                }
+                chan.send(msg).unwrap();
+            }
+        });
+        let mut source = MyImplementerSource::new();
+        source.append(implementer);
+
+        let mut master = MyMaster::new();
+        master.add_implementer_source(source);
+        master
+    }
+
+    /// Instance of the only thread besides the thread of the master.
+    fn worker(master: &MyMaster) -> MyThread {
+        master.threads().into_iter()
+                .find(|thr| thr.unarch().id() != master.thread)
+                .unwrap()
+    }
+
+    fn object_id(master: &MyMaster) -> usize {
+        let reg = master.runtime.lock();
+        reg.objects.values()
+                .find(|obj| obj.implementer.path() == "org.example.fragile")
+                .unwrap()
+                .implementer.id()
+    }
+
+    fn service_id(master: &MyMaster) -> usize {
+        let object = object_id(master);
+        let reg = master.runtime.lock();
+        reg.services.iter()
+                .find(|&(_, rec)| rec.object == object)
+                .map(|(&id, _)| id)
+                .unwrap()
+    }
+}
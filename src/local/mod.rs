@@ -8,27 +8,48 @@
 //! This module can be used as tutorial for implementing your own CCS
 //! master.
 
-use super::meta::{Interface, InterfaceSet, Object};
+use super::meta::*;
+use super::master::*;
 
-/// The implementer of Interface source. When application begins,
-/// we add the list of all interfaces we will use so that master could
-/// find all requested sources when they get needed.
-pub struct MyInterfaceSource {
+/// Shared state of the runtime.
+mod runtime;
 
-    /// The set of application interfaces.
-    ints: InterfaceSet,
-}
+/// Interface and implementer sources.
+mod source;
+pub use self::source::*;
 
-impl MyInterfaceSource {
+/// Objects and services.
+mod object;
+pub use self::object::*;
 
-    /// Append new interface.
-    pub fn append(&mut self, i: Interface) {
-        self.ints.insert(i);
-    }
-}
+/// Channels.
+mod channel;
+pub use self::channel::*;
 
-pub struct MyObject {
+/// Waiting on several channels at once.
+mod select;
+pub use self::select::*;
 
-    /// Arch independent part.
-    object: Object,
-}
+/// Descriptors for the external event loops.
+#[cfg(unix)]
+mod event_fd;
+#[cfg(unix)]
+pub use self::event_fd::*;
+
+/// Futures of the channel operations.
+mod future;
+pub use self::future::*;
+
+/// Streams and sinks of the channel messages.
+#[cfg(feature = "futures")]
+mod stream;
+#[cfg(feature = "futures")]
+pub use self::stream::*;
+
+/// Threads and their memory.
+mod thread;
+pub use self::thread::*;
+
+/// Master of the local runtime.
+mod master;
+pub use self::master::*;
@@ -0,0 +1,213 @@
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use super::*;
+
+/// Object of the local runtime.
+pub struct MyObject {
+
+    /// Arch independent part.
+    object: Shared<Object>,
+
+    /// Implementer from which this object gets loaded.
+    implementer: Arc<MyImplementer>,
+
+    /// Services of this object.
+    services: ServiceArchSet<MyService>,
+
+    /// Handles of the services ordered by their IDs.
+    service_handles: HandleCollection<Service>,
+
+    /// Subobjects. Local runtime does not have any.
+    objects: ObjectArchSet<MyObject>,
+
+    /// Handles of the subobjects.
+    object_handles: HandleCollection<Object>,
+}
+
+/// Service of the local runtime.
+pub struct MyService {
+
+    /// Arch independent part.
+    service: Service,
+
+    /// Entry point of the service.
+    entry: MyServiceEntry,
+}
+
+/// Entry point of the local service. Wraps any entry that can be
+/// shared among the threads.
+#[derive(Clone)]
+pub struct MyServiceEntry(Arc<dyn ServiceEntry + Send + Sync>);
+
+impl MyObject {
+
+    /// Create the object from given implementer.
+    pub fn new(implementer: Arc<MyImplementer>) -> Self {
+        Self::with_generation(implementer, 0)
+    }
+
+    /// Create the object from given implementer. Handles of the object
+    /// and its services get given generation.
+    pub fn with_generation(implementer: Arc<MyImplementer>,
+            generation: usize) -> Self {
+        let path = Path::parse(implementer.path()).unwrap();
+        let object = Id::new(implementer.id(), generation);
+        let object = Object::new(path, object);
+        let object = Shared::new(object);
+
+        let mut services = ServiceArchSet::default();
+        let service_handles = HandleCollection::new();
+        for &(id, ref name, ref entry) in implementer.services() {
+            let handle = Id::new(id, generation);
+            let service = Service::new(object.clone(), name, handle)
+                    .unwrap();
+            service_handles.insert(id, service.clone());
+            services.insert(MyService::new(service, entry.clone()));
+        }
+
+        MyObject {
+            object,
+            implementer,
+            services,
+            service_handles,
+            objects: Default::default(),
+            object_handles: Default::default(),
+        }
+    }
+
+    /// Implementer from which this object gets loaded.
+    pub fn implementer(&self) -> &Arc<MyImplementer> {
+        &self.implementer
+    }
+}
+
+impl ObjectArchitecture for MyObject {
+
+    type S = MyService;
+
+    fn handle(&self) -> &Object {
+        &self.object
+    }
+
+    fn service_iter(&self, mode: IterMode) -> ServiceIterator<'_> {
+        ServiceIterator::new(&self.service_handles, mode)
+    }
+
+    fn object_iter(&self, mode: IterMode) -> ObjectIterator<'_> {
+        ObjectIterator::new(&self.object_handles, mode)
+    }
+
+    fn services(&self) -> &ServiceArchSet<MyService> {
+        &self.services
+    }
+
+    fn objects(&self) -> &ObjectArchSet<MyObject> {
+        &self.objects
+    }
+
+    fn on_initialize(&self) -> ObjectResult<()> {
+        match self.implementer.initialize_hook() {
+            Some(hook) => hook(),
+            None => Ok(()),
+        }
+    }
+
+    fn on_start(&self) -> ObjectResult<()> {
+        match self.implementer.start_hook() {
+            Some(hook) => hook(),
+            None => Ok(()),
+        }
+    }
+
+    fn on_stop(&self) {
+        if let Some(hook) = self.implementer.stop_hook() {
+            hook()
+        }
+    }
+}
+
+impl PartialEq for MyObject {
+
+    fn eq(&self, other: &MyObject) -> bool {
+        self.object.id() == other.object.id()
+    }
+}
+
+impl Eq for MyObject {}
+
+impl PartialOrd for MyObject {
+
+    fn partial_cmp(&self, other: &MyObject) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MyObject {
+
+    fn cmp(&self, other: &MyObject) -> Ordering {
+        self.object.id().cmp(&other.object.id())
+    }
+}
+
+impl MyService {
+
+    /// Create new service with given handle and entry point.
+    pub fn new(service: Service, entry: MyServiceEntry) -> Self {
+        MyService { service, entry }
+    }
+}
+
+impl ServiceArchitecture for MyService {
+
+    type SE = MyServiceEntry;
+
+    type O = MyObject;
+
+    fn handle(&self) -> &Service {
+        &self.service
+    }
+
+    fn entry_point(&self) -> &MyServiceEntry {
+        &self.entry
+    }
+}
+
+impl PartialEq for MyService {
+
+    fn eq(&self, other: &MyService) -> bool {
+        self.service.id() == other.service.id()
+    }
+}
+
+impl Eq for MyService {}
+
+impl PartialOrd for MyService {
+
+    fn partial_cmp(&self, other: &MyService) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MyService {
+
+    fn cmp(&self, other: &MyService) -> Ordering {
+        self.service.id().cmp(&other.service.id())
+    }
+}
+
+impl MyServiceEntry {
+
+    /// Wrap given entry point.
+    pub fn new<E>(entry: E) -> Self
+            where E: ServiceEntry + Send + Sync + 'static {
+        MyServiceEntry(Arc::new(entry))
+    }
+}
+
+impl ServiceEntry for MyServiceEntry {
+
+    fn start(&self, channel: &Channel) {
+        self.0.start(channel)
+    }
+}
@@ -0,0 +1,1901 @@
+//! Shared state of the local runtime.
+//!
+//! Handles of the meta module use Rc unless `sync` feature is enabled
+//! and cannot be sent to other threads. So the runtime stores only plain
+//! data here and each thread creates its own handles from it when needed.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, RwLock};
+use std::task::Waker;
+use std::time::{Duration, Instant};
+
+use super::*;
+#[cfg(unix)]
+use super::event_fd::ReadySignal;
+use tools::IdAllocator;
+
+/// Counter used to generate IDs of all local runtime elements.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// All the events of the channel.
+const EVENTS: [ChannelEvent; 4] = [
+    ChannelEvent::Receive,
+    ChannelEvent::Send,
+    ChannelEvent::Members,
+    ChannelEvent::Closed,
+];
+
+thread_local! {
+    /// Runtime and thread ID of the thread that is currently running.
+    /// Set only for threads spawned by the runtime.
+    static CURRENT: RefCell<Option<(Arc<Runtime>, usize)>>
+            = const { RefCell::new(None) };
+}
+
+/// Generate new ID which is unique in the process.
+pub(crate) fn next_id() -> usize {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Runtime and ID of the thread that is currently running.
+pub(crate) fn current() -> Option<(Arc<Runtime>, usize)> {
+    CURRENT.with(|cur| cur.borrow().clone())
+}
+
+/// Whether the handle with given ID refers to the element that was
+/// removed. Its ID may belong to other element now.
+fn is_stale(ids: &IdAllocator, id: Id) -> bool {
+    ids.is_known(id.index()) && !ids.is_current(id.index(), id.generation())
+}
+
+/// Message of the caught panic.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Shared state of the local runtime.
+pub(crate) struct Runtime {
+
+    /// All the registered elements.
+    registry: Mutex<Registry>,
+
+    /// Notified when any of the channels or threads change.
+    changed: Condvar,
+
+    /// Wakers of the pending futures by the channel and the event they
+    /// wait for. They are woken when the event may have appeared
+    /// and get removed then.
+    wakers: Mutex<BTreeMap<(usize, ChannelEvent), Vec<Waker>>>,
+}
+
+/// Loaded object.
+pub(crate) struct ObjectRecord {
+
+    /// Implementer which was used to load this object.
+    pub implementer: Arc<MyImplementer>,
+
+    /// Current state of the object.
+    pub state: ObjectState,
+
+    /// Memory used by all threads of the object.
+    pub memory: MemoryRecord,
+}
+
+/// Memory accounting of the thread or the object.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct MemoryRecord {
+
+    /// Count of bytes that are used now.
+    pub usage: usize,
+
+    /// Highest count of bytes that were used at once.
+    pub peak: usize,
+
+    /// Count of bytes that can be used.
+    pub limit: Option<usize>,
+}
+
+/// Owner of the charged memory.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum MemoryOwner {
+
+    /// Memory is charged to the thread and its object.
+    Thread(usize),
+
+    /// Memory is charged to the object only.
+    Object(usize),
+}
+
+/// Service of loaded object.
+pub(crate) struct ServiceRecord {
+
+    /// ID of the object this service belongs to.
+    pub object: usize,
+
+    /// The name of this service.
+    pub name: String,
+
+    /// Entry point of the service.
+    pub entry: MyServiceEntry,
+}
+
+/// Element of the queue of the channel member. Message is charged
+/// to its sender until it gets received.
+#[derive(Clone)]
+pub(crate) enum Delivery {
+
+    Message(Message, MemoryOwner),
+
+    Notice(ChannelNotice),
+}
+
+/// Sequence numbers of the messages of some sender that were delivered
+/// over reliable channel. Numbers up to the floor were all delivered.
+/// Higher ones are kept until the gap below them gets filled.
+#[derive(Default)]
+pub(crate) struct Window {
+
+    /// Number up to which all the messages were delivered.
+    floor: u64,
+
+    /// Delivered numbers above the floor.
+    above: BTreeSet<u64>,
+}
+
+/// Channel and the queues of its members.
+pub(crate) struct ChannelRecord {
+
+    /// ID of the service that originated this channel.
+    pub origin: usize,
+
+    /// Channel policy.
+    pub policy: Policy,
+
+    /// Incoming messages of each connected thread.
+    pub members: BTreeMap<usize, VecDeque<Delivery>>,
+
+    /// Services that were invited to join the channel.
+    pub invited: BTreeSet<usize>,
+
+    /// Topic patterns of the members of publish-subscribe channel.
+    /// Members without patterns receive all messages.
+    pub filters: BTreeMap<usize, Vec<TopicPattern>>,
+
+    /// Last messages published over publish-subscribe channel. They
+    /// are replayed to the new members.
+    pub history: VecDeque<Message>,
+
+    /// Messages of the work queue that were received by each member
+    /// but not acknowledged yet. They stay charged to their senders.
+    pub unacked: BTreeMap<usize, Vec<(Message, MemoryOwner)>>,
+
+    /// ID of the member that received the last message of the work
+    /// queue.
+    pub cursor: usize,
+
+    /// Last sequence number used by each sender of reliable channel.
+    pub sequences: BTreeMap<usize, u64>,
+
+    /// Sequence numbers of reliable channel delivered to each receiver,
+    /// keyed by the sender and the receiver. Members of the work queue
+    /// share the window without the receiver because each message
+    /// is meant for any one of them.
+    pub delivered: BTreeMap<(usize, Option<usize>), Window>,
+
+    /// Messages of reliable work queue that the members left without
+    /// acknowledging and no other member could take. They stay charged
+    /// to their senders and are queued to the next member that joins.
+    pub orphans: VecDeque<(Message, MemoryOwner)>,
+}
+
+/// Thread of some service.
+pub(crate) struct ThreadRecord {
+
+    /// ID of the service this thread runs.
+    pub service: usize,
+
+    /// Current state of the thread.
+    pub state: ThreadState,
+
+    /// Channels this thread is connected to.
+    pub channels: BTreeSet<usize>,
+
+    /// Handle table of the thread. Maps descriptors to channel IDs.
+    pub handles: BTreeMap<Descriptor, usize>,
+
+    /// Descriptors of the channels the thread was disconnected from.
+    /// Their numbers stay taken until the thread closes them.
+    pub closed: BTreeSet<Descriptor>,
+
+    /// Channel which was passed to the entry point of the service.
+    /// Main thread of the application does not have it.
+    pub start_channel: Option<usize>,
+
+    /// Whether the OS thread of this thread still runs.
+    pub running: bool,
+
+    /// Whether the thread was replaced by the supervisor. Such thread
+    /// is not connected to any channel and its exit is ignored.
+    pub superseded: bool,
+
+    /// Position of the thread in the start order. Thread that replaces
+    /// other one takes its position.
+    pub order: usize,
+
+    /// Fault the thread gets when it exits even if its entry point
+    /// returns normally.
+    pub fault: Option<String>,
+
+    /// Entry point the thread runs.
+    pub entry: MyServiceEntry,
+
+    /// Whether the thread was requested to cancel.
+    pub cancelled: bool,
+
+    /// Whether the thread was requested to suspend.
+    pub suspended: bool,
+
+    /// Memory used by the thread.
+    pub memory: MemoryRecord,
+
+    /// Count of the thread instances that refer to this thread. Record
+    /// of the exited thread is kept while there are any, so its final
+    /// state can still be joined.
+    pub watchers: usize,
+}
+
+/// Grant of the memory region.
+pub(crate) struct GrantRecord {
+
+    /// ID of the granted region.
+    pub region: usize,
+
+    /// Bytes of the region. Must not be locked while the registry
+    /// is locked, region access runs closures that may lock it.
+    pub data: Arc<RwLock<Vec<u8>>>,
+
+    /// Thread that gave the grant.
+    pub grantor: usize,
+
+    /// Grant through which the grantor accesses the region. None if
+    /// the grantor owns the region.
+    pub parent: Option<usize>,
+
+    /// Access given to the holders.
+    pub access: Access,
+
+    /// Threads that received the grant.
+    pub holders: BTreeSet<usize>,
+
+    /// Whether the grant was sent over a channel.
+    pub sent: bool,
+}
+
+/// Element which threads are supervised.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub(crate) enum SupervisedKey {
+
+    Object(usize),
+
+    Service(usize),
+}
+
+/// Supervisor and the times of its recent restarts.
+pub(crate) struct SupervisorRecord {
+
+    pub supervisor: Supervisor,
+
+    /// Times of the restarts in the intensity period.
+    pub restarts: VecDeque<Instant>,
+}
+
+/// Thread that replaces failed one and must be started.
+pub(crate) struct Restart {
+
+    /// ID of the new thread.
+    thread: usize,
+
+    /// Channel to pass to the entry point.
+    channel: usize,
+
+    /// Entry point of the service.
+    entry: MyServiceEntry,
+
+    /// Delay before the entry point gets started.
+    delay: Duration,
+
+    /// Thread that is replaced and the generation of its ID. Entry point
+    /// is not started until the replaced thread exits or the shutdown
+    /// time passes.
+    replaces: Option<(usize, usize)>,
+
+    /// Time the replaced thread gets to exit.
+    shutdown: Duration,
+}
+
+/// All the registered elements of the runtime.
+#[derive(Default)]
+pub(crate) struct Registry {
+
+    pub objects: BTreeMap<usize, ObjectRecord>,
+
+    pub services: BTreeMap<usize, ServiceRecord>,
+
+    pub channels: BTreeMap<usize, ChannelRecord>,
+
+    pub threads: BTreeMap<usize, ThreadRecord>,
+
+    /// Copies of implementer sources that are used to load objects.
+    pub implementer_sources: Vec<MyImplementerSource>,
+
+    /// Supervisors of objects and services.
+    pub supervisors: BTreeMap<SupervisedKey, SupervisorRecord>,
+
+    /// Grants of the memory regions.
+    pub grants: BTreeMap<usize, GrantRecord>,
+
+    /// IDs of threads. They get reused after the threads are removed.
+    pub thread_ids: IdAllocator,
+
+    /// IDs of channels. They get reused after the channels are closed.
+    pub channel_ids: IdAllocator,
+
+    /// IDs of the removed threads which OS threads still run. They
+    /// get released when the threads exit, so the exiting thread
+    /// cannot affect other one that took its ID.
+    pub detached: BTreeSet<usize>,
+
+    /// Generations of loaded objects and services. Their IDs are given
+    /// by implementers so they are the same each time the object gets
+    /// loaded.
+    pub instances: IdAllocator,
+
+    /// Position of the next started thread in the start order.
+    pub next_order: usize,
+
+    /// Events of the channels that may have appeared since the last
+    /// notification. Futures waiting for them get woken.
+    pub changed: BTreeSet<(usize, ChannelEvent)>,
+
+    /// Signals of the event descriptors by the IDs of the thread
+    /// and the channel.
+    #[cfg(unix)]
+    pub signals: BTreeMap<(usize, usize), Vec<Arc<ReadySignal>>>,
+}
+
+impl Runtime {
+
+    /// Create new runtime with empty registry.
+    pub fn new() -> Arc<Self> {
+        Arc::new(Runtime {
+            registry: Default::default(),
+            changed: Condvar::new(),
+            wakers: Default::default(),
+        })
+    }
+
+    /// Lock the registry. Service threads never panic while holding
+    /// the lock so the poisoning is ignored.
+    pub fn lock(&self) -> MutexGuard<'_, Registry> {
+        self.registry.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Wait for some change in the registry.
+    pub fn wait<'a>(&self, guard: MutexGuard<'a, Registry>)
+            -> MutexGuard<'a, Registry> {
+        self.changed.wait(guard).unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Wait for some change in the registry but no longer than given
+    /// timeout. Returns whether timeout has passed.
+    pub fn wait_timeout<'a>(&self, guard: MutexGuard<'a, Registry>,
+            timeout: Duration) -> (MutexGuard<'a, Registry>, bool) {
+        let (guard, result) = self.changed.wait_timeout(guard, timeout)
+                .unwrap_or_else(PoisonError::into_inner);
+        (guard, result.timed_out())
+    }
+
+    /// Unlock the registry and notify all waiting threads about
+    /// the change in it. Pending futures are woken only if the events
+    /// they wait for may have appeared. Wakers run after the locks
+    /// are released, so they may poll the futures right away.
+    pub fn notify(&self, mut reg: MutexGuard<'_, Registry>) {
+        let changed = ::std::mem::take(&mut reg.changed);
+        drop(reg);
+        self.changed.notify_all();
+        if changed.is_empty() {
+            return;
+        }
+
+        let woken: Vec<Waker> = {
+            let mut wakers = self.wakers.lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+            changed.iter()
+                    .filter_map(|key| wakers.remove(key))
+                    .flatten()
+                    .collect()
+        };
+        for waker in woken {
+            waker.wake();
+        }
+    }
+
+    /// Wake the future when given event of the channel may have appeared.
+    /// Future checks its condition in the same locked registry, so
+    /// the earlier changes are seen already and no change is missed.
+    pub fn register(&self, reg: &mut Registry, channel: usize,
+            event: ChannelEvent, waker: &Waker) {
+        reg.changed.remove(&(channel, event));
+        let mut wakers = self.wakers.lock()
+                .unwrap_or_else(PoisonError::into_inner);
+        let wakers = wakers.entry((channel, event)).or_default();
+        if !wakers.iter().any(|other| other.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+
+    /// Spawn new thread of given service that runs given entry point with
+    /// the channel connected to the thread. ID of the new thread
+    /// is returned. If the thread is watched, its record counts
+    /// the instance the caller creates for it and is kept until
+    /// the instance is dropped.
+    pub fn spawn(this: &Arc<Self>, service: usize, channel: usize,
+            entry: MyServiceEntry, watched: bool) -> Option<usize> {
+        let id = {
+            let mut reg = this.lock();
+            let id = reg.thread_ids.allocate().0;
+            let mut rec = ThreadRecord::new(service, entry.clone(),
+                    Some(channel));
+            rec.watchers = watched as usize;
+            reg.add_thread(id, rec);
+            reg.connect(id, channel);
+            id
+        };
+
+        let restart = Restart {
+            thread: id,
+            channel,
+            entry,
+            delay: Duration::from_secs(0),
+            replaces: None,
+            shutdown: Duration::from_secs(0),
+        };
+        if Self::start(this, restart) {
+            return Some(id);
+        }
+
+        // Nobody gets the instance of the thread that has not started.
+        let mut reg = this.lock();
+        if let Some(rec) = reg.threads.get_mut(&id) {
+            rec.watchers = 0;
+        }
+        reg.reap(id);
+        None
+    }
+
+    /// Start the OS thread for registered thread. If it fails, the thread
+    /// gets finished.
+    fn start(this: &Arc<Self>, restart: Restart) -> bool {
+        let id = restart.thread;
+        let name = {
+            let reg = this.lock();
+            reg.service_path(reg.threads[&id].service)
+        };
+
+        let runtime = this.clone();
+        let result = ::std::thread::Builder::new()
+                .name(name)
+                .spawn(move || Self::run(runtime, restart));
+        if result.is_err() {
+            let mut reg = this.lock();
+            reg.finish(id);
+            reg.exited(id);
+            this.notify(reg);
+            return false;
+        }
+        true
+    }
+
+    /// Body of the spawned thread. Panic of the entry point is caught
+    /// so it does not affect other threads.
+    fn run(this: Arc<Self>, restart: Restart) {
+        let id = restart.thread;
+        CURRENT.with(|cur| *cur.borrow_mut() = Some((this.clone(), id)));
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            Self::enter(&this, restart)
+        }));
+        let fault = result.err().map(|payload| panic_message(&*payload));
+
+        Self::exit(&this, id, fault);
+        CURRENT.with(|cur| *cur.borrow_mut() = None);
+    }
+
+    /// Charge given count of bytes to the owner. Error is returned
+    /// if the quota is exceeded. If the policy says so, the owning
+    /// thread is also cancelled and faults when it exits. Caller may
+    /// run on any thread, so it is never panicked.
+    pub fn charge<'a>(&self, mut reg: MutexGuard<'a, Registry>,
+            owner: MemoryOwner, bytes: usize)
+            -> MemoryResult<MutexGuard<'a, Registry>> {
+        match reg.charge(owner, bytes) {
+            Ok(()) => Ok(reg),
+            Err(err) => {
+                if reg.quota_policy(owner) == QuotaPolicy::Fault {
+                    if let MemoryOwner::Thread(id) = owner {
+                        reg.fault(id, "memory quota exceeded");
+                    }
+                    self.notify(reg);
+                }
+                Err(err)
+            },
+        }
+    }
+
+    /// Block the thread while it is suspended.
+    pub fn park<'a>(&self, mut reg: MutexGuard<'a, Registry>, thread: usize)
+            -> MutexGuard<'a, Registry> {
+        let mut parked = false;
+        loop {
+            match reg.threads.get_mut(&thread) {
+                Some(rec) if rec.suspended && !rec.state.is_finished() => {
+                    rec.state = ThreadState::Suspended;
+                    parked = true;
+                },
+                Some(rec) => {
+                    if parked && !rec.state.is_finished() {
+                        rec.state = ThreadState::Active;
+                    }
+                    return reg;
+                },
+                None => return reg,
+            }
+            reg = self.wait(reg);
+        }
+    }
+
+    /// Wait for the replaced thread to exit and for the restart delay.
+    /// Then call the entry point. Replaced thread that does not exit
+    /// in its shutdown time is left running.
+    fn enter(this: &Arc<Self>, restart: Restart) {
+        if let Some((old, generation)) = restart.replaces {
+            let due = Instant::now() + restart.shutdown;
+            let mut reg = this.lock();
+            loop {
+                let running = reg.thread_ids.is_current(old, generation)
+                        && reg.threads.get(&old)
+                                .is_some_and(|rec| !rec.state.is_finished());
+                let now = Instant::now();
+                if !running || now >= due {
+                    break;
+                }
+                reg = this.wait_timeout(reg, due - now).0;
+            }
+        }
+        if restart.delay > Duration::from_secs(0) {
+            ::std::thread::sleep(restart.delay);
+        }
+
+        let handle = {
+            let reg = this.lock();
+            if !reg.channels.contains_key(&restart.channel) {
+                // Channel was closed before the thread started.
+                return;
+            }
+            reg.channel_handle(restart.channel)
+        };
+        restart.entry.start(&handle);
+    }
+
+    /// Handle the exit of the thread. Peers of the faulted thread get
+    /// notified and the thread gets restarted by its supervisor if any.
+    fn exit(this: &Arc<Self>, thread: usize, fault: Option<String>) {
+        let restarts = {
+            let mut reg = this.lock();
+            let (superseded, fault) = match reg.threads.get_mut(&thread) {
+                Some(rec) => (rec.superseded, fault.or(rec.fault.take())),
+                None => {
+                    reg.exited(thread);
+                    return;
+                },
+            };
+
+            let restarts = if fault.is_some() && !superseded {
+                reg.notify_peers(thread, ChannelNotice::PeerFault(thread));
+                reg.supervise(thread)
+            } else {
+                None
+            };
+            if restarts.is_none() && !superseded {
+                reg.finish(thread);
+            }
+
+            reg.threads.get_mut(&thread).unwrap().state = match fault {
+                Some(reason) => ThreadState::Faulted(reason),
+                None => ThreadState::Dead,
+            };
+            reg.release_grants(thread);
+            reg.exited(thread);
+            this.notify(reg);
+            restarts
+        };
+
+        for restart in restarts.unwrap_or_default() {
+            Self::start(this, restart);
+        }
+    }
+}
+
+impl MemoryOwner {
+
+    /// ID of the thread that sent the message charged to this owner.
+    /// Messages charged to the object have no sender thread.
+    pub fn sender(self) -> usize {
+        match self {
+            MemoryOwner::Thread(id) => id,
+            MemoryOwner::Object(_) => usize::MAX,
+        }
+    }
+}
+
+impl Window {
+
+    /// Window of the member that is offered the messages of the sender
+    /// starting from given sequence number. Member that joined later
+    /// does not get the earlier messages.
+    fn starting(sequence: u64) -> Self {
+        Window {
+            floor: sequence.saturating_sub(1),
+            above: Default::default(),
+        }
+    }
+
+    /// Whether the message with given sequence number was delivered.
+    fn contains(&self, sequence: u64) -> bool {
+        sequence <= self.floor || self.above.contains(&sequence)
+    }
+
+    /// Record that the message with given sequence number was delivered.
+    fn insert(&mut self, sequence: u64) {
+        if sequence > self.floor {
+            self.above.insert(sequence);
+        }
+        while self.above.remove(&(self.floor + 1)) {
+            self.floor += 1;
+        }
+    }
+}
+
+impl ChannelRecord {
+
+    /// Count of the messages in the queue of the member. Notices
+    /// are not limited by the capacity so they are not counted.
+    pub fn occupancy(&self, thread: usize) -> usize {
+        self.members.get(&thread)
+                .map(|queue| queue.iter()
+                        .filter(|d| matches!(**d, Delivery::Message(..)))
+                        .count())
+                .unwrap_or(0)
+    }
+
+    /// Whether all members except the sender have room for one more
+    /// message. Work queue needs room of any single member.
+    pub fn has_room(&self, sender: usize) -> bool {
+        let receivers: Vec<usize> = self.members.keys().cloned()
+                .filter(|&id| id != sender)
+                .collect();
+        if self.policy.work_queue() {
+            receivers.is_empty()
+                    || receivers.iter().any(|&id| self.fits(&[id]))
+        } else {
+            self.fits(&receivers)
+        }
+    }
+
+    /// Count of the messages the member of the work queue has to handle.
+    pub fn load(&self, thread: usize) -> usize {
+        let unacked = self.unacked.get(&thread).map_or(0, Vec::len);
+        self.occupancy(thread) + unacked
+    }
+
+    /// Whether all given members have room for one more message.
+    pub fn fits(&self, receivers: &[usize]) -> bool {
+        let capacity = match self.policy.capacity() {
+            Some(capacity) => capacity,
+            None => return true,
+        };
+        receivers.iter().all(|&id| self.occupancy(id) < capacity)
+    }
+
+    /// Whether the member accepts the message. Members
+    /// of publish-subscribe channel accept messages without topic
+    /// and those which topics match their patterns.
+    pub fn accepts(&self, thread: usize, msg: &Message) -> bool {
+        let topic = match msg.topic() {
+            Some(topic) => topic,
+            None => return true,
+        };
+        match self.filters.get(&thread) {
+            Some(patterns) => patterns.iter().any(|p| p.matches(&topic)),
+            None => true,
+        }
+    }
+
+    /// Members except the sender that accept the message.
+    pub fn receivers(&self, sender: usize, msg: &Message) -> Vec<usize> {
+        self.members.keys().cloned()
+                .filter(|&id| id != sender && self.accepts(id, msg))
+                .collect()
+    }
+
+    /// Members that receive the message according to the distribution
+    /// of the channel. Work queue prefers the members with room
+    /// for the message.
+    pub fn select(&self, sender: usize, msg: &Message) -> Vec<usize> {
+        let receivers = self.receivers(sender, msg);
+        let free: Vec<usize> = receivers.iter().cloned()
+                .filter(|&id| self.fits(&[id]))
+                .collect();
+        let candidates = if free.is_empty() { &receivers } else { &free };
+
+        let chosen = match self.policy.distribution() {
+            Distribution::Broadcast => return receivers,
+            Distribution::RoundRobin => {
+                candidates.iter().cloned()
+                        .find(|&id| id > self.cursor)
+                        .or_else(|| candidates.first().cloned())
+            },
+            Distribution::LeastLoaded => {
+                candidates.iter().cloned().min_by_key(|&id| self.load(id))
+            },
+        };
+        chosen.into_iter().collect()
+    }
+
+    /// Members that get the message sent now. Reliable channel skips
+    /// the receivers which already got the message with its sequence
+    /// number. Messages queued again after their receiver left or got
+    /// restarted do not pass here, so they are never skipped.
+    pub fn targets(&self, sender: usize, msg: &Message) -> Vec<usize> {
+        let mut receivers = self.select(sender, msg);
+        receivers.retain(|&id| !self.is_duplicate(sender, id, msg));
+        receivers
+    }
+
+    /// Copy of the message numbered by reliable channel. None
+    /// if the channel does not number the messages or the sender
+    /// has numbered the message itself. Sender can only repeat its
+    /// numbers or use the next one.
+    pub fn stamp(&self, sender: usize, msg: &Message)
+            -> ChannelResult<Option<Message>> {
+        if !self.policy.reliable() {
+            return Ok(None);
+        }
+        let last = self.sequences.get(&sender).cloned().unwrap_or(0);
+        match msg.sequence() {
+            None => Ok(Some(msg.clone().with_sequence(last + 1))),
+            Some(sequence) if sequence == 0 || sequence > last + 1 => {
+                Err(ChannelError::InvalidSequence)
+            },
+            Some(_) => Ok(None),
+        }
+    }
+
+    /// Key of the window of delivered sequence numbers.
+    fn window(&self, sender: usize, receiver: usize)
+            -> (usize, Option<usize>) {
+        if self.policy.work_queue() {
+            (sender, None)
+        } else {
+            (sender, Some(receiver))
+        }
+    }
+
+    /// Whether the receiver already got the numbered message.
+    fn is_duplicate(&self, sender: usize, receiver: usize, msg: &Message)
+            -> bool {
+        let sequence = match msg.sequence() {
+            Some(sequence) if self.policy.reliable() => sequence,
+            _ => return false,
+        };
+        let key = self.window(sender, receiver);
+        self.delivered.get(&key)
+                .is_some_and(|window| window.contains(sequence))
+    }
+
+    /// Highest sequence number up to which all the messages
+    /// of the sender over reliable channel were acknowledged. Message
+    /// is not acknowledged while it is queued, received or left
+    /// by the member of the work queue. Messages dropped on overflow
+    /// or left by the member of broadcast channel are not waited for.
+    pub fn acknowledged(&self, sender: usize) -> Option<u64> {
+        let last = *self.sequences.get(&sender)?;
+        let owner = MemoryOwner::Thread(sender);
+        let queued = self.members.values().flatten()
+                .filter_map(|delivery| match *delivery {
+                    Delivery::Message(ref msg, from) => Some((msg, from)),
+                    Delivery::Notice(_) => None,
+                });
+        let taken = self.unacked.values().flatten().chain(&self.orphans)
+                .map(|&(ref msg, from)| (msg, from));
+        let pending = queued.chain(taken)
+                .filter(|&(_, from)| from == owner)
+                .filter_map(|(msg, _)| msg.sequence())
+                .min();
+        match pending {
+            Some(pending) => pending.checked_sub(1).filter(|&seq| seq > 0),
+            None => Some(last),
+        }
+    }
+
+    /// Record that the numbered message was offered to the members.
+    /// Members that do not accept it will not get it later either.
+    /// Window of the member starts at the first message it is offered.
+    pub fn confirm(&mut self, sender: usize, msg: &Message) {
+        let sequence = match msg.sequence() {
+            Some(sequence) if self.policy.reliable() => sequence,
+            _ => return,
+        };
+        let last = self.sequences.entry(sender).or_insert(0);
+        *last = (*last).max(sequence);
+
+        let keys: BTreeSet<(usize, Option<usize>)> = self.members.keys()
+                .filter(|&&id| id != sender)
+                .map(|&id| self.window(sender, id))
+                .collect();
+        for key in keys {
+            self.delivered.entry(key)
+                    .or_insert_with(|| Window::starting(sequence))
+                    .insert(sequence);
+        }
+    }
+
+    /// Move the state of the member to the thread that replaces it.
+    pub fn rename(&mut self, old: usize, new: usize) {
+        if let Some(filters) = self.filters.remove(&old) {
+            self.filters.insert(new, filters);
+        }
+        if let Some(unacked) = self.unacked.remove(&old) {
+            self.unacked.insert(new, unacked);
+        }
+        if let Some(last) = self.sequences.remove(&old) {
+            self.sequences.insert(new, last);
+        }
+
+        let rename = |id: usize| if id == old { new } else { id };
+        self.delivered = ::std::mem::take(&mut self.delivered).into_iter()
+                .map(|((from, to), window)| {
+                    ((rename(from), to.map(rename)), window)
+                })
+                .collect();
+    }
+
+    /// Forget the state of the member that left the channel.
+    pub fn forget(&mut self, thread: usize) {
+        self.filters.remove(&thread);
+        self.sequences.remove(&thread);
+        self.delivered.retain(|&(from, to), _| {
+            from != thread && to != Some(thread)
+        });
+    }
+
+    /// Remember the published message to replay it to the new members.
+    /// Messages that pass descriptors or grants are not remembered.
+    pub fn remember(&mut self, msg: &Message) {
+        let replay = self.policy.replay();
+        let plain = msg.descriptors().is_empty() && msg.grants().is_empty();
+        if replay == 0 || !plain {
+            return;
+        }
+
+        self.history.push_back(msg.clone());
+        while self.history.len() > replay {
+            self.history.pop_front();
+        }
+    }
+
+    /// Drop the oldest message of the member to make room for the new
+    /// one. Sender and size of the dropped message are returned.
+    pub fn drop_oldest(&mut self, thread: usize)
+            -> Option<(MemoryOwner, usize)> {
+        let queue = self.members.get_mut(&thread)?;
+        let index = queue.iter()
+                .position(|d| matches!(*d, Delivery::Message(..)))?;
+        match queue.remove(index) {
+            Some(Delivery::Message(msg, sender)) => {
+                Some((sender, msg.data().len()))
+            },
+            _ => None,
+        }
+    }
+}
+
+impl ThreadRecord {
+
+    /// Record of new active thread which is not connected to any channel.
+    pub fn new(service: usize, entry: MyServiceEntry,
+            start_channel: Option<usize>) -> Self {
+        ThreadRecord {
+            service,
+            state: ThreadState::Active,
+            channels: Default::default(),
+            handles: Default::default(),
+            closed: Default::default(),
+            running: start_channel.is_some(),
+            start_channel,
+            superseded: false,
+            order: 0,
+            fault: None,
+            entry,
+            cancelled: false,
+            suspended: false,
+            memory: Default::default(),
+            watchers: 0,
+        }
+    }
+}
+
+impl ThreadRecord {
+
+    /// Channel the descriptor refers to.
+    pub fn channel(&self, descriptor: Descriptor) -> ChannelResult<usize> {
+        if self.closed.contains(&descriptor) {
+            return Err(ChannelError::Disconnected);
+        }
+        self.handles.get(&descriptor)
+                .cloned()
+                .ok_or(ChannelError::BadDescriptor)
+    }
+
+    /// Add the descriptor of the channel to the handle table. Lowest
+    /// free number is used.
+    pub fn add_descriptor(&mut self, channel: usize) -> Descriptor {
+        let descriptor = (0..)
+                .map(Descriptor::new)
+                .find(|d| {
+                    !self.handles.contains_key(d) && !self.closed.contains(d)
+                })
+                .unwrap();
+        self.handles.insert(descriptor, channel);
+        descriptor
+    }
+
+    /// Whether the thread has some descriptor of the channel.
+    pub fn holds(&self, channel: usize) -> bool {
+        self.handles.values().any(|&c| c == channel)
+    }
+
+    /// Count of the descriptors of the channel.
+    pub fn descriptor_count(&self, channel: usize) -> usize {
+        self.handles.values().filter(|&&c| c == channel).count()
+    }
+}
+
+impl MemoryRecord {
+
+    /// Whether given count of bytes can be used additionally.
+    fn fits(&self, bytes: usize) -> bool {
+        self.limit.is_none_or(|limit| {
+            self.usage.checked_add(bytes).is_some_and(|sum| sum <= limit)
+        })
+    }
+
+    /// Use given count of bytes additionally.
+    fn add(&mut self, bytes: usize) {
+        self.usage += bytes;
+        self.peak = self.peak.max(self.usage);
+    }
+}
+
+impl Registry {
+
+    /// Register the object of given implementer if it is not loaded yet.
+    pub fn load(&mut self, implementer: &Arc<MyImplementer>) {
+        if self.objects.contains_key(&implementer.id()) {
+            return;
+        }
+
+        self.instances.acquire(implementer.id());
+        for &(id, ref name, ref entry) in implementer.services() {
+            self.instances.acquire(id);
+            self.services.insert(id, ServiceRecord {
+                object: implementer.id(),
+                name: name.clone(),
+                entry: entry.clone(),
+            });
+        }
+        let memory = MemoryRecord {
+            limit: implementer.object_quota(),
+            ..Default::default()
+        };
+        self.objects.insert(implementer.id(), ObjectRecord {
+            implementer: implementer.clone(),
+            state: ObjectState::Loading,
+            memory,
+        });
+    }
+
+    /// Register the thread. Its memory limit is taken from the object.
+    pub fn add_thread(&mut self, id: usize, mut rec: ThreadRecord) {
+        let object = self.services[&rec.service].object;
+        rec.memory.limit = self.objects[&object].implementer.thread_quota();
+        rec.order = self.next_order;
+        self.next_order += 1;
+        self.threads.insert(id, rec);
+    }
+
+    /// Object of the memory owner if it still is loaded.
+    fn owner_object(&self, owner: MemoryOwner) -> Option<usize> {
+        let object = match owner {
+            MemoryOwner::Thread(id) => {
+                let service = self.threads.get(&id)?.service;
+                self.services.get(&service)?.object
+            },
+            MemoryOwner::Object(id) => id,
+        };
+        if self.objects.contains_key(&object) {
+            Some(object)
+        } else {
+            None
+        }
+    }
+
+    /// Charge given count of bytes to the owner. Error is returned
+    /// if the quota of the thread or the object would be exceeded.
+    pub fn charge(&mut self, owner: MemoryOwner, bytes: usize)
+            -> MemoryResult<()> {
+        let object = self.owner_object(owner);
+        let thread = match owner {
+            MemoryOwner::Thread(id) => self.threads.get(&id).map(|_| id),
+            MemoryOwner::Object(_) => None,
+        };
+
+        let thread_fits = thread.is_none_or(|id| {
+            self.threads[&id].memory.fits(bytes)
+        });
+        let object_fits = object.is_none_or(|id| {
+            self.objects[&id].memory.fits(bytes)
+        });
+        if !thread_fits || !object_fits {
+            return Err(MemoryError::QuotaExceeded);
+        }
+
+        if let Some(id) = thread {
+            self.threads.get_mut(&id).unwrap().memory.add(bytes);
+        }
+        if let Some(id) = object {
+            self.objects.get_mut(&id).unwrap().memory.add(bytes);
+        }
+        Ok(())
+    }
+
+    /// Release given count of bytes charged to the owner.
+    pub fn release(&mut self, owner: MemoryOwner, bytes: usize) {
+        if let Some(id) = self.owner_object(owner) {
+            let memory = &mut self.objects.get_mut(&id).unwrap().memory;
+            memory.usage = memory.usage.saturating_sub(bytes);
+        }
+        if let MemoryOwner::Thread(id) = owner {
+            if let Some(rec) = self.threads.get_mut(&id) {
+                rec.memory.usage = rec.memory.usage.saturating_sub(bytes);
+            }
+            self.reap(id);
+        }
+    }
+
+    /// Make the thread fault when it exits and cancel it so it exits
+    /// soon. Main thread of the application is never faulted because
+    /// the runtime does not run it.
+    pub fn fault(&mut self, thread: usize, reason: &str) {
+        if let Some(rec) = self.threads.get_mut(&thread) {
+            if rec.start_channel.is_some() && !rec.state.is_finished() {
+                rec.fault.get_or_insert_with(|| reason.to_string());
+                rec.cancelled = true;
+                rec.suspended = false;
+            }
+        }
+        self.touch_thread(thread);
+    }
+
+    /// What happens when the owner exceeds its quota.
+    pub fn quota_policy(&self, owner: MemoryOwner) -> QuotaPolicy {
+        match self.owner_object(owner) {
+            Some(id) => self.objects[&id].implementer.quota_policy(),
+            None => QuotaPolicy::Fail,
+        }
+    }
+
+    /// Remove the object, its services and their threads. Channels
+    /// originated by the services get closed.
+    pub fn unload(&mut self, object: usize) {
+        let services: BTreeSet<usize> = self.services.iter()
+                .filter(|&(_, rec)| rec.object == object)
+                .map(|(&id, _)| id)
+                .collect();
+
+        let threads: Vec<usize> = self.threads.iter()
+                .filter(|&(_, rec)| services.contains(&rec.service))
+                .map(|(&id, _)| id)
+                .collect();
+        for id in threads {
+            self.finish(id);
+            if self.threads.remove(&id).unwrap().running {
+                self.detached.insert(id);
+            } else {
+                self.thread_ids.release(id);
+            }
+        }
+
+        let channels: Vec<usize> = self.channels.iter()
+                .filter(|&(_, rec)| services.contains(&rec.origin))
+                .map(|(&id, _)| id)
+                .collect();
+        for id in channels {
+            self.close(id);
+        }
+
+        for id in services {
+            self.services.remove(&id);
+            self.supervisors.remove(&SupervisedKey::Service(id));
+            self.instances.release(id);
+        }
+        self.objects.remove(&object);
+        self.instances.release(object);
+        self.supervisors.remove(&SupervisedKey::Object(object));
+    }
+
+    /// Switch the state of the object.
+    pub fn switch(&mut self, object: usize, to: ObjectState)
+            -> ObjectResult<()> {
+        let rec = self.objects.get_mut(&object)
+                .ok_or(ObjectError::NotFound)?;
+        rec.state = rec.state.switch(to)?;
+        Ok(())
+    }
+
+    /// Threads of the object services that are not dead yet.
+    pub fn object_threads(&self, object: usize) -> Vec<usize> {
+        self.threads.iter()
+                .filter(|&(_, rec)| !rec.state.is_finished())
+                .filter(|&(_, rec)| self.services[&rec.service].object == object)
+                .map(|(&id, _)| id)
+                .collect()
+    }
+
+    /// Handle the abnormal exit of the thread by its supervisors.
+    /// Threads that must be started are returned. None is returned when
+    /// there is no supervisor or all of them gave up.
+    pub fn supervise(&mut self, thread: usize) -> Option<Vec<Restart>> {
+        let service = self.threads[&thread].service;
+        let object = self.services[&service].object;
+        if self.objects[&object].state != ObjectState::Running {
+            return None;
+        }
+
+        let now = Instant::now();
+        let keys = [
+            SupervisedKey::Service(service),
+            SupervisedKey::Object(object),
+        ];
+        for &key in &keys {
+            let (supervisor, recent) = match self.supervisors.get_mut(&key) {
+                Some(rec) => {
+                    let period = rec.supervisor.period();
+                    while rec.restarts.front()
+                            .is_some_and(|&time| now - time > period) {
+                        rec.restarts.pop_front();
+                    }
+
+                    let recent = rec.restarts.len();
+                    if !rec.supervisor.allows(recent) {
+                        // Pass the failure to the parent supervisor.
+                        continue;
+                    }
+                    rec.restarts.push_back(now);
+                    (rec.supervisor, recent)
+                },
+                None => continue,
+            };
+
+            let children = self.supervised_threads(key, thread);
+            let order = self.threads[&thread].order;
+            let restarted: Vec<usize> = match supervisor.strategy() {
+                RestartStrategy::OneForOne => vec![thread],
+                RestartStrategy::OneForAll => children,
+                RestartStrategy::RestForOne => {
+                    children.into_iter()
+                            .filter(|id| self.threads[id].order >= order)
+                            .collect()
+                },
+            };
+
+            let delay = supervisor.backoff(recent);
+            let restarts = restarted.into_iter()
+                    .map(|old| self.replace(old, delay, supervisor.shutdown()))
+                    .collect();
+            return Some(restarts);
+        }
+
+        // Object supervisor gave up so the object fails.
+        if self.supervisors.contains_key(&SupervisedKey::Object(object)) {
+            self.objects.get_mut(&object).unwrap().state = ObjectState::Failed;
+            for id in self.object_threads(object) {
+                if id != thread {
+                    self.disconnect_all(id, ChannelNotice::Shutdown(id));
+                    self.cancel(id);
+                }
+            }
+        }
+        None
+    }
+
+    /// Threads supervised by given key. Failed thread is included.
+    fn supervised_threads(&self, key: SupervisedKey, failed: usize)
+            -> Vec<usize> {
+        self.threads.iter()
+                .filter(|&(_, rec)| rec.start_channel.is_some())
+                .filter(|&(&id, rec)| {
+                    id == failed
+                    || (!rec.superseded && !rec.state.is_finished())
+                })
+                .filter(|&(_, rec)| match key {
+                    SupervisedKey::Service(id) => rec.service == id,
+                    SupervisedKey::Object(id) => {
+                        self.services[&rec.service].object == id
+                    },
+                })
+                .map(|(&id, _)| id)
+                .collect()
+    }
+
+    /// Replace the thread by new one of the same service. New thread gets
+    /// all the channels of the old one along with their queues. Other
+    /// members of the channels get restart notice. Old thread that still
+    /// runs is cancelled and the new one starts after it exits or its
+    /// shutdown time passes.
+    fn replace(&mut self, old: usize, delay: Duration, shutdown: Duration)
+            -> Restart {
+        let generation = self.thread_ids.generation(old).unwrap_or(0);
+        let new = self.thread_ids.allocate().0;
+        let (service, channels, handles, closed, start_channel, entry,
+                order) = {
+            let rec = self.threads.get_mut(&old).unwrap();
+            rec.superseded = true;
+            rec.cancelled = true;
+            rec.suspended = false;
+            let channels = ::std::mem::take(&mut rec.channels);
+            let handles = ::std::mem::take(&mut rec.handles);
+            let closed = ::std::mem::take(&mut rec.closed);
+            let entry = rec.entry.clone();
+            (rec.service, channels, handles, closed, rec.start_channel, entry,
+                    rec.order)
+        };
+
+        let notice = ChannelNotice::Restarted { old, new };
+        for &channel in &channels {
+            let chan = self.channels.get_mut(&channel).unwrap();
+            let mut queue = chan.members.remove(&old).unwrap_or_default();
+
+            // Restarted thread gets its unacknowledged messages again.
+            let unacked = chan.unacked.remove(&old).unwrap_or_default();
+            for (msg, sender) in unacked.into_iter().rev() {
+                queue.push_front(Delivery::Message(msg, sender));
+            }
+            for q in chan.members.values_mut() {
+                q.push_back(Delivery::Notice(notice));
+            }
+            chan.members.insert(new, queue);
+            chan.rename(old, new);
+        }
+        self.signal_members(&channels, new);
+
+        let mut rec = ThreadRecord::new(service, entry.clone(), start_channel);
+        rec.channels = channels;
+        rec.handles = handles;
+        rec.closed = closed;
+        self.add_thread(new, rec);
+        self.threads.get_mut(&new).unwrap().order = order;
+
+        Restart {
+            thread: new,
+            channel: start_channel.unwrap(),
+            entry,
+            delay,
+            replaces: Some((old, generation)),
+            shutdown,
+        }
+    }
+
+    /// Full path of the service in string form.
+    pub fn service_path(&self, service: usize) -> String {
+        let rec = &self.services[&service];
+        let obj = &self.objects[&rec.object];
+        format!("{}{}{}", obj.implementer.path(), Path::SEPARATOR, rec.name)
+    }
+
+    /// Create new channel originated from given service.
+    pub fn new_channel(&mut self, origin: usize, policy: Policy) -> usize {
+        let id = self.channel_ids.allocate().0;
+        self.channels.insert(id, ChannelRecord {
+            origin,
+            policy,
+            members: Default::default(),
+            invited: Default::default(),
+            filters: Default::default(),
+            history: Default::default(),
+            unacked: Default::default(),
+            cursor: 0,
+            sequences: Default::default(),
+            delivered: Default::default(),
+            orphans: Default::default(),
+        });
+        id
+    }
+
+    /// Connect the thread to the channel. New descriptor of the channel
+    /// is added to the handle table of the thread and returned.
+    pub fn connect(&mut self, thread: usize, channel: usize) -> Descriptor {
+        self.touch(channel, &EVENTS);
+        let (joins, replay) = {
+            let chan = self.channels.get_mut(&channel).unwrap();
+            let joins = !chan.members.contains_key(&thread);
+            chan.members.entry(thread).or_default();
+            let replay = if joins {
+                chan.history.clone()
+            } else {
+                VecDeque::new()
+            };
+            (joins, replay)
+        };
+        self.replay(thread, channel, replay);
+        if joins {
+            self.adopt_orphans(thread, channel);
+        }
+
+        let rec = self.threads.get_mut(&thread).unwrap();
+        rec.channels.insert(channel);
+        rec.add_descriptor(channel)
+    }
+
+    /// Queue the remembered messages to the new member
+    /// of publish-subscribe channel. They are charged to the member
+    /// and replay stops when its quota is exceeded.
+    fn replay(&mut self, thread: usize, channel: usize,
+            history: VecDeque<Message>) {
+        let owner = MemoryOwner::Thread(thread);
+        for msg in history {
+            if self.charge(owner, msg.data().len()).is_err() {
+                break;
+            }
+            let queue = self.channels.get_mut(&channel).unwrap()
+                    .members.get_mut(&thread).unwrap();
+            queue.push_back(Delivery::Message(msg, owner));
+        }
+        self.signal(thread, channel);
+    }
+
+    /// Queue the messages that other members left without acknowledging
+    /// to the new member of reliable work queue. They were never taken
+    /// by the other members so they skip the deduplication. Messages
+    /// the member cannot receive wait for other member.
+    fn adopt_orphans(&mut self, thread: usize, channel: usize) {
+        let chan = self.channels.get_mut(&channel).unwrap();
+        let orphans = ::std::mem::take(&mut chan.orphans);
+        for (msg, sender) in orphans {
+            if sender.sender() == thread || !chan.accepts(thread, &msg) {
+                chan.orphans.push_back((msg, sender));
+                continue;
+            }
+            let queue = chan.members.get_mut(&thread).unwrap();
+            queue.push_back(Delivery::Message(msg, sender));
+        }
+        self.signal(thread, channel);
+    }
+
+    /// Check whether the thread can leave the channel.
+    pub fn can_leave(&self, thread: usize, channel: usize)
+            -> ChannelResult<()> {
+        let service = self.threads.get(&thread)
+                .map(|rec| rec.service)
+                .ok_or(ChannelError::NotMember)?;
+        let chan = self.channels.get(&channel)
+                .ok_or(ChannelError::NotMember)?;
+        if !chan.members.contains_key(&thread) {
+            return Err(ChannelError::NotMember);
+        }
+
+        let origin = chan.origin == service;
+        let others = chan.members.len() > 1;
+        if origin && others && !chan.policy.origin_can_leave() {
+            return Err(ChannelError::LeaveForbiden);
+        }
+        Ok(())
+    }
+
+    /// Move the descriptor of one thread to the handle table of other
+    /// thread. Other thread joins the channel if it is not a member yet.
+    /// New descriptor of the other thread is returned.
+    pub fn transfer(&mut self, from: usize, descriptor: Descriptor,
+            to: usize) -> ChannelResult<Descriptor> {
+        self.can_pass(from, &[descriptor], to)?;
+        Ok(self.move_descriptor(from, descriptor, to))
+    }
+
+    /// Pass the descriptors with the message from one thread to other.
+    /// Receiver gets invited to the channels. New descriptors
+    /// of the receiver are returned.
+    pub fn pass(&mut self, from: usize, descriptors: &[Descriptor],
+            to: usize) -> ChannelResult<Vec<Descriptor>> {
+        self.can_pass(from, descriptors, to)?;
+        let service = self.threads[&to].service;
+        let mut moved = Vec::with_capacity(descriptors.len());
+        for &descriptor in descriptors {
+            let channel = self.threads[&from].channel(descriptor)?;
+            moved.push(self.move_descriptor(from, descriptor, to));
+            self.channels.get_mut(&channel).unwrap().invited.insert(service);
+        }
+        Ok(moved)
+    }
+
+    /// Move the descriptor which was checked to be passed.
+    fn move_descriptor(&mut self, from: usize, descriptor: Descriptor,
+            to: usize) -> Descriptor {
+        let rec = &self.threads[&from];
+        let channel = rec.handles[&descriptor];
+        let last = rec.descriptor_count(channel) == 1;
+        let joins = !self.channels[&channel].members.contains_key(&to);
+
+        self.threads.get_mut(&from).unwrap().handles.remove(&descriptor);
+        if last && joins {
+            // Undelivered messages go to the new member.
+            let chan = self.channels.get_mut(&channel).unwrap();
+            let queue = chan.members.remove(&from).unwrap_or_default();
+            chan.members.insert(to, queue);
+            chan.rename(from, to);
+            self.threads.get_mut(&from).unwrap().channels.remove(&channel);
+        } else if last {
+            self.disconnect(from, channel);
+        }
+
+        let descriptor = self.connect(to, channel);
+        self.signal(to, channel);
+        descriptor
+    }
+
+    /// Check that the descriptors can be passed with the message from one
+    /// thread to other. Receiver joins the channels as if it was invited
+    /// by the sender, so the policy of each channel applies.
+    pub fn can_pass(&self, from: usize, descriptors: &[Descriptor],
+            to: usize) -> ChannelResult<()> {
+        let rec = self.threads.get(&from).ok_or(ChannelError::NotMember)?;
+        let alive = self.threads.get(&to)
+                .is_some_and(|rec| !rec.state.is_finished());
+        if !alive {
+            return Err(ChannelError::NoThread);
+        }
+
+        let mut channels = Vec::with_capacity(descriptors.len());
+        for (i, &descriptor) in descriptors.iter().enumerate() {
+            channels.push(rec.channel(descriptor)?);
+            if descriptors[..i].contains(&descriptor) {
+                return Err(ChannelError::BadDescriptor);
+            }
+        }
+
+        let passed: BTreeSet<usize> = channels.iter().cloned().collect();
+        for channel in passed {
+            let chan = self.channels.get(&channel)
+                    .ok_or(ChannelError::Disconnected)?;
+            let member = chan.members.contains_key(&from);
+            if !member && !chan.policy.invitation_from_not_member() {
+                return Err(ChannelError::InvitationForbiden);
+            }
+
+            // Sender leaves the channel if it passes all its descriptors.
+            let count = channels.iter().filter(|&&c| c == channel).count();
+            let last = rec.descriptor_count(channel) == count;
+            let joins = !chan.members.contains_key(&to);
+            let single = chan.policy.no_multiple_connectons();
+            if joins && !last && single && chan.members.len() >= 2 {
+                return Err(ChannelError::MultipleConnectionsForbiden);
+            }
+            if last && !joins {
+                self.can_leave(from, channel)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether receiving from the channel does not block the thread.
+    /// Message or notice is queued or all other members have left.
+    pub fn can_receive(&self, thread: usize, channel: usize) -> bool {
+        let chan = match self.channels.get(&channel) {
+            Some(chan) => chan,
+            None => return true,
+        };
+        match chan.members.get(&thread) {
+            Some(queue) => !queue.is_empty() || chan.members.len() < 2,
+            None => true,
+        }
+    }
+
+    /// Make the event descriptors of the thread readable if receiving
+    /// from the channel does not block anymore. Futures receiving
+    /// from the channel get woken.
+    pub fn signal(&mut self, thread: usize, channel: usize) {
+        self.touch(channel, &[ChannelEvent::Receive]);
+        #[cfg(unix)]
+        {
+            let signals = match self.signals.get(&(thread, channel)) {
+                Some(signals) => signals,
+                None => return,
+            };
+            if self.can_receive(thread, channel) {
+                for signal in signals {
+                    signal.raise();
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = (thread, channel);
+    }
+
+    /// Disconnect all members and remove the channel.
+    pub fn close(&mut self, channel: usize) {
+        let members: Vec<usize> = match self.channels.get(&channel) {
+            Some(rec) => rec.members.keys().cloned().collect(),
+            None => return,
+        };
+
+        for thread in members {
+            self.disconnect(thread, channel);
+        }
+        self.touch(channel, &EVENTS);
+        let orphans = self.channels.remove(&channel).unwrap().orphans;
+        for (msg, sender) in orphans {
+            self.release(sender, msg.data().len());
+        }
+        self.channel_ids.release(channel);
+    }
+
+    /// Disconnect the thread from the channel.
+    pub fn disconnect(&mut self, thread: usize, channel: usize) {
+        self.touch(channel, &EVENTS);
+        let (queue, unacked) = match self.channels.get_mut(&channel) {
+            Some(chan) => {
+                chan.forget(thread);
+                (chan.members.remove(&thread).unwrap_or_default(),
+                        chan.unacked.remove(&thread).unwrap_or_default())
+            },
+            None => Default::default(),
+        };
+        let messages = unacked.into_iter()
+                .chain(queue.into_iter().filter_map(|delivery| {
+                    match delivery {
+                        Delivery::Message(msg, sender) => Some((msg, sender)),
+                        Delivery::Notice(_) => None,
+                    }
+                }))
+                .collect();
+        self.redeliver(channel, messages);
+
+        if let Some(thr) = self.threads.get_mut(&thread) {
+            thr.channels.remove(&channel);
+            // Descriptors report the disconnection until they are closed.
+            let gone: Vec<Descriptor> = thr.handles.iter()
+                    .filter(|&(_, &c)| c == channel)
+                    .map(|(&descriptor, _)| descriptor)
+                    .collect();
+            for descriptor in gone {
+                thr.handles.remove(&descriptor);
+                thr.closed.insert(descriptor);
+            }
+        }
+
+        // Last member left alone does not block on receiving.
+        self.signal(thread, channel);
+        let alone = self.channels.get(&channel)
+                .filter(|chan| chan.members.len() == 1)
+                .and_then(|chan| chan.members.keys().next().cloned());
+        if let Some(member) = alone {
+            self.signal(member, channel);
+        }
+    }
+
+    /// Queue the messages of the member that left the work queue to other
+    /// members. Reliable work queue keeps the messages nobody else can
+    /// handle for the next member that joins. Otherwise they are
+    /// released. Messages of broadcast channel were meant for the member
+    /// that left only, so they are released too.
+    fn redeliver(&mut self, channel: usize,
+            messages: Vec<(Message, MemoryOwner)>) {
+        for (msg, sender) in messages {
+            let chan = match self.channels.get_mut(&channel) {
+                Some(chan) => chan,
+                None => {
+                    self.release(sender, msg.data().len());
+                    continue;
+                },
+            };
+
+            let work_queue = chan.policy.work_queue();
+            let receiver = if work_queue {
+                chan.select(sender.sender(), &msg).pop()
+            } else {
+                None
+            };
+            match receiver {
+                Some(receiver) => {
+                    chan.cursor = receiver;
+                    let queue = chan.members.get_mut(&receiver).unwrap();
+                    queue.push_back(Delivery::Message(msg, sender));
+                    self.signal(receiver, channel);
+                },
+                None if work_queue && chan.policy.reliable() => {
+                    chan.orphans.push_back((msg, sender));
+                },
+                None => self.release(sender, msg.data().len()),
+            }
+        }
+    }
+
+    /// Send the notice to other members of all the channels of the thread
+    /// and disconnect the thread from them.
+    pub fn disconnect_all(&mut self, thread: usize, notice: ChannelNotice) {
+        self.notify_peers(thread, notice);
+        let channels = match self.threads.get(&thread) {
+            Some(rec) => rec.channels.clone(),
+            None => return,
+        };
+
+        for channel in channels {
+            self.disconnect(thread, channel);
+        }
+    }
+
+    /// Send the notice to other members of all the channels of the thread.
+    pub fn notify_peers(&mut self, thread: usize, notice: ChannelNotice) {
+        let channels = match self.threads.get(&thread) {
+            Some(rec) => rec.channels.clone(),
+            None => return,
+        };
+
+        for channel in &channels {
+            let chan = self.channels.get_mut(channel).unwrap();
+            for (_, queue) in chan.members.iter_mut()
+                    .filter(|&(&id, _)| id != thread) {
+                queue.push_back(Delivery::Notice(notice));
+            }
+        }
+        self.signal_members(&channels, thread);
+    }
+
+    /// Signal all members of the channels except given thread.
+    fn signal_members(&mut self, channels: &BTreeSet<usize>,
+            except: usize) {
+        for &channel in channels {
+            let members: Vec<usize> = match self.channels.get(&channel) {
+                Some(chan) => chan.members.keys().cloned()
+                        .filter(|&id| id != except)
+                        .collect(),
+                None => continue,
+            };
+            for member in members {
+                self.signal(member, channel);
+            }
+        }
+    }
+
+    /// Request the thread to cancel. Suspended thread gets resumed
+    /// so it can see the request.
+    pub fn cancel(&mut self, thread: usize) {
+        if let Some(rec) = self.threads.get_mut(&thread) {
+            rec.cancelled = true;
+            rec.suspended = false;
+        }
+        self.touch_thread(thread);
+    }
+
+    /// Note that given events of the channel may have appeared.
+    pub fn touch(&mut self, channel: usize, events: &[ChannelEvent]) {
+        self.changed.extend(events.iter().map(|&event| (channel, event)));
+    }
+
+    /// Note that the state of the thread has changed. Futures
+    /// of the thread check it whatever event they wait for.
+    pub fn touch_thread(&mut self, thread: usize) {
+        let channels = match self.threads.get(&thread) {
+            Some(rec) => rec.channels.clone(),
+            None => return,
+        };
+        for channel in channels {
+            self.touch(channel, &EVENTS);
+        }
+    }
+
+    /// Whether the thread can access the region through the grant.
+    pub fn holds_grant(&self, grant: usize, thread: usize) -> bool {
+        self.grants.get(&grant)
+                .is_some_and(|rec| rec.holders.contains(&thread))
+    }
+
+    /// Whether the grant was given by the thread and can still be sent.
+    pub fn can_send_grant(&self, grant: usize, thread: usize) -> bool {
+        match self.grants.get(&grant) {
+            Some(rec) if rec.grantor == thread => {
+                rec.parent.is_none_or(|parent| self.holds_grant(parent, thread))
+            },
+            _ => false,
+        }
+    }
+
+    /// Revoke the grant and all the grants that were given through it.
+    pub fn revoke(&mut self, grant: usize) {
+        if self.grants.remove(&grant).is_none() {
+            return;
+        }
+
+        let children: Vec<usize> = self.grants.iter()
+                .filter(|&(_, rec)| rec.parent == Some(grant))
+                .map(|(&id, _)| id)
+                .collect();
+        for id in children {
+            self.revoke(id);
+        }
+    }
+
+    /// Revoke all grants of the region.
+    pub fn revoke_region(&mut self, region: usize) {
+        let grants: Vec<usize> = self.grants.iter()
+                .filter(|&(_, rec)| rec.region == region)
+                .map(|(&id, _)| id)
+                .collect();
+        for id in grants {
+            self.revoke(id);
+        }
+    }
+
+    /// Release the grants held by the thread. Grants that have no holders
+    /// left and the grants that the thread gave but never sent
+    /// get revoked.
+    pub fn release_grants(&mut self, thread: usize) {
+        for rec in self.grants.values_mut() {
+            rec.holders.remove(&thread);
+        }
+
+        let unused: Vec<usize> = self.grants.iter()
+                .filter(|&(_, rec)| rec.holders.is_empty())
+                .filter(|&(_, rec)| rec.sent || rec.grantor == thread)
+                .map(|(&id, _)| id)
+                .collect();
+        for id in unused {
+            self.revoke(id);
+        }
+    }
+
+    /// Note that the OS thread of the thread has exited. ID of the removed
+    /// thread gets free.
+    fn exited(&mut self, thread: usize) {
+        if let Some(rec) = self.threads.get_mut(&thread) {
+            rec.running = false;
+            self.reap(thread);
+        } else if self.detached.remove(&thread) {
+            self.thread_ids.release(thread);
+        }
+    }
+
+    /// Remove the record of the finished thread which OS thread has
+    /// exited. Record is kept while some thread instance refers to it
+    /// and while some memory is still charged to the thread. Its peers
+    /// have been notified already, so nobody else asks for its final
+    /// state. ID of the thread gets free.
+    pub fn reap(&mut self, thread: usize) {
+        let done = self.threads.get(&thread).is_some_and(|rec| {
+            !rec.running && rec.state.is_finished()
+                    && rec.watchers == 0 && rec.memory.usage == 0
+        });
+        if done {
+            self.threads.remove(&thread);
+            self.thread_ids.release(thread);
+        }
+    }
+
+    /// Mark the thread as dead and disconnect it from all the channels.
+    pub fn finish(&mut self, thread: usize) {
+        let channels = match self.threads.get_mut(&thread) {
+            Some(thr) => {
+                thr.state = ThreadState::Dead;
+                thr.channels.clone()
+            },
+            None => return,
+        };
+
+        for channel in channels {
+            self.disconnect(thread, channel);
+        }
+        self.release_grants(thread);
+    }
+
+    /// ID of the loaded object. Error is returned if the handle refers
+    /// to the object that was destroyed.
+    pub fn object_id(&self, object: &Object) -> ObjectResult<usize> {
+        let id = object.id();
+        if is_stale(&self.instances, object.tagged_id()) {
+            return Err(ObjectError::StaleHandle);
+        }
+        self.objects.get(&id).map(|_| id).ok_or(ObjectError::NotFound)
+    }
+
+    /// ID of the service of loaded object. Error is returned if
+    /// the handle refers to the service of the destroyed object.
+    pub fn service_id(&self, service: &Service) -> ObjectResult<usize> {
+        let id = service.id();
+        if is_stale(&self.instances, service.tagged_id()) {
+            return Err(ObjectError::StaleHandle);
+        }
+        self.services.get(&id).map(|_| id).ok_or(ObjectError::NotFound)
+    }
+
+    /// ID of the registered thread. Error is returned if the handle
+    /// refers to the removed thread.
+    pub fn thread_id(&self, thread: &Thread) -> ThreadResult<usize> {
+        let id = thread.id();
+        if is_stale(&self.thread_ids, thread.tagged_id()) {
+            return Err(ThreadError::StaleHandle);
+        }
+        self.threads.get(&id).map(|_| id).ok_or(ThreadError::NotFound)
+    }
+
+    /// ID of the open channel. Error is returned if the handle refers
+    /// to the closed channel.
+    pub fn channel_id(&self, channel: &Channel) -> ChannelResult<usize> {
+        let id = channel.id();
+        if is_stale(&self.channel_ids, channel.tagged_id()) {
+            return Err(ChannelError::StaleHandle);
+        }
+        self.channels.get(&id).map(|_| id).ok_or(ChannelError::Disconnected)
+    }
+
+    /// Create handle of the object.
+    pub fn object_handle(&self, object: usize) -> Shared<Object> {
+        let rec = &self.objects[&object];
+        let path = Path::parse(rec.implementer.path()).unwrap();
+        let generation = self.instances.generation(object).unwrap_or(0);
+        Shared::new(Object::new(path, Id::new(object, generation)))
+    }
+
+    /// Create handle of the service.
+    pub fn service_handle(&self, service: usize) -> Shared<Service> {
+        let rec = &self.services[&service];
+        let object = self.object_handle(rec.object);
+        let generation = self.instances.generation(service).unwrap_or(0);
+        let id = Id::new(service, generation);
+        Shared::new(Service::new(object, &rec.name, id).unwrap())
+    }
+
+    /// Create handle of the channel.
+    pub fn channel_handle(&self, channel: usize) -> Channel {
+        let rec = &self.channels[&channel];
+        let generation = self.channel_ids.generation(channel).unwrap_or(0);
+        let id = Id::new(channel, generation);
+        Channel::new(self.service_handle(rec.origin), id)
+    }
+
+    /// Create handle of the thread.
+    pub fn thread_handle(&self, thread: usize) -> Thread {
+        let rec = &self.threads[&thread];
+        let generation = self.thread_ids.generation(thread).unwrap_or(0);
+        let id = Id::new(thread, generation);
+        Thread::new(self.service_handle(rec.service), id)
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+use super::*;
+
+/// Waits for the events of several channels at once. All channels must
+/// be the instances of the same thread. While waiting the thread is in
+/// [Wait](../meta/enum.ThreadState.html#variant.Wait) state for any
+/// or all of the channels.
+///
+/// ```
+/// # use kobzar_ccs_usr::local::*;
+/// # use kobzar_ccs_usr::master::*;
+/// # use kobzar_ccs_usr::meta::*;
+/// # use std::time::Duration;
+/// let mut master = MyMaster::new();
+/// let entry = MyServiceEntry::new(|chan: &Channel| {
+///     let chan = MyMaster::current().unwrap().channel(chan).unwrap();
+///     while let Ok(msg) = chan.receive() {
+///         chan.send(msg).unwrap();
+///     }
+/// });
+/// let (_, control) = master.spawn_thread(entry.clone()).unwrap();
+/// let (_, data) = master.spawn_thread(entry).unwrap();
+///
+/// let mut select = MySelect::new();
+/// select.add(&control, &[ChannelEvent::Receive]);
+/// let index = select.add(&data, &[ChannelEvent::Receive]);
+///
+/// let timeout = Duration::from_millis(10);
+/// assert_eq!(select.wait_timeout(timeout), Ok(vec![]));
+///
+/// data.send(Message::new(vec![1])).unwrap();
+/// assert_eq!(select.wait(), Ok(vec![(index, ChannelEvent::Receive)]));
+/// ```
+pub struct MySelect<'a> {
+
+    /// Channels in the order they were added.
+    entries: Vec<SelectEntry<'a>>,
+}
+
+/// Channel watched by the select.
+struct SelectEntry<'a> {
+
+    /// Watched channel.
+    channel: &'a MyChannel,
+
+    /// Events the channel is watched for.
+    kinds: BTreeSet<ChannelEvent>,
+
+    /// Count of the members when the members event was last reported.
+    members: usize,
+}
+
+impl<'a> MySelect<'a> {
+
+    /// Create select without channels.
+    pub fn new() -> Self {
+        MySelect { entries: Vec::new() }
+    }
+
+    /// Watch the channel for given events. Index of the channel
+    /// in the select is returned. It identifies the channel in the list
+    /// of the events.
+    pub fn add(&mut self, channel: &'a MyChannel, kinds: &[ChannelEvent])
+            -> usize {
+        self.entries.push(SelectEntry {
+            channel,
+            kinds: kinds.iter().cloned().collect(),
+            members: channel.connections_count(),
+        });
+        self.entries.len() - 1
+    }
+
+    /// Wait until some events appear. Indices of the channels and their
+    /// events are returned.
+    pub fn wait(&mut self) -> ChannelResult<Vec<(usize, ChannelEvent)>> {
+        self.wait_events(None, false)
+    }
+
+    /// Wait until some events appear but no longer than given timeout.
+    /// Empty list is returned if timeout has passed.
+    pub fn wait_timeout(&mut self, timeout: Duration)
+            -> ChannelResult<Vec<(usize, ChannelEvent)>> {
+        self.wait_events(Some(Instant::now() + timeout), false)
+    }
+
+    /// Wait until events appear in all of the channels. Waiting stops
+    /// earlier if some channel has no other members left because
+    /// it cannot get events anymore. Events present at that moment are
+    /// returned.
+    pub fn wait_all(&mut self)
+            -> ChannelResult<Vec<(usize, ChannelEvent)>> {
+        self.wait_events(None, true)
+    }
+
+    /// Wait until events appear in all of the channels but no longer
+    /// than given timeout. Events present at the timeout are returned.
+    pub fn wait_all_timeout(&mut self, timeout: Duration)
+            -> ChannelResult<Vec<(usize, ChannelEvent)>> {
+        self.wait_events(Some(Instant::now() + timeout), true)
+    }
+
+    /// Wait for the events of any or all of the channels until
+    /// the deadline. Thread is in waiting state meanwhile. Cancelled
+    /// thread does not wait.
+    fn wait_events(&mut self, deadline: Option<Instant>, all: bool)
+            -> ChannelResult<Vec<(usize, ChannelEvent)>> {
+        let first = match self.entries.first() {
+            Some(entry) => entry.channel,
+            None => return Ok(Vec::new()),
+        };
+        let thread = first.thread_id();
+        if self.entries.iter().any(|e| e.channel.thread_id() != thread) {
+            return Err(ChannelError::NotMember);
+        }
+
+        let runtime = first.runtime();
+        let channels = self.entries.iter()
+                .map(|entry| entry.channel.handle().id())
+                .collect();
+        let target = if all {
+            WaitTarget::All(channels)
+        } else {
+            WaitTarget::Any(channels)
+        };
+
+        let mut reg = runtime.lock();
+        let result = loop {
+            reg = runtime.park(reg, thread);
+            let events: Vec<(usize, ChannelEvent)> = self.entries.iter()
+                    .enumerate()
+                    .flat_map(|(i, entry)| {
+                        entry.channel.events(&mut reg, &entry.kinds,
+                                entry.members)
+                                .into_iter()
+                                .map(move |event| (i, event))
+                    })
+                    .collect();
+            let done = if all {
+                let alone = self.entries.iter()
+                        .any(|entry| entry.channel.is_alone(&mut reg));
+                let ready = (0..self.entries.len())
+                        .all(|i| events.iter().any(|&(j, _)| i == j));
+                alone || ready
+            } else {
+                !events.is_empty()
+            };
+            if done {
+                break Ok(events);
+            }
+
+            let cancelled = reg.threads.get(&thread)
+                    .is_some_and(|rec| rec.cancelled);
+            if cancelled {
+                break Err(ChannelError::Cancelled);
+            }
+
+            let now = Instant::now();
+            if deadline.is_some_and(|deadline| deadline <= now) {
+                break Ok(events);
+            }
+
+            let state = ThreadState::Wait {
+                target: target.clone(),
+                deadline,
+            };
+            first.set_state(&mut reg, state);
+            reg = match deadline {
+                Some(deadline) => runtime.wait_timeout(reg, deadline - now).0,
+                None => runtime.wait(reg),
+            };
+        };
+        first.set_state(&mut reg, ThreadState::Active);
+        drop(reg);
+
+        // Next wait reports only the later changes of the members.
+        if let Ok(ref events) = result {
+            for &(i, event) in events {
+                if event == ChannelEvent::Members {
+                    let entry = &mut self.entries[i];
+                    entry.members = entry.channel.connections_count();
+                }
+            }
+        }
+        result
+    }
+}
+
+impl<'a> Default for MySelect<'a> {
+
+    fn default() -> Self {
+        MySelect::new()
+    }
+}
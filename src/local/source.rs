@@ -0,0 +1,319 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::*;
+use super::runtime::next_id;
+
+/// The implementer of Interface source. When application begins,
+/// we add the list of all interfaces we will use so that master could
+/// find all requested sources when they get needed.
+#[derive(Default)]
+pub struct MyInterfaceSource {
+
+    /// The set of application interfaces.
+    ints: InterfaceSet,
+}
+
+/// The implementer of Implementer source. Contains all the objects
+/// that application can load when some interface implementation
+/// is requested.
+///
+/// Master keeps a copy of the source so implementers that get appended
+/// after the source was added to the master are not visible to it.
+#[derive(Clone, Default)]
+pub struct MyImplementerSource {
+
+    /// Objects that can be loaded.
+    implementers: Vec<Arc<MyImplementer>>,
+}
+
+/// Lifecycle hook of the object that can fail.
+pub type MyHook = Arc<dyn Fn() -> ObjectResult<()> + Send + Sync>;
+
+/// Description of the object that can be loaded by the master.
+/// It holds the path of the object, the interfaces it implements,
+/// its services and lifecycle hooks.
+///
+/// # Example
+/// ```
+/// # use kobzar_ccs_usr::local::MyImplementer;
+/// # use kobzar_ccs_usr::meta::Channel;
+/// let mut implementer = MyImplementer::new("org.example.echo").unwrap();
+/// assert!(implementer.add_service("main", |_: &Channel| {}));
+///
+/// // Service names are validated like any other path node name.
+/// assert!(!implementer.add_service("1st", |_: &Channel| {}));
+/// ```
+pub struct MyImplementer {
+
+    /// ID of the object that is loaded from this implementer.
+    id: usize,
+
+    /// Path of the object in string form.
+    path: String,
+
+    /// Vendor paths and versions of implemented interfaces.
+    interfaces: Vec<(String, Version)>,
+
+    /// Vendor paths and versions of interfaces the implemented
+    /// interfaces depend on.
+    requires: Vec<(String, Version)>,
+
+    /// Time given to the object threads to finish when it gets stopped.
+    stop_deadline: Option<Duration>,
+
+    /// Memory limit of each thread of the object.
+    thread_quota: Option<usize>,
+
+    /// Memory limit of the whole object.
+    object_quota: Option<usize>,
+
+    /// What happens when the quota is exceeded.
+    quota_policy: QuotaPolicy,
+
+    /// IDs, names and entry points of the services.
+    services: Vec<(usize, String, MyServiceEntry)>,
+
+    /// Hook called when the object gets created.
+    initialize_hook: Option<MyHook>,
+
+    /// Hook called when the object gets started.
+    start_hook: Option<MyHook>,
+
+    /// Hook called when the object gets stopped.
+    stop_hook: Option<Arc<dyn Fn() + Send + Sync>>,
+}
+
+impl MyInterfaceSource {
+
+    /// Create new empty interface source.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Append new interface.
+    pub fn append(&mut self, i: Interface) {
+        self.ints.insert(i);
+    }
+}
+
+impl InterfaceSource for MyInterfaceSource {
+
+    fn lookup(&mut self, requirements: InterfaceRequirements)
+            -> InterfaceSet {
+        self.ints.iter()
+                .filter(|i| requirements.matches(i))
+                .cloned()
+                .collect()
+    }
+}
+
+impl MyImplementerSource {
+
+    /// Create new empty implementer source.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Append new implementer.
+    pub fn append(&mut self, implementer: MyImplementer) {
+        self.implementers.push(Arc::new(implementer));
+    }
+
+    /// Find the implementer of the object with given path.
+    pub(crate) fn find_object(&self, path: &str)
+            -> Option<&Arc<MyImplementer>> {
+        self.implementers.iter().find(|imp| imp.path() == path)
+    }
+
+    /// Find the implementer whose object has the service with given path.
+    pub(crate) fn find_service(&self, path: &str)
+            -> Option<&Arc<MyImplementer>> {
+        self.implementers.iter()
+                .find(|imp| imp.service_by_path(path).is_some())
+    }
+}
+
+impl ImplementerSource for MyImplementerSource {
+
+    type O = MyObject;
+
+    fn lookup(&mut self, requirements: ImplementerRequirements)
+            -> ObjectArchSet<MyObject> {
+        let mut set = ObjectArchSet::default();
+        for imp in &self.implementers {
+            let all = requirements.interfaces().iter()
+                    .all(|i| imp.implements(i));
+            if all {
+                set.insert(MyObject::new(imp.clone()));
+            }
+        }
+        set
+    }
+}
+
+impl MyImplementer {
+
+    /// Create new implementer of the object with given path. None is
+    /// returned if the path is invalid.
+    pub fn new(path: &str) -> Option<Self> {
+        let path = Path::parse(path)?;
+        Some(MyImplementer {
+            id: next_id(),
+            path: path.to_string(),
+            interfaces: Default::default(),
+            requires: Default::default(),
+            stop_deadline: None,
+            thread_quota: None,
+            object_quota: None,
+            quota_policy: QuotaPolicy::Fail,
+            services: Default::default(),
+            initialize_hook: None,
+            start_hook: None,
+            stop_hook: None,
+        })
+    }
+
+    /// Set the hook which is called when the object gets created.
+    pub fn set_initialize_hook<F>(&mut self, f: F)
+            where F: Fn() -> ObjectResult<()> + Send + Sync + 'static {
+        self.initialize_hook = Some(Arc::new(f));
+    }
+
+    /// Set the hook which is called when the object gets started.
+    pub fn set_start_hook<F>(&mut self, f: F)
+            where F: Fn() -> ObjectResult<()> + Send + Sync + 'static {
+        self.start_hook = Some(Arc::new(f));
+    }
+
+    /// Set the hook which is called when the object gets stopped.
+    pub fn set_stop_hook<F>(&mut self, f: F)
+            where F: Fn() + Send + Sync + 'static {
+        self.stop_hook = Some(Arc::new(f));
+    }
+
+    /// Mark the interface as implemented by this object.
+    pub fn implement(&mut self, interface: &Interface) {
+        self.interfaces.push(Self::key(interface));
+        for dep in interface.all_dependencies() {
+            self.requires.push(Self::key(&dep));
+        }
+    }
+
+    /// Set the time given to the object threads to finish when it
+    /// gets stopped. Without the deadline master waits for them
+    /// as long as needed unless other deadline is given on shutdown.
+    pub fn set_stop_deadline(&mut self, deadline: Duration) {
+        self.stop_deadline = Some(deadline);
+    }
+
+    /// Time given to the object threads to finish when it gets stopped.
+    pub fn stop_deadline(&self) -> Option<Duration> {
+        self.stop_deadline
+    }
+
+    /// Set memory limits of each thread of the object and of the whole
+    /// object. None means no limit. Policy tells what happens to
+    /// the thread that exceeds any of the limits.
+    pub fn set_memory_quota(&mut self, thread: Option<usize>,
+            object: Option<usize>, policy: QuotaPolicy) {
+        self.thread_quota = thread;
+        self.object_quota = object;
+        self.quota_policy = policy;
+    }
+
+    /// Memory limit of each thread of the object.
+    pub fn thread_quota(&self) -> Option<usize> {
+        self.thread_quota
+    }
+
+    /// Memory limit of the whole object.
+    pub fn object_quota(&self) -> Option<usize> {
+        self.object_quota
+    }
+
+    /// What happens when the quota is exceeded.
+    pub fn quota_policy(&self) -> QuotaPolicy {
+        self.quota_policy
+    }
+
+    /// Add the service with given name and entry point. Returns false
+    /// if the name is invalid or such service already exists.
+    pub fn add_service<E>(&mut self, name: &str, entry: E) -> bool
+            where E: ServiceEntry + Send + Sync + 'static {
+        let valid = Path::new(name).is_some();
+        let exists = self.services.iter().any(|s| s.1 == name);
+        if !valid || exists {
+            return false;
+        }
+
+        let entry = MyServiceEntry::new(entry);
+        self.services.push((next_id(), name.to_string(), entry));
+        true
+    }
+
+    /// ID of the object that gets loaded from this implementer.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Path of the object in string form.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Whether this object implements given interface.
+    pub fn implements(&self, interface: &Interface) -> bool {
+        let key = Self::key(interface);
+        self.interfaces.contains(&key)
+    }
+
+    /// Whether this object requires any of the interfaces implemented
+    /// by other object.
+    pub fn depends_on(&self, other: &MyImplementer) -> bool {
+        self.requires.iter().any(|key| other.interfaces.contains(key))
+    }
+
+    /// Vendor path and version that identify the interface.
+    fn key(interface: &Interface) -> (String, Version) {
+        (interface.vendor().to_string(), *interface.version())
+    }
+
+    /// Hook called when the object gets created.
+    pub(crate) fn initialize_hook(&self) -> Option<&MyHook> {
+        self.initialize_hook.as_ref()
+    }
+
+    /// Hook called when the object gets started.
+    pub(crate) fn start_hook(&self) -> Option<&MyHook> {
+        self.start_hook.as_ref()
+    }
+
+    /// Hook called when the object gets stopped.
+    pub(crate) fn stop_hook(&self) -> Option<&Arc<dyn Fn() + Send + Sync>> {
+        self.stop_hook.as_ref()
+    }
+
+    /// IDs, names and entry points of the services.
+    pub(crate) fn services(&self) -> &[(usize, String, MyServiceEntry)] {
+        &self.services
+    }
+
+    /// ID of the service with given name.
+    pub(crate) fn service_by_name(&self, name: &str) -> Option<usize> {
+        self.services.iter().find(|s| s.1 == name).map(|s| s.0)
+    }
+
+    /// ID of the service with given full path.
+    pub(crate) fn service_by_path(&self, path: &str) -> Option<usize> {
+        let prefix = self.path.len();
+        let matches = path.len() > prefix + 1
+                && path.starts_with(self.path.as_str())
+                && path[prefix..].starts_with(Path::SEPARATOR);
+        if !matches {
+            return None;
+        }
+
+        self.service_by_name(&path[prefix + 1..])
+    }
+}
@@ -0,0 +1,97 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use futures_sink::Sink;
+
+use super::*;
+
+/// Stream of the messages received from the channel. See
+/// [stream](struct.MyChannel.html#method.stream).
+pub struct MessageStream<'a> {
+
+    /// Channel to receive the messages from.
+    channel: &'a MyChannel,
+}
+
+/// Sink of the messages sent to the channel. See
+/// [sink](struct.MyChannel.html#method.sink).
+pub struct MessageSink<'a> {
+
+    /// Channel to send the messages to.
+    channel: &'a MyChannel,
+
+    /// Message that was started but is not sent yet.
+    pending: Option<Message>,
+}
+
+impl MyChannel {
+
+    /// Stream of the received messages. It ends when all other members
+    /// leave the channel. Notices and other errors are yielded as items
+    /// so the stream can go on after them.
+    pub fn stream(&self) -> MessageStream<'_> {
+        MessageStream { channel: self }
+    }
+
+    /// Sink that sends the messages to the other members of the channel.
+    /// It is ready for next message when the previous one is sent.
+    pub fn sink(&self) -> MessageSink<'_> {
+        MessageSink { channel: self, pending: None }
+    }
+}
+
+impl<'a> Stream for MessageStream<'a> {
+
+    type Item = ChannelResult<Message>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>)
+            -> Poll<Option<ChannelResult<Message>>> {
+        match self.channel.poll_receive(cx) {
+            Poll::Ready(Err(ChannelError::Disconnected)) => Poll::Ready(None),
+            Poll::Ready(result) => Poll::Ready(Some(result)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<'a> MessageSink<'a> {
+
+    /// Send the pending message if there is some.
+    fn poll_pending(&mut self, cx: &mut Context<'_>)
+            -> Poll<ChannelResult<()>> {
+        let result = match self.pending {
+            Some(ref msg) => self.channel.poll_send(cx, msg),
+            None => return Poll::Ready(Ok(())),
+        };
+        if result.is_ready() {
+            self.pending = None;
+        }
+        result
+    }
+}
+
+impl<'a> Sink<Message> for MessageSink<'a> {
+
+    type Error = ChannelError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>)
+            -> Poll<ChannelResult<()>> {
+        self.get_mut().poll_pending(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, msg: Message) -> ChannelResult<()> {
+        self.get_mut().pending = Some(msg);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>)
+            -> Poll<ChannelResult<()>> {
+        self.get_mut().poll_pending(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>)
+            -> Poll<ChannelResult<()>> {
+        self.get_mut().poll_pending(cx)
+    }
+}
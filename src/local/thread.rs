@@ -0,0 +1,443 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, PoisonError, RwLock};
+
+use super::*;
+use super::runtime::{self, Runtime, Registry, ThreadRecord};
+use super::runtime::{MemoryOwner, MemoryRecord, GrantRecord};
+
+/// Memory of the local thread or object. It counts the bytes
+/// of the allocated blocks and of the sent messages that are not
+/// received yet.
+pub struct MyMemory {
+
+    /// Runtime this memory belongs to.
+    runtime: Arc<Runtime>,
+
+    /// Thread or object this memory belongs to.
+    owner: MemoryOwner,
+}
+
+/// Memory region that can be shared with other threads without
+/// copying. The owner of the region grants it to other threads
+/// by sending the [grant](../meta/struct.Grant.html) with the message.
+/// Receivers [map](struct.MyMemory.html#method.map) the grant to get
+/// their own handle of the region. Receivers can grant the region
+/// further with no more access than they have.
+///
+/// Grant gets released when all its receivers die. Grantor can revoke
+/// the grant at any time. Dropping the owner handle revokes all grants
+/// of the region.
+pub struct MyRegion {
+
+    /// Runtime the memory of which was used.
+    runtime: Arc<Runtime>,
+
+    /// ID of the region.
+    region: usize,
+
+    /// ID of the thread that uses this handle.
+    thread: usize,
+
+    /// Grant through which the region is accessed. None for the owner.
+    grant: Option<usize>,
+
+    /// Access of the thread to the region.
+    access: Access,
+
+    /// Size of the region in bytes.
+    size: usize,
+
+    /// Bytes of the region. Never locked while the registry lock
+    /// is held.
+    data: Arc<RwLock<Vec<u8>>>,
+}
+
+/// Block of bytes allocated in the memory of the thread. Bytes
+/// are released when the block is dropped.
+pub struct MyBlock {
+
+    /// Runtime the memory of which was used.
+    runtime: Arc<Runtime>,
+
+    /// Owner the block is charged to.
+    owner: MemoryOwner,
+
+    /// Bytes of the block.
+    data: Vec<u8>,
+}
+
+/// Thread of the local runtime.
+pub struct MyThread {
+
+    /// Runtime this thread belongs to.
+    runtime: Arc<Runtime>,
+
+    /// Arch independent part.
+    thread: Thread,
+
+    /// Memory of this thread.
+    memory: MyMemory,
+}
+
+impl MyMemory {
+
+    /// Create the memory of given owner.
+    pub(crate) fn new(runtime: Arc<Runtime>, owner: MemoryOwner) -> Self {
+        MyMemory { runtime, owner }
+    }
+
+    /// Allocate zeroed block of given size. Error is returned if this
+    /// would exceed the quota. Thread may fault instead if its object
+    /// has such policy.
+    pub fn allocate(&self, size: usize) -> MemoryResult<MyBlock> {
+        drop(self.runtime.charge(self.runtime.lock(), self.owner, size)?);
+        Ok(MyBlock {
+            runtime: self.runtime.clone(),
+            owner: self.owner,
+            data: vec![0; size],
+        })
+    }
+
+    /// Allocate zeroed region of given size that can be shared with
+    /// other threads. Regions can be allocated only in the memory
+    /// of the thread.
+    pub fn allocate_region(&self, size: usize) -> MemoryResult<MyRegion> {
+        let thread = match self.owner {
+            MemoryOwner::Thread(id) => id,
+            MemoryOwner::Object(_) => return Err(MemoryError::AccessDenied),
+        };
+
+        drop(self.runtime.charge(self.runtime.lock(), self.owner, size)?);
+        Ok(MyRegion {
+            runtime: self.runtime.clone(),
+            region: runtime::next_id(),
+            thread,
+            grant: None,
+            access: Access::ReadWrite,
+            size,
+            data: Arc::new(RwLock::new(vec![0; size])),
+        })
+    }
+
+    /// Map the region granted to the thread of this memory.
+    pub fn map(&self, grant: &Grant) -> MemoryResult<MyRegion> {
+        let thread = match self.owner {
+            MemoryOwner::Thread(id) => id,
+            MemoryOwner::Object(_) => return Err(MemoryError::NotGranted),
+        };
+
+        let (region, access, data) = {
+            let reg = self.runtime.lock();
+            let rec = reg.grants.get(&grant.id())
+                    .ok_or(MemoryError::Revoked)?;
+            if !rec.holders.contains(&thread) {
+                return Err(MemoryError::NotGranted);
+            }
+            (rec.region, rec.access, rec.data.clone())
+        };
+
+        let size = data.read().unwrap_or_else(PoisonError::into_inner).len();
+        Ok(MyRegion {
+            runtime: self.runtime.clone(),
+            region,
+            thread,
+            grant: Some(grant.id()),
+            access,
+            size,
+            data,
+        })
+    }
+
+    /// Accounting record of the owner.
+    fn record(&self) -> MemoryRecord {
+        let reg = self.runtime.lock();
+        let rec = match self.owner {
+            MemoryOwner::Thread(id) => reg.threads.get(&id).map(|r| r.memory),
+            MemoryOwner::Object(id) => reg.objects.get(&id).map(|r| r.memory),
+        };
+        rec.unwrap_or_default()
+    }
+}
+
+impl MemoryArchitecture for MyMemory {
+
+    fn usage(&self) -> usize {
+        self.record().usage
+    }
+
+    fn peak(&self) -> usize {
+        self.record().peak
+    }
+
+    fn limit(&self) -> Option<usize> {
+        self.record().limit
+    }
+}
+
+impl MyRegion {
+
+    /// Size of the region in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Access of the thread to the region.
+    pub fn access(&self) -> Access {
+        self.access
+    }
+
+    /// Read the region. Error is returned if the grant was revoked.
+    pub fn read<F, R>(&self, f: F) -> MemoryResult<R>
+            where F: FnOnce(&[u8]) -> R {
+        self.check(Access::ReadOnly)?;
+        let data = self.data.read().unwrap_or_else(PoisonError::into_inner);
+        Ok(f(&data))
+    }
+
+    /// Write the region. Error is returned if the grant was revoked
+    /// or it gives read-only access.
+    pub fn write<F, R>(&self, f: F) -> MemoryResult<R>
+            where F: FnOnce(&mut [u8]) -> R {
+        self.check(Access::ReadWrite)?;
+        let mut data = self.data.write()
+                .unwrap_or_else(PoisonError::into_inner);
+        Ok(f(&mut data))
+    }
+
+    /// Create the grant of the region with given access. It can be sent
+    /// with the message over the channel. Access cannot exceed
+    /// the access of this thread.
+    pub fn grant(&self, access: Access) -> MemoryResult<Grant> {
+        self.check(access)?;
+        let id = runtime::next_id();
+        self.runtime.lock().grants.insert(id, GrantRecord {
+            region: self.region,
+            data: self.data.clone(),
+            grantor: self.thread,
+            parent: self.grant,
+            access,
+            holders: Default::default(),
+            sent: false,
+        });
+        Ok(Grant::new(id, self.size, access))
+    }
+
+    /// Revoke the grant given by this thread. Grants that receivers
+    /// gave further get revoked too.
+    pub fn revoke(&self, grant: &Grant) -> MemoryResult<()> {
+        let mut reg = self.runtime.lock();
+        let grantor = reg.grants.get(&grant.id())
+                .filter(|rec| rec.region == self.region)
+                .map(|rec| rec.grantor);
+        match grantor {
+            Some(thread) if thread == self.thread => {
+                reg.revoke(grant.id());
+                Ok(())
+            },
+            Some(_) => Err(MemoryError::AccessDenied),
+            None => Err(MemoryError::Revoked),
+        }
+    }
+
+    /// Check that the thread still has given access to the region.
+    fn check(&self, access: Access) -> MemoryResult<()> {
+        if access > self.access {
+            return Err(MemoryError::AccessDenied);
+        }
+
+        match self.grant {
+            Some(grant) => {
+                if self.runtime.lock().holds_grant(grant, self.thread) {
+                    Ok(())
+                } else {
+                    Err(MemoryError::Revoked)
+                }
+            },
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for MyRegion {
+
+    fn drop(&mut self) {
+        if self.grant.is_none() {
+            let mut reg = self.runtime.lock();
+            reg.revoke_region(self.region);
+            let owner = MemoryOwner::Thread(self.thread);
+            reg.release(owner, self.size);
+        }
+    }
+}
+
+impl Deref for MyBlock {
+
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl DerefMut for MyBlock {
+
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+}
+
+impl Drop for MyBlock {
+
+    fn drop(&mut self) {
+        self.runtime.lock().release(self.owner, self.data.len());
+    }
+}
+
+impl MyThread {
+
+    /// Create new instance of the registered thread. Record of the thread
+    /// is kept until the instance is dropped.
+    pub(crate) fn new(runtime: Arc<Runtime>, reg: &mut Registry,
+            thread: usize) -> Self {
+        reg.threads.get_mut(&thread).unwrap().watchers += 1;
+        Self::watched(runtime, reg, thread)
+    }
+
+    /// Create new instance of the thread which record counts it already.
+    pub(crate) fn watched(runtime: Arc<Runtime>, reg: &Registry,
+            thread: usize) -> Self {
+        let thread = reg.thread_handle(thread);
+        let owner = MemoryOwner::Thread(thread.id());
+        MyThread {
+            memory: MyMemory::new(runtime.clone(), owner),
+            runtime,
+            thread,
+        }
+    }
+
+    /// Record of this thread. Error is returned if the thread was
+    /// removed.
+    fn record<'a>(&self, reg: &'a Registry)
+            -> ChannelResult<&'a ThreadRecord> {
+        match reg.thread_id(&self.thread) {
+            Ok(id) => Ok(&reg.threads[&id]),
+            Err(ThreadError::StaleHandle) => Err(ChannelError::StaleHandle),
+            Err(_) => Err(ChannelError::NoThread),
+        }
+    }
+}
+
+impl Drop for MyThread {
+
+    fn drop(&mut self) {
+        let mut reg = self.runtime.lock();
+        if let Ok(id) = reg.thread_id(&self.thread) {
+            reg.threads.get_mut(&id).unwrap().watchers -= 1;
+            reg.reap(id);
+        }
+    }
+}
+
+impl ThreadArchitecture for MyThread {
+
+    type MA = MyMemory;
+
+    type C = MyChannel;
+
+    fn unarch(&self) -> &Thread {
+        &self.thread
+    }
+
+    fn memory(&self) -> &MyMemory {
+        &self.memory
+    }
+
+    fn state(&self) -> ThreadState {
+        let reg = self.runtime.lock();
+        match self.record(&reg) {
+            Ok(rec) => rec.state.clone(),
+            Err(_) => ThreadState::Dead,
+        }
+    }
+
+    fn connected_channels(&self) -> ChannelArchSet<MyChannel> {
+        let reg = self.runtime.lock();
+        let mut set = ChannelArchSet::default();
+        if let Ok(rec) = self.record(&reg) {
+            for &id in &rec.channels {
+                let runtime = self.runtime.clone();
+                let thread = self.thread.id();
+                set.insert(MyChannel::new(runtime, &reg, thread, id));
+            }
+        }
+        set
+    }
+
+    fn is_cancelled(&self) -> bool {
+        let reg = self.runtime.lock();
+        self.record(&reg).map(|rec| rec.cancelled).unwrap_or(false)
+    }
+
+    fn descriptors(&self) -> Vec<Descriptor> {
+        let reg = self.runtime.lock();
+        match self.record(&reg) {
+            Ok(rec) => rec.handles.keys().cloned().collect(),
+            Err(_) => Default::default(),
+        }
+    }
+
+    fn channel(&self, descriptor: Descriptor) -> ChannelResult<MyChannel> {
+        let reg = self.runtime.lock();
+        let channel = self.record(&reg)?.channel(descriptor)?;
+        let runtime = self.runtime.clone();
+        Ok(MyChannel::new(runtime, &reg, self.thread.id(), channel))
+    }
+
+    fn duplicate(&self, descriptor: Descriptor) -> ChannelResult<Descriptor> {
+        let mut reg = self.runtime.lock();
+        let channel = self.record(&reg)?.channel(descriptor)?;
+        let rec = reg.threads.get_mut(&self.thread.id()).unwrap();
+        Ok(rec.add_descriptor(channel))
+    }
+
+    fn close(&self, descriptor: Descriptor) -> ChannelResult<()> {
+        let mut reg = self.runtime.lock();
+        let thread = self.thread.id();
+        self.record(&reg)?;
+        let (channel, last) = {
+            let rec = reg.threads.get_mut(&thread).unwrap();
+            if rec.closed.remove(&descriptor) {
+                // Channel is gone already, only the number gets free.
+                return Ok(());
+            }
+            let channel = rec.channel(descriptor)?;
+            (channel, rec.descriptor_count(channel) == 1)
+        };
+
+        if last {
+            reg.can_leave(thread, channel)?;
+            reg.disconnect(thread, channel);
+            reg.threads.get_mut(&thread).unwrap().closed.remove(&descriptor);
+            self.runtime.notify(reg);
+        } else {
+            reg.threads.get_mut(&thread).unwrap().handles.remove(&descriptor);
+        }
+        Ok(())
+    }
+
+    fn transfer(&self, descriptor: Descriptor, to: &Thread)
+            -> ChannelResult<Descriptor> {
+        let mut reg = self.runtime.lock();
+        self.record(&reg)?;
+        let to = match reg.thread_id(to) {
+            Ok(id) => id,
+            Err(ThreadError::StaleHandle) => {
+                return Err(ChannelError::StaleHandle);
+            },
+            Err(_) => return Err(ChannelError::NoThread),
+        };
+        let result = reg.transfer(self.thread.id(), descriptor, to);
+        self.runtime.notify(reg);
+        result
+    }
+}
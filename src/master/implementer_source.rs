@@ -16,5 +16,18 @@ pub trait ImplementerSource {
 pub struct ImplementerRequirements {
 
     /// Interfaces that must be implemented by the object.
-    interfaces: Rc<meta::InterfaceSet>,
+    interfaces: Shared<meta::InterfaceSet>,
+}
+
+impl ImplementerRequirements {
+
+    /// Create new requirements for implementer of given interfaces.
+    pub fn new(interfaces: Shared<meta::InterfaceSet>) -> Self {
+        ImplementerRequirements { interfaces }
+    }
+
+    /// Interfaces that must be implemented by the object.
+    pub fn interfaces(&self) -> &meta::InterfaceSet {
+        self.interfaces.as_ref()
+    }
 }
@@ -30,3 +30,43 @@ pub struct InterfaceRequirements {
     /// Version rules.
     version: VersionRule,
 }
+
+impl VersionRule {
+
+    /// Whether given version satisfies this rule.
+    pub fn matches(&self, version: &meta::Version) -> bool {
+        use self::VersionRule::*;
+
+        match *self {
+            Equal(ref v) => v == version,
+            NewerOrEqual(ref v) => {
+                v.major() == version.major() && v <= version
+            },
+        }
+    }
+}
+
+impl InterfaceRequirements {
+
+    /// Create new requirements for interface with given vendor path
+    /// and version rule.
+    pub fn new(vendor: meta::Path, version: VersionRule) -> Self {
+        InterfaceRequirements { vendor, version }
+    }
+
+    /// Vendor and name of the interface.
+    pub fn vendor(&self) -> &meta::Path {
+        &self.vendor
+    }
+
+    /// Version rules.
+    pub fn version(&self) -> &VersionRule {
+        &self.version
+    }
+
+    /// Whether given interface satisfies these requirements.
+    pub fn matches(&self, interface: &meta::Interface) -> bool {
+        self.vendor() == interface.vendor()
+                && self.version.matches(interface.version())
+    }
+}
@@ -1,6 +1,7 @@
 //! Master module that allows allocating new channels, services and objects.
 
-use std::rc::Rc;
+use meta::Shared;
+use std::time::Duration;
 
 use super::meta;
 
@@ -10,8 +11,28 @@ pub use self::interface_source::*;
 mod implementer_source;
 pub use self::implementer_source::*;
 
+/// Requests of the services.
+mod service_request;
+pub use self::service_request::*;
+
+/// Report of the master shutdown.
+mod shutdown;
+pub use self::shutdown::*;
+
+/// Supervisors that restart failed threads.
+mod supervisor;
+pub use self::supervisor::*;
+
+/// Diagnostic dump of the master threads.
+mod thread_dump;
+pub use self::thread_dump::*;
+
 /// Master controls all allocations, lists, sets, maps, trees and other stuff
 /// of CCS.
+///
+/// IDs of destroyed elements may be reused. Handles carry the generation
+/// of the ID so the master rejects stale handles instead of acting
+/// on the new element with the same ID.
 pub trait Master {
 
     /// Channel type that is used in this master.
@@ -43,4 +64,98 @@ pub trait Master {
     /// that was passed gets returned.
     fn add_implementer_source(&mut self, isrc: Self::ImpSrc)
             -> &Self::ImpSrc;
+
+    /// Request the service. Master finds the service by given request
+    /// or loads the object that implements it from implementer sources.
+    /// Then new thread of the service is started and
+    /// [entry point](../meta/trait.ServiceEntry.html) gets executed with
+    /// the new channel. The end of the channel that belongs to the caller
+    /// is returned.
+    fn request_service(&mut self, request: ServiceRequest)
+            -> ServiceRequestResult<Self::Chan>;
+
+    /// Load the object with given path from implementer sources and
+    /// initialize it. Created object is stopped and must be started
+    /// to make its services accessible.
+    fn create_object(&mut self, path: &meta::Path)
+            -> meta::ObjectResult<Self::Obj>;
+
+    /// Start stopped object so its services get accessible.
+    fn start_object(&mut self, object: &meta::Object)
+            -> meta::ObjectResult<()>;
+
+    /// Stop running object. Services get stopped one by one in reverse
+    /// order of their declaration. Threads of the service get cancelled
+    /// and other members of their channels get shutdown notice. Master
+    /// waits for the threads to finish and then closes the channels
+    /// of the service.
+    fn stop_object(&mut self, object: &meta::Object)
+            -> meta::ObjectResult<()>;
+
+    /// Unload stopped or failed object. Channels originated by its
+    /// services get closed.
+    fn destroy_object(&mut self, object: &meta::Object)
+            -> meta::ObjectResult<()>;
+
+    /// Current state of the object.
+    fn object_state(&self, object: &meta::Object)
+            -> meta::ObjectResult<meta::ObjectState>;
+
+    /// Stop all running objects. Objects get stopped in reverse order
+    /// of their dependencies which are defined by
+    /// [interface dependencies](../meta/struct.Interface.html#method.dependencies):
+    /// an object is stopped before the objects implementing the
+    /// interfaces it depends on. All objects must stop within given
+    /// deadline which starts with the shutdown. Object with its own
+    /// deadline gets no more time than that. Objects that do not stop
+    /// in time are failed and reported.
+    fn shutdown(&mut self, deadline: Duration) -> ShutdownReport;
+
+    /// Attach the supervisor to the object or the service. Previous
+    /// supervisor of the same target gets replaced.
+    fn supervise(&mut self, target: SupervisionTarget,
+            supervisor: Supervisor) -> meta::ObjectResult<()>;
+
+    /// Spawn new thread of the service of the current thread. Entry
+    /// point gets executed with the new channel between the threads.
+    /// New thread and the end of the channel that belongs to the caller
+    /// are returned.
+    fn spawn_thread(&mut self,
+            entry: <Self::Srv as meta::ServiceArchitecture>::SE)
+            -> meta::ThreadResult<(Self::Thr, Self::Chan)>;
+
+    /// Wait for the thread to finish and return its final state.
+    /// Without timeout it waits forever.
+    fn join_thread(&mut self, thread: &meta::Thread,
+            timeout: Option<Duration>) -> meta::ThreadResult<meta::ThreadState>;
+
+    /// Request the thread to cancel. Thread sees the request and
+    /// decides when to finish.
+    fn cancel_thread(&mut self, thread: &meta::Thread)
+            -> meta::ThreadResult<()>;
+
+    /// Suspend the thread until it gets resumed.
+    fn suspend_thread(&mut self, thread: &meta::Thread)
+            -> meta::ThreadResult<()>;
+
+    /// Resume suspended thread.
+    fn resume_thread(&mut self, thread: &meta::Thread)
+            -> meta::ThreadResult<()>;
+
+    /// All threads known to this master including finished ones.
+    fn threads(&self) -> Vec<Self::Thr>;
+
+    /// Snapshot of all threads known to this master. States
+    /// and channels of all threads are taken at once so the dump never
+    /// mixes the moments before and after some change.
+    fn thread_dump(&self) -> ThreadDump;
+
+    /// Lazy iterator over the services of the loaded objects. Live
+    /// iterator sees the objects that get loaded and unloaded while
+    /// it walks.
+    fn service_iter(&self, mode: meta::IterMode) -> meta::ServiceIterator<'_>;
+
+    /// Lazy iterator over the open channels. Live iterator sees
+    /// the channels that get created and closed while it walks.
+    fn channel_iter(&self, mode: meta::IterMode) -> meta::ChannelIterator<'_>;
 }
@@ -0,0 +1,43 @@
+use super::meta;
+
+/// Request of some service. Master uses it to find the service and
+/// to start new thread that serves the requester.
+#[derive(Clone, Copy)]
+pub enum ServiceRequest<'a> {
+
+    /// Service with exact path.
+    Path(&'a meta::Path),
+
+    /// Service with given name of any object that implements the
+    /// interface.
+    Interface {
+
+        /// Interface that must be implemented by the object.
+        interface: &'a meta::Interface,
+
+        /// The name of the service that is declared by the interface.
+        name: &'a str,
+    },
+}
+
+/// Error which appears when requesting a service.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ServiceRequestError {
+
+    /// No service was found by given path.
+    NotFound,
+
+    /// Interface does not declare the service with requested name.
+    NotDeclared,
+
+    /// No object was found that implements the interface.
+    NoImplementer,
+
+    /// Object of the service is not running or failed to start.
+    NotRunning,
+
+    /// Service was found but its thread could not be started.
+    SpawnFailed,
+}
+
+pub type ServiceRequestResult<T> = Result<T, ServiceRequestError>;
@@ -0,0 +1,58 @@
+use super::meta;
+
+/// Report of the master shutdown.
+#[derive(Default)]
+pub struct ShutdownReport {
+
+    /// Objects that were stopped in time, in the order of stopping.
+    stopped: Vec<meta::Object>,
+
+    /// Objects that did not stop before their deadline.
+    overdue: Vec<meta::Object>,
+
+    /// Threads that still were running when the deadline of their
+    /// object passed.
+    threads: Vec<meta::Thread>,
+}
+
+impl ShutdownReport {
+
+    /// Create empty report.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Record the object that was stopped in time.
+    pub fn add_stopped(&mut self, object: meta::Object) {
+        self.stopped.push(object);
+    }
+
+    /// Record the object that did not stop in time and its threads that
+    /// still are running.
+    pub fn add_overdue(&mut self, object: meta::Object,
+            threads: Vec<meta::Thread>) {
+        self.overdue.push(object);
+        self.threads.extend(threads);
+    }
+
+    /// Objects that were stopped in time, in the order of stopping.
+    pub fn stopped(&self) -> &[meta::Object] {
+        &self.stopped
+    }
+
+    /// Objects that did not stop before their deadline.
+    pub fn overdue(&self) -> &[meta::Object] {
+        &self.overdue
+    }
+
+    /// Threads that still were running when the deadline of their
+    /// object passed.
+    pub fn overdue_threads(&self) -> &[meta::Thread] {
+        &self.threads
+    }
+
+    /// Whether all the objects were stopped in time.
+    pub fn is_clean(&self) -> bool {
+        self.overdue.is_empty()
+    }
+}
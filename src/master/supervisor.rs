@@ -0,0 +1,161 @@
+use std::time::Duration;
+
+use super::meta;
+
+/// Which threads get restarted when one of the supervised threads dies
+/// abnormally. Supervised threads are ordered by the time they were
+/// started.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RestartStrategy {
+
+    /// Only the failed thread gets restarted.
+    OneForOne,
+
+    /// All supervised threads get restarted.
+    OneForAll,
+
+    /// The failed thread and all the threads started after it get
+    /// restarted.
+    RestForOne,
+}
+
+/// Element of the system which threads are supervised.
+#[derive(Clone, Copy)]
+pub enum SupervisionTarget<'a> {
+
+    /// All threads of all services of the object.
+    Object(&'a meta::Object),
+
+    /// All threads of the service.
+    Service(&'a meta::Service),
+}
+
+/// Supervisor restarts the threads that died abnormally.
+///
+/// Supervisors form a tree: supervisor of the service is a child of
+/// the supervisor of its object. When supervisor restarts threads too
+/// often it gives up and the failure is passed to the supervisor of
+/// the object. When object supervisor gives up the object fails.
+///
+/// Each restart is delayed. The delay begins with initial back-off and
+/// gets doubled for each restart in the intensity period until
+/// it reaches maximal back-off.
+///
+/// ```
+/// # use kobzar_ccs_usr::master::*;
+/// # use std::time::Duration;
+/// let mut supervisor = Supervisor::new(RestartStrategy::OneForOne);
+/// supervisor.set_intensity(3, Duration::from_secs(5));
+/// supervisor.set_backoff(Duration::from_millis(10),
+///         Duration::from_millis(25));
+///
+/// assert!(supervisor.allows(2));
+/// assert!(!supervisor.allows(3));
+///
+/// assert_eq!(supervisor.backoff(0), Duration::from_millis(10));
+/// assert_eq!(supervisor.backoff(1), Duration::from_millis(20));
+/// assert_eq!(supervisor.backoff(2), Duration::from_millis(25));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Supervisor {
+
+    /// Strategy of the restarts.
+    strategy: RestartStrategy,
+
+    /// Maximal count of restarts in the intensity period.
+    max_restarts: usize,
+
+    /// Period in which restarts are counted.
+    period: Duration,
+
+    /// Delay before the first restart in the period.
+    initial_backoff: Duration,
+
+    /// Maximal delay before the restart.
+    max_backoff: Duration,
+
+    /// Time the replaced thread gets to exit before its replacement
+    /// starts anyway.
+    shutdown: Duration,
+}
+
+impl Supervisor {
+
+    /// Create new supervisor with given strategy. It allows 3 restarts
+    /// in 5 seconds, has no back-off and gives replaced threads
+    /// 5 seconds to exit.
+    pub fn new(strategy: RestartStrategy) -> Self {
+        Supervisor {
+            strategy,
+            max_restarts: 3,
+            period: Duration::from_secs(5),
+            initial_backoff: Duration::from_secs(0),
+            max_backoff: Duration::from_secs(0),
+            shutdown: Duration::from_secs(5),
+        }
+    }
+
+    /// Set maximal count of restarts in given period.
+    pub fn set_intensity(&mut self, max_restarts: usize, period: Duration) {
+        self.max_restarts = max_restarts;
+        self.period = period;
+    }
+
+    /// Set initial and maximal delay before restarts.
+    pub fn set_backoff(&mut self, initial: Duration, max: Duration) {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+    }
+
+    /// Set the time the replaced thread gets to exit. Replaced threads
+    /// are cancelled, but the thread that does not check it keeps
+    /// running. Its replacement starts when the time passes.
+    pub fn set_shutdown(&mut self, shutdown: Duration) {
+        self.shutdown = shutdown;
+    }
+
+    /// Time the replaced thread gets to exit before its replacement
+    /// starts anyway.
+    pub fn shutdown(&self) -> Duration {
+        self.shutdown
+    }
+
+    /// Strategy of the restarts.
+    pub fn strategy(&self) -> RestartStrategy {
+        self.strategy
+    }
+
+    /// Maximal count of restarts in the intensity period.
+    pub fn max_restarts(&self) -> usize {
+        self.max_restarts
+    }
+
+    /// Period in which restarts are counted.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Whether one more restart is allowed when given count of restarts
+    /// was already done in the intensity period.
+    pub fn allows(&self, recent: usize) -> bool {
+        recent < self.max_restarts
+    }
+
+    /// Delay before the restart when given count of restarts was already
+    /// done in the intensity period.
+    pub fn backoff(&self, recent: usize) -> Duration {
+        let mut delay = self.initial_backoff;
+        for _ in 0..recent {
+            if delay >= self.max_backoff {
+                break;
+            }
+            delay *= 2;
+        }
+
+        if delay > self.max_backoff {
+            self.max_backoff.max(self.initial_backoff)
+        } else {
+            delay
+        }
+    }
+}
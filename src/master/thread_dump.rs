@@ -0,0 +1,266 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::time::Instant;
+
+use super::meta;
+use super::Master;
+
+/// Information about one thread of the dump.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ThreadInfo {
+
+    /// ID of the thread.
+    id: usize,
+
+    /// Path of the origin service of the thread.
+    service: String,
+
+    /// State of the thread at the moment of the dump.
+    state: meta::ThreadState,
+
+    /// IDs of the channels the thread is connected to.
+    channels: BTreeSet<usize>,
+}
+
+/// Snapshot of all threads of the master. It is printed as plain text
+/// by `Display` and can be converted to JSON.
+///
+/// ```
+/// # use kobzar_ccs_usr::local::*;
+/// # use kobzar_ccs_usr::master::*;
+/// let master = MyMaster::new();
+/// let dump = ThreadDump::capture(&master);
+/// let main = &dump.threads()[0];
+/// assert_eq!(main.service(), "app.main");
+///
+/// let text = format!("{}", dump);
+/// assert!(text.contains("\"app.main\""));
+/// assert!(dump.to_json().contains("\"state\":\"active\""));
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ThreadDump {
+
+    /// Threads ordered by their IDs.
+    threads: Vec<ThreadInfo>,
+}
+
+impl ThreadInfo {
+
+    /// Create information about the thread.
+    pub fn new(id: usize, service: String, state: meta::ThreadState,
+            channels: BTreeSet<usize>) -> Self {
+        ThreadInfo { id, service, state, channels }
+    }
+
+    /// ID of the thread.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Path of the origin service of the thread.
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    /// State of the thread at the moment of the dump.
+    pub fn state(&self) -> &meta::ThreadState {
+        &self.state
+    }
+
+    /// IDs of the channels the thread is connected to.
+    pub fn channels(&self) -> &BTreeSet<usize> {
+        &self.channels
+    }
+
+    /// Channels the thread waits on. None if thread is not waiting.
+    pub fn wait_target(&self) -> Option<&meta::WaitTarget> {
+        self.state.wait_target()
+    }
+
+    /// Name of the state used in the dump.
+    fn state_name(&self) -> &'static str {
+        use self::meta::ThreadState::*;
+        match self.state {
+            Active => "active",
+            Wait { .. } => "waiting",
+            Suspended => "suspended",
+            Dead => "dead",
+            Faulted(_) => "faulted",
+        }
+    }
+
+    /// Milliseconds left until the deadline of the waiting thread.
+    fn deadline_millis(&self) -> Option<u128> {
+        self.state.deadline().map(|deadline| {
+            deadline.saturating_duration_since(Instant::now()).as_millis()
+        })
+    }
+
+    /// Write the thread in JSON format.
+    fn write_json(&self, out: &mut String) {
+        out.push_str(&format!("{{\"id\":{},\"service\":", self.id));
+        write_json_str(out, &self.service);
+        out.push_str(&format!(",\"state\":\"{}\"", self.state_name()));
+        if let meta::ThreadState::Faulted(ref reason) = self.state {
+            out.push_str(",\"reason\":");
+            write_json_str(out, reason);
+        }
+        out.push_str(",\"channels\":");
+        write_json_ids(out, &self.channels);
+
+        out.push_str(",\"waiting\":");
+        match self.wait_target() {
+            Some(target) => {
+                let mode = match *target {
+                    meta::WaitTarget::Any(_) => "any",
+                    meta::WaitTarget::All(_) => "all",
+                };
+                out.push_str(&format!("{{\"mode\":\"{}\",\"channels\":", mode));
+                write_json_ids(out, target.channels());
+                out.push_str(",\"deadline_ms\":");
+                match self.deadline_millis() {
+                    Some(millis) => out.push_str(&millis.to_string()),
+                    None => out.push_str("null"),
+                }
+                out.push('}');
+            },
+            None => out.push_str("null"),
+        }
+        out.push('}');
+    }
+}
+
+impl fmt::Display for ThreadInfo {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Thread {} \"{}\"", self.id, self.service)?;
+        match self.state {
+            meta::ThreadState::Faulted(ref reason) => {
+                writeln!(f, "    state: faulted: {}", reason)?;
+            },
+            _ => writeln!(f, "    state: {}", self.state_name())?,
+        }
+        writeln!(f, "    channels: {:?}", self.channels)?;
+
+        if let Some(target) = self.wait_target() {
+            let mode = match *target {
+                meta::WaitTarget::Any(_) => "any of",
+                meta::WaitTarget::All(_) => "all of",
+            };
+            write!(f, "    waiting on {} {:?}", mode, target.channels())?;
+            match self.deadline_millis() {
+                Some(millis) => writeln!(f, ", deadline in {} ms", millis)?,
+                None => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ThreadDump {
+
+    /// Capture the state of all the threads of the master. All threads
+    /// are captured at the same moment. See
+    /// [thread_dump](trait.Master.html#tymethod.thread_dump).
+    pub fn capture<M: Master>(master: &M) -> Self {
+        master.thread_dump()
+    }
+
+    /// Create the dump of given threads.
+    pub fn new(mut threads: Vec<ThreadInfo>) -> Self {
+        threads.sort_by_key(|info| info.id);
+        ThreadDump { threads }
+    }
+
+    /// Threads ordered by their IDs.
+    pub fn threads(&self) -> &[ThreadInfo] {
+        &self.threads
+    }
+
+    /// Dump in JSON format.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"threads\":[");
+        for (i, thread) in self.threads.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            thread.write_json(&mut out);
+        }
+        out.push_str("]}");
+        out
+    }
+}
+
+impl fmt::Display for ThreadDump {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Thread dump: {} threads", self.threads.len())?;
+        for thread in &self.threads {
+            writeln!(f)?;
+            write!(f, "{}", thread)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write the string as JSON string literal.
+fn write_json_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                out.push_str(&format!("\\u{:04x}", c as u32));
+            },
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Write the IDs as JSON array.
+fn write_json_ids(out: &mut String, ids: &BTreeSet<usize>) {
+    out.push('[');
+    for (i, id) in ids.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(&id.to_string());
+    }
+    out.push(']');
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn json_format() {
+        let waiting = meta::ThreadState::Wait {
+            target: meta::WaitTarget::single(3),
+            deadline: None,
+        };
+        let faulted = meta::ThreadState::Faulted("bad \"input\"\n".into());
+        let dump = ThreadDump::new(vec![
+            ThreadInfo::new(2, "org.a.main".into(), faulted, BTreeSet::new()),
+            ThreadInfo::new(1, "org.b.main".into(), waiting,
+                    vec![3, 4].into_iter().collect()),
+        ]);
+
+        assert_eq!(dump.to_json(), concat!("{\"threads\":[",
+                "{\"id\":1,\"service\":\"org.b.main\",\"state\":\"waiting\",",
+                "\"channels\":[3,4],\"waiting\":{\"mode\":\"any\",",
+                "\"channels\":[3],\"deadline_ms\":null}},",
+                "{\"id\":2,\"service\":\"org.a.main\",\"state\":\"faulted\",",
+                "\"reason\":\"bad \\\"input\\\"\\n\",\"channels\":[],",
+                "\"waiting\":null}]}"));
+
+        let text = format!("{}", dump);
+        assert!(text.contains("    waiting on any of {3}\n"));
+        assert!(text.contains("    state: faulted: bad \"input\"\n"));
+    }
+}
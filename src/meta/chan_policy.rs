@@ -1,3 +1,5 @@
+use super::{ChannelError, ChannelResult};
+
 macro_rules! unwrap {
     ($self: ident, $name: ident) => {
         let $name = if let Some(v) = $self.$name {
@@ -25,6 +27,60 @@ pub struct Policy {
     /// Whether allowed to have multiple thread connected to this channel.
     /// If forbider, only single peer-to-peer is allowed.
     no_multiple_connectons: bool,
+
+    /// How many messages can wait in the queue of each member. None
+    /// if the queues are unbounded.
+    capacity: Option<usize>,
+
+    /// What happens when the message is sent to the member with
+    /// the full queue.
+    overflow: Overflow,
+
+    /// Count of the last messages replayed to the new members
+    /// of publish-subscribe channel. None if the channel is not
+    /// publish-subscribe one.
+    publish_subscribe: Option<usize>,
+
+    /// Which members receive each message.
+    distribution: Distribution,
+
+    /// Whether messages are numbered, deduplicated and kept until
+    /// the receiver acknowledges them.
+    reliable: bool,
+}
+
+/// What happens when the message is sent to the member whose queue
+/// has reached the [capacity](struct.Policy.html#method.capacity)
+/// of the channel.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Overflow {
+
+    /// Sender waits until all receivers have room for the message.
+    #[default]
+    Block,
+
+    /// Sending fails with
+    /// [QueueFull](enum.ChannelError.html#variant.QueueFull) error.
+    Fail,
+
+    /// Oldest message in the full queue is dropped to make room.
+    DropOldest,
+}
+
+/// Which members of the channel receive the sent message.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Distribution {
+
+    /// Every member except the sender receives the copy.
+    #[default]
+    Broadcast,
+
+    /// Members take turns to receive the messages.
+    RoundRobin,
+
+    /// Member with the least messages queued or not acknowledged
+    /// receives the message.
+    LeastLoaded,
 }
 
 /// Struct that simplifies building the policy.
@@ -49,6 +105,25 @@ pub struct Policy {
 /// assert!(result.is_ok());
 /// ```
 ///
+/// Capacity of the queues is optional. Queues are unbounded
+/// if it is not set. Zero capacity is rejected.
+/// ```
+/// # use kobzar_ccs_usr::meta::{Overflow, PolicyBuilder};
+/// let mut builder = PolicyBuilder::new();
+/// builder.invitation_from_not_member(false);
+/// builder.origin_can_leave(true);
+/// builder.join_by_handle(false);
+/// builder.no_multiple_connectons(true);
+/// builder.capacity(Some(0), Overflow::DropOldest);
+///
+/// let mut builder = builder.build().unwrap_err();
+/// builder.capacity(Some(16), Overflow::DropOldest);
+///
+/// let policy = builder.build().unwrap();
+/// assert_eq!(policy.capacity(), Some(16));
+/// assert_eq!(policy.overflow(), Overflow::DropOldest);
+/// ```
+///
 /// When there are still unassigned fields builder will fail to
 /// build the policy and instead it will return Err result and
 /// pass itself as Err field.
@@ -78,6 +153,97 @@ pub struct PolicyBuilder {
     origin_can_leave: Option<bool>,
     join_by_handle: Option<bool>,
     no_multiple_connectons: Option<bool>,
+    capacity: Option<usize>,
+    overflow: Overflow,
+    publish_subscribe: Option<usize>,
+    distribution: Distribution,
+    reliable: bool,
+}
+
+impl Policy {
+
+    /// Whether not-a-members of the channel can invite Services to join.
+    pub fn invitation_from_not_member(&self) -> bool {
+        self.invitation_from_not_member
+    }
+
+    /// Whether to allow origin service to leave the channel when there
+    /// still is active threads connected.
+    pub fn origin_can_leave(&self) -> bool {
+        self.origin_can_leave
+    }
+
+    /// Whether to allow to join by handle without invitation.
+    pub fn join_by_handle(&self) -> bool {
+        self.join_by_handle
+    }
+
+    /// Whether only single peer-to-peer connection is allowed.
+    pub fn no_multiple_connectons(&self) -> bool {
+        self.no_multiple_connectons
+    }
+
+    /// How many messages can wait in the queue of each member. None
+    /// if the queues are unbounded.
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    /// What happens when the message is sent to the member with
+    /// the full queue.
+    pub fn overflow(&self) -> Overflow {
+        self.overflow
+    }
+
+    /// Whether the channel is publish-subscribe one. Such channel
+    /// delivers messages only to the members subscribed to their topics
+    /// and publishing does not fail when there are no subscribers.
+    pub fn publish_subscribe(&self) -> bool {
+        self.publish_subscribe.is_some()
+    }
+
+    /// Count of the last published messages that are replayed to each
+    /// new member. Zero if the channel is not publish-subscribe one.
+    pub fn replay(&self) -> usize {
+        self.publish_subscribe.unwrap_or(0)
+    }
+
+    /// Which members receive each message.
+    pub fn distribution(&self) -> Distribution {
+        self.distribution
+    }
+
+    /// Whether each message goes to single member. Members of such
+    /// channel compete for the messages and must acknowledge them.
+    pub fn work_queue(&self) -> bool {
+        self.distribution != Distribution::Broadcast
+    }
+
+    /// Whether the channel is reliable. Each message gets the sequence
+    /// number of its sender and each receiver gets it only once.
+    /// Received messages are kept until acknowledged and are delivered
+    /// again to the restarted receiver. Messages that the member
+    /// of the work queue left go to the next member that joins.
+    ///
+    /// Runtime does not retransmit the messages lost outside of it.
+    /// Sender may send the message again with its sequence number
+    /// and receivers that already got it drop the copy.
+    pub fn reliable(&self) -> bool {
+        self.reliable
+    }
+
+    /// Copy of the policy with other capacity of the queues. Capacity
+    /// cannot be zero, the same as in the
+    /// [builder](struct.PolicyBuilder.html#method.capacity).
+    pub fn with_capacity(mut self, capacity: Option<usize>,
+            overflow: Overflow) -> ChannelResult<Self> {
+        if capacity == Some(0) {
+            return Err(ChannelError::ZeroCapacity);
+        }
+        self.capacity = capacity;
+        self.overflow = overflow;
+        Ok(self)
+    }
 }
 
 impl PolicyBuilder {
@@ -103,21 +269,62 @@ impl PolicyBuilder {
         self.no_multiple_connectons = Some(val);
     }
 
+    /// Make the channel publish-subscribe one which replays given count
+    /// of the last messages to each new member. This field is optional
+    /// and channels just pass all messages to all members by default.
+    /// Publish-subscribe channel broadcasts the messages, so it cannot
+    /// be the work queue.
+    pub fn publish_subscribe(&mut self, replay: usize) {
+        self.publish_subscribe = Some(replay);
+    }
+
+    /// Choose which members receive each message. This field is optional
+    /// and messages are broadcast by default.
+    pub fn distribution(&mut self, val: Distribution) {
+        self.distribution = val;
+    }
+
+    /// Make the channel reliable. This field is optional and channels
+    /// neither number nor deduplicate the messages by default.
+    pub fn reliable(&mut self, val: bool) {
+        self.reliable = val;
+    }
+
+    /// Limit the queue of each member. This field is optional and queues
+    /// are unbounded by default.
+    pub fn capacity(&mut self, capacity: Option<usize>, overflow: Overflow) {
+        self.capacity = capacity;
+        self.overflow = overflow;
+    }
+
     /// Try building a Policy instance. If any of the fields
-    /// were unassigned then this builder will fail and return self
-    /// in error message. If everything goes well then policy instance
-    /// will be returned instead.
+    /// were unassigned, the capacity is zero or publish-subscribe channel
+    /// does not broadcast the messages then this builder will fail
+    /// and return self in error message. If everything goes well then
+    /// policy instance will be returned instead.
     pub fn build(self) -> Result<Policy, Self> {
         unwrap!(self, invitation_from_not_member);
         unwrap!(self, origin_can_leave);
         unwrap!(self, join_by_handle);
         unwrap!(self, no_multiple_connectons);
+        if self.capacity == Some(0) {
+            return Err(self);
+        }
+        let broadcast = self.distribution == Distribution::Broadcast;
+        if self.publish_subscribe.is_some() && !broadcast {
+            return Err(self);
+        }
 
         Ok(Policy {
             invitation_from_not_member,
             origin_can_leave,
             join_by_handle,
             no_multiple_connectons,
+            capacity: self.capacity,
+            overflow: self.overflow,
+            publish_subscribe: self.publish_subscribe,
+            distribution: self.distribution,
+            reliable: self.reliable,
         })
     }
 }
@@ -1,21 +1,29 @@
 use super::*;
-use std::rc::Rc;
+use std::time::Duration;
 
 /// Channel that allows communication among services.
 #[derive(Clone)]
 pub struct Channel {
 
     /// ID of the channel registered in the system.
-    id: usize,
+    id: Id,
 
     /// The service which originated this channel. Even though service
     /// could give up this channel, it still is identified as that which
     /// has origin from that service. The ID of the channel is related
     /// to the local network of the object of origin service.
-    origin: Rc<Service>,
+    origin: Shared<Service>,
 }
 
+/// Descriptor of the channel in the handle table of the thread.
+/// Like file descriptor, it is a small number which is valid only
+/// in the thread it belongs to. Lowest free number is used for each
+/// new descriptor.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Descriptor(usize);
+
 /// Error which appears when using Channel.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum ChannelError {
 
     /// This service is no longer a member
@@ -33,6 +41,99 @@ pub enum ChannelError {
     /// Multiple connections to the channel are forbiden. Only
     /// peer-to-peer single connections are allowed.
     MultipleConnectionsForbiden,
+
+    /// Cannot invite the service because this service is not a member
+    /// and the channel policy forbids invitations from not-a-members.
+    InvitationForbiden,
+
+    /// Origin service cannot leave the channel while there still are
+    /// other threads connected.
+    LeaveForbiden,
+
+    /// All other members have left the channel and no messages
+    /// are left to receive.
+    Disconnected,
+
+    /// Notice was received instead of the message.
+    Notice(ChannelNotice),
+
+    /// Thread was requested to cancel so it does not wait for messages
+    /// anymore.
+    Cancelled,
+
+    /// Queued message would exceed the memory quota of the sender.
+    /// Message gets charged to the sender until all receivers take it.
+    QuotaExceeded,
+
+    /// Message carries the grant which was not given by the sender
+    /// or was revoked.
+    InvalidGrant,
+
+    /// Descriptor is not present in the handle table of the thread.
+    BadDescriptor,
+
+    /// Target thread does not exist or has finished.
+    NoThread,
+
+    /// Handle of the channel or thread is stale. See
+    /// [Id](struct.Id.html).
+    StaleHandle,
+
+    /// Queue of some receiver has reached the capacity of the channel.
+    QueueFull,
+
+    /// Capacity of the queues must allow at least one message.
+    ZeroCapacity,
+
+    /// Sequence number set by the sender skips the next number of its
+    /// messages over reliable channel.
+    InvalidSequence,
+}
+
+/// Notice about the change of the channel members. Notices are
+/// delivered in order with the messages.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChannelNotice {
+
+    /// Thread with given ID is being shut down and leaves the channel.
+    Shutdown(usize),
+
+    /// Thread with given ID has panicked. It is either restarted or
+    /// leaves the channel.
+    PeerFault(usize),
+
+    /// Thread has died abnormally and was replaced by new thread
+    /// of the same service. New thread took the place of the old one
+    /// in the channel.
+    Restarted {
+
+        /// ID of the failed thread.
+        old: usize,
+
+        /// ID of the new thread.
+        new: usize,
+    },
+}
+
+/// Event of the channel the thread can wait for along with the events
+/// of other channels.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum ChannelEvent {
+
+    /// Receiving does not block. Message or notice is in the queue
+    /// or all other members have left the channel.
+    Receive,
+
+    /// Thread is a member and there are other members with room
+    /// for the message.
+    Send,
+
+    /// Count of the channel members has changed since the thread started
+    /// watching the channel.
+    Members,
+
+    /// Channel was closed or the thread is no longer its member.
+    Closed,
 }
 
 pub type ChannelResult<T> = Result<T, ChannelError>;
@@ -57,6 +158,35 @@ pub trait ChannelArchitecture: Ord {
     /// this service won't join and receives an error.
     fn join(&self) -> ChannelResult<()>;
 
+    /// Send the message to the other members of the channel. Channel
+    /// descriptors attached to the message are moved to the receiver
+    /// which joins their channels as if it was invited by the sender.
+    /// Descriptors can be passed only when there is single receiver.
+    /// If the queue of some receiver is full the
+    /// [overflow](../meta/enum.Overflow.html) of the policy applies.
+    fn send(&self, msg: Message) -> ChannelResult<()>;
+
+    /// Receive next message. Blocks until some message arrives or
+    /// until all other members leave the channel. If notice is next in
+    /// the queue it is returned as an error.
+    fn receive(&self) -> ChannelResult<Message>;
+
+    /// Receive next message but wait no longer than given timeout.
+    /// None is returned if timeout has passed.
+    fn receive_timeout(&self, timeout: Duration)
+            -> ChannelResult<Option<Message>>;
+
+    /// Receive next message if any is present. Does not block.
+    fn try_receive(&self) -> ChannelResult<Option<Message>>;
+
+    /// How many messages wait in the queue of this member. Sending
+    /// keeps it within the capacity given by the
+    /// [policy](../meta/struct.Policy.html#method.capacity), but it
+    /// may exceed the capacity after the capacity gets reduced, when
+    /// the messages are replayed to the new member or when the messages
+    /// of the member that left or restarted are delivered again.
+    fn occupancy(&self) -> usize;
+
     /// How many threads are connected to the channel.
     fn connections_count(&self) -> usize;
 
@@ -67,10 +197,33 @@ pub trait ChannelArchitecture: Ord {
     fn handle(&self) -> &Channel;
 }
 
+impl Descriptor {
+
+    /// Create the descriptor with given number.
+    pub fn new(index: usize) -> Self {
+        Descriptor(index)
+    }
+
+    /// Number of the descriptor in the handle table.
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
 impl Channel {
 
+    /// Create new channel handle.
+    pub fn new(origin: Shared<Service>, id: Id) -> Self {
+        Channel { id, origin }
+    }
+
     /// ID that uniquely identifies this channel inside the object.
     pub fn id(&self) -> usize {
+        self.id.index()
+    }
+
+    /// ID tagged with its generation. See [Id](struct.Id.html).
+    pub fn tagged_id(&self) -> Id {
         self.id
     }
 
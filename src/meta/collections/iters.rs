@@ -1,117 +1,356 @@
 use super::*;
-use std::rc::Rc;
+use std::collections::BTreeMap;
+use std::collections::Bound;
+
+/// How the iterator sees the changes of the collection made during
+/// the iteration.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IterMode {
+
+    /// Iterator walks the collection as it was when the iterator was
+    /// created and does not see later changes. Collection gets copied
+    /// on its first change while the snapshot is alive.
+    Snapshot,
+
+    /// Iterator walks the current state of the collection. Handles
+    /// added between the front and the back cursors get yielded, removed
+    /// ones do not. Each ID is yielded at most once.
+    Live,
+}
 
-/// Iterator over Object handles.
-#[derive(Clone)]
-pub struct ObjectIterator {
+/// Range of the handle IDs that were not yielded yet.
+pub type HandleRange = (Bound<usize>, Bound<usize>);
+
+/// Collection of handles that can be walked by
+/// [HandleIterator](struct.HandleIterator.html). Handles are ordered
+/// by their IDs.
+pub trait HandleSource<H: Clone> {
+
+    /// Handles as they are now. Returned map must not change so
+    /// the collection gets copied when it is changed while some map
+    /// is still referenced.
+    fn handles(&self) -> Shared<BTreeMap<usize, H>>;
+
+    /// First or last handle in given range of IDs as it is now. Live
+    /// iterators call it on each step, so collections that do not keep
+    /// their handles in the map should find just this one.
+    fn find(&self, range: HandleRange, from_back: bool)
+            -> Option<(usize, H)> {
+        let map = self.handles();
+        let mut range = map.range(range);
+        let (&id, handle) = if from_back {
+            range.next_back()?
+        } else {
+            range.next()?
+        };
+        Some((id, handle.clone()))
+    }
+}
+
+/// Collection of handles that copies its map on change only while
+/// some snapshot of it is alive. It can be changed during the iteration.
+///
+/// ```
+/// # use kobzar_ccs_usr::meta::*;
+/// let path = Path::parse("org.example").unwrap();
+/// let objects = HandleCollection::new();
+/// for id in 1..4 {
+///     objects.insert(id, Object::new(path.clone(), Id::new(id, 0)));
+/// }
+///
+/// let mut live = ObjectIterator::new(&objects, IterMode::Live);
+/// let mut snapshot = ObjectIterator::new(&objects, IterMode::Snapshot);
+/// assert_eq!(live.next().unwrap().id(), 1);
+/// assert_eq!(live.next_back().unwrap().id(), 3);
+///
+/// objects.remove(2);
+/// objects.insert(5, Object::new(path.clone(), Id::new(5, 0)));
+/// assert!(live.next().is_none());
+/// let snapshot = snapshot.into_snapshot().unwrap();
+/// assert_eq!(snapshot.len(), 3);
+/// let ids: Vec<usize> = snapshot.map(|obj| obj.id()).collect();
+/// assert_eq!(ids, vec![1, 2, 3]);
+/// ```
+pub struct HandleCollection<H: Clone> {
+
+    /// Current map of the handles.
+    map: SharedCell<Shared<BTreeMap<usize, H>>>,
+}
+
+/// Front and back cursors of the iterator over the handles.
+#[derive(Clone, Copy)]
+struct Cursors {
 
-    /// Vector containing all Objects.
-    vec: Vec<Object>,
+    /// IDs at or before this bound were already yielded from the front.
+    front: Bound<usize>,
 
-    /// Current iterator index.
-    i: usize,
+    /// IDs at or after this bound were already yielded from the back.
+    back: Bound<usize>,
 }
 
-/// Iterator over Service handles.
-#[derive(Clone)]
-pub struct ServiceIterator {
+/// Iterator over the handles of the collection as they were when it was
+/// created. It keeps the map of the collection, so its length is known.
+pub struct SnapshotIterator<H> {
 
-    /// Vector containing all Services.
-    vec: Vec<Service>,
+    /// Map taken when the iterator was created.
+    map: Shared<BTreeMap<usize, H>>,
 
-    /// Current iterator index.
-    i: usize,
+    /// Handles that were already yielded.
+    cursors: Cursors,
+
+    /// Count of handles left to yield.
+    left: usize,
 }
 
-/// Iterator over Channel handles.
-pub struct ChannelIterator {
+/// Lazy iterator over the handles of the collection. It borrows
+/// the collection and clones only the handles it yields.
+pub struct HandleIterator<'a, H: 'a + Clone> {
 
-    /// Vector containing all Channels.
-    vec: Vec<Channel>,
+    /// Collection that is walked.
+    source: &'a dyn HandleSource<H>,
 
-    /// Current iterator index.
-    i: usize,
+    /// Snapshot that is walked instead of the collection. None in live
+    /// mode.
+    snapshot: Option<SnapshotIterator<H>>,
+
+    /// Handles that were already yielded in live mode.
+    cursors: Cursors,
 }
 
+/// Iterator over Object handles.
+pub type ObjectIterator<'a> = HandleIterator<'a, Object>;
+
+/// Iterator over Service handles.
+pub type ServiceIterator<'a> = HandleIterator<'a, Service>;
+
+/// Iterator over Channel handles.
+pub type ChannelIterator<'a> = HandleIterator<'a, Channel>;
+
+/// Snapshot of Object handles.
+pub type ObjectSnapshot = SnapshotIterator<Object>;
+
+/// Snapshot of Service handles.
+pub type ServiceSnapshot = SnapshotIterator<Service>;
+
+/// Snapshot of Channel handles.
+pub type ChannelSnapshot = SnapshotIterator<Channel>;
+
 /// Iterator over [path nodes](some.html).
 pub struct PathNodeIterator {
 
     /// Last node of the path.
-    last: Option<Rc<Path>>,
+    last: Option<Path>,
 }
 
-impl Iterator for ObjectIterator {
+impl<H: Clone> HandleCollection<H> {
 
-    type Item = Object;
+    /// Create an empty collection.
+    pub fn new() -> Self {
+        HandleCollection { map: SharedCell::new(Default::default()) }
+    }
 
-    fn next(&mut self) -> Option<Object> {
-        if self.i >= self.vec.len() {
-            // Iterator is exhausted.
-            return None;
-        }
+    /// Add the handle with given ID. Previous handle with the same ID
+    /// is returned.
+    pub fn insert(&self, id: usize, handle: H) -> Option<H> {
+        self.map.write(|map| Shared::make_mut(map).insert(id, handle))
+    }
+
+    /// Remove the handle with given ID.
+    pub fn remove(&self, id: usize) -> Option<H> {
+        self.map.write(|map| {
+            if !map.contains_key(&id) {
+                return None;
+            }
+            Shared::make_mut(map).remove(&id)
+        })
+    }
+
+    /// Handle with given ID.
+    pub fn get(&self, id: usize) -> Option<H> {
+        self.map.read(|map| map.get(&id).cloned())
+    }
+
+    /// Count of the handles.
+    pub fn len(&self) -> usize {
+        self.map.read(|map| map.len())
+    }
 
-        let obj = self.vec[self.i].clone();
-        self.i += 1;
-        Some(obj)
+    /// Whether the collection is empty.
+    pub fn is_empty(&self) -> bool {
+        self.map.read(|map| map.is_empty())
     }
 }
 
-impl ExactSizeIterator for ObjectIterator {
+impl<H: Clone> Default for HandleCollection<H> {
 
-    fn len(&self) -> usize {
-        self.vec.len()
+    fn default() -> Self {
+        HandleCollection::new()
     }
 }
 
-impl Iterator for ServiceIterator {
+impl<H: Clone> HandleSource<H> for HandleCollection<H> {
+
+    fn handles(&self) -> Shared<BTreeMap<usize, H>> {
+        self.map.read(|map| map.clone())
+    }
+}
 
-    type Item = Service;
+impl Cursors {
 
-    fn next(&mut self) -> Option<Service> {
-        if self.i >= self.vec.len() {
-            // Iterator is exhausted.
-            return None;
+    /// Cursors before the first and after the last handle.
+    fn new() -> Self {
+        Cursors { front: Bound::Unbounded, back: Bound::Unbounded }
+    }
+
+    /// IDs that were not yielded yet. None if the cursors have met.
+    fn range(&self) -> Option<HandleRange> {
+        if let (Bound::Excluded(front), Bound::Excluded(back))
+                = (self.front, self.back) {
+            if front >= back {
+                return None;
+            }
         }
+        Some((self.front, self.back))
+    }
 
-        let serv = self.vec[self.i].clone();
-        self.i += 1;
-        Some(serv)
+    /// Move the front or the back cursor past given ID.
+    fn advance(&mut self, id: usize, from_back: bool) {
+        if from_back {
+            self.back = Bound::Excluded(id);
+        } else {
+            self.front = Bound::Excluded(id);
+        }
     }
 }
 
-impl ExactSizeIterator for ServiceIterator {
+impl<H: Clone> SnapshotIterator<H> {
+
+    /// Create new iterator over the handles the source has now.
+    pub fn new(source: &dyn HandleSource<H>) -> Self {
+        let map = source.handles();
+        SnapshotIterator {
+            left: map.len(),
+            map,
+            cursors: Cursors::new(),
+        }
+    }
+
+    /// Find the handle next to the front or the back cursor and move
+    /// that cursor past it.
+    fn step(&mut self, from_back: bool) -> Option<H> {
+        let (id, handle) = {
+            let mut range = self.map.range(self.cursors.range()?);
+            let (&id, handle) = if from_back {
+                range.next_back()?
+            } else {
+                range.next()?
+            };
+            (id, handle.clone())
+        };
+        self.cursors.advance(id, from_back);
+        self.left -= 1;
+        Some(handle)
+    }
+}
+
+impl<H: Clone> Iterator for SnapshotIterator<H> {
+
+    type Item = H;
+
+    fn next(&mut self) -> Option<H> {
+        self.step(false)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.left, Some(self.left))
+    }
+}
+
+impl<H: Clone> DoubleEndedIterator for SnapshotIterator<H> {
+
+    fn next_back(&mut self) -> Option<H> {
+        self.step(true)
+    }
+}
+
+impl<H: Clone> ExactSizeIterator for SnapshotIterator<H> {
 
     fn len(&self) -> usize {
-        self.vec.len()
+        self.left
     }
 }
 
-impl Iterator for ChannelIterator {
+impl<'a, H: Clone> HandleIterator<'a, H> {
+
+    /// Create new iterator over the handles of given source.
+    pub fn new(source: &'a dyn HandleSource<H>, mode: IterMode) -> Self {
+        let snapshot = match mode {
+            IterMode::Snapshot => Some(SnapshotIterator::new(source)),
+            IterMode::Live => None,
+        };
+        HandleIterator {
+            source,
+            snapshot,
+            cursors: Cursors::new(),
+        }
+    }
 
-    type Item = Channel;
+    /// Mode of this iterator.
+    pub fn mode(&self) -> IterMode {
+        match self.snapshot {
+            Some(_) => IterMode::Snapshot,
+            None => IterMode::Live,
+        }
+    }
 
-    fn next(&mut self) -> Option<Channel> {
-        if self.i >= self.vec.len() {
-            // Iterator is exhausted.
-            return None;
+    /// Snapshot this iterator walks. It knows its exact length. None
+    /// in live mode.
+    pub fn into_snapshot(self) -> Option<SnapshotIterator<H>> {
+        self.snapshot
+    }
+
+    /// Find the handle next to the front or the back cursor and move
+    /// that cursor past it.
+    fn step(&mut self, from_back: bool) -> Option<H> {
+        if let Some(ref mut snapshot) = self.snapshot {
+            return snapshot.step(from_back);
         }
 
-        let chan = self.vec[self.i].clone();
-        self.i += 1;
-        Some(chan)
+        let range = self.cursors.range()?;
+        let (id, handle) = self.source.find(range, from_back)?;
+        self.cursors.advance(id, from_back);
+        Some(handle)
     }
 }
 
-impl ExactSizeIterator for ChannelIterator {
+impl<'a, H: Clone> Iterator for HandleIterator<'a, H> {
 
-    fn len(&self) -> usize {
-        self.vec.len()
+    type Item = H;
+
+    fn next(&mut self) -> Option<H> {
+        self.step(false)
+    }
+
+    /// Exact in snapshot mode. Nothing is known in live mode because
+    /// the collection can change.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.snapshot {
+            Some(ref snapshot) => snapshot.size_hint(),
+            None => (0, None),
+        }
+    }
+}
+
+impl<'a, H: Clone> DoubleEndedIterator for HandleIterator<'a, H> {
+
+    fn next_back(&mut self) -> Option<H> {
+        self.step(true)
     }
 }
 
 impl PathNodeIterator {
 
-    pub fn new(node: Rc<Path>) -> Self {
+    pub fn new(node: Path) -> Self {
         PathNodeIterator {
             last: Some(node),
         }
@@ -120,17 +359,12 @@ impl PathNodeIterator {
 
 impl Iterator for PathNodeIterator {
 
-    type Item = Rc<Path>;
-
-    fn next(&mut self) -> Option<Rc<Path>> {
-        // Get Rc of current node.
-        let rc = self.last.clone();
+    type Item = Path;
 
+    fn next(&mut self) -> Option<Path> {
         // Move to previous node.
-        if let Some(ref v) = self.last.clone() {
-            self.last = v.parent().clone();
-        }
-
-        rc
+        let node = self.last.take()?;
+        self.last = node.parent();
+        Some(node)
     }
 }
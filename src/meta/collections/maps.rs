@@ -0,0 +1,162 @@
+use super::*;
+use std::collections::BTreeMap;
+use std::collections::btree_map;
+use std::iter::FromIterator;
+
+/// Channels keyed by the IDs of their handles. Only one instance
+/// of each channel is kept.
+///
+/// ```
+/// # use kobzar_ccs_usr::local::*;
+/// # use kobzar_ccs_usr::master::*;
+/// # use kobzar_ccs_usr::meta::*;
+/// let mut master = MyMaster::new();
+/// let entry = MyServiceEntry::new(|_: &Channel| {});
+/// let (_, chan) = master.spawn_thread(entry).unwrap();
+/// let id = chan.handle().id();
+///
+/// let channels = master.thread().connected_channels();
+/// assert!(channels.contains_id(id));
+///
+/// let map: ChannelArchMap<_> = channels.into_iter().collect();
+/// assert_eq!(map.get(id).unwrap().handle().id(), id);
+/// ```
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ChannelArchMap<C: ChannelArchitecture>(BTreeMap<usize, C>);
+
+/// Services keyed by the IDs of their handles.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ServiceArchMap<S: ServiceArchitecture>(BTreeMap<usize, S>);
+
+/// Objects keyed by the IDs of their handles.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ObjectArchMap<O: ObjectArchitecture>(BTreeMap<usize, O>);
+
+/// Implement the collection API of the map of architecture-dependent
+/// elements. Key of each element is the ID of its handle.
+macro_rules! arch_map {
+    ($map: ident, $set: ident, $arch: ident, $name: expr) => {
+
+impl<T> Default for $map<T>
+        where T: $arch {
+
+    fn default() -> Self {
+        $map(BTreeMap::new())
+    }
+}
+
+impl<T> $map<T>
+        where T: $arch {
+
+    /// Create an empty map.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    #[doc = concat!("Add the ", $name, " to the map. Previous ", $name,
+            " with the same handle ID is returned.")]
+    pub fn insert(&mut self, value: T) -> Option<T> {
+        self.0.insert(value.handle().id(), value)
+    }
+
+    #[doc = concat!("Remove the ", $name, " with given handle ID.")]
+    pub fn remove(&mut self, id: usize) -> Option<T> {
+        self.0.remove(&id)
+    }
+
+    #[doc = concat!("The ", $name, " with given handle ID.")]
+    pub fn get(&self, id: usize) -> Option<&T> {
+        self.0.get(&id)
+    }
+
+    #[doc = concat!("Whether the map contains the ", $name, " with \
+            given handle ID.")]
+    pub fn contains_id(&self, id: usize) -> bool {
+        self.0.contains_key(&id)
+    }
+
+    #[doc = concat!("Count of ", $name, "s in the map.")]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterator over the handle IDs in ascending order.
+    pub fn ids(&self) -> btree_map::Keys<'_, usize, T> {
+        self.0.keys()
+    }
+
+    #[doc = concat!("Iterator over the ", $name, "s ordered by their \
+            handle IDs.")]
+    pub fn values(&self) -> btree_map::Values<'_, usize, T> {
+        self.0.values()
+    }
+
+    #[doc = concat!("Iterator over the handle IDs and their ", $name, "s.")]
+    pub fn iter(&self) -> btree_map::Iter<'_, usize, T> {
+        self.0.iter()
+    }
+}
+
+impl<T> FromIterator<T> for $map<T>
+        where T: $arch {
+
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut map = $map::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<T> Extend<T> for $map<T>
+        where T: $arch {
+
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<T> From<$set<T>> for $map<T>
+        where T: $arch {
+
+    fn from(set: $set<T>) -> Self {
+        set.into_iter().collect()
+    }
+}
+
+impl<T> IntoIterator for $map<T>
+        where T: $arch {
+
+    type Item = (usize, T);
+
+    type IntoIter = btree_map::IntoIter<usize, T>;
+
+    fn into_iter(self) -> btree_map::IntoIter<usize, T> {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a $map<T>
+        where T: $arch {
+
+    type Item = (&'a usize, &'a T);
+
+    type IntoIter = btree_map::Iter<'a, usize, T>;
+
+    fn into_iter(self) -> btree_map::Iter<'a, usize, T> {
+        self.0.iter()
+    }
+}
+
+    }
+}
+
+arch_map!(ServiceArchMap, ServiceArchSet, ServiceArchitecture, "service");
+arch_map!(ChannelArchMap, ChannelArchSet, ChannelArchitecture, "channel");
+arch_map!(ObjectArchMap, ObjectArchSet, ObjectArchitecture, "object");
@@ -10,3 +10,7 @@ pub use self::iters::*;
 /// Sets of handles.
 mod sets;
 pub use self::sets::*;
+
+/// Maps of handles keyed by their IDs.
+mod maps;
+pub use self::maps::*;
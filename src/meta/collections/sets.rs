@@ -1,43 +1,252 @@
 use super::*;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::collections::btree_map;
+use std::iter::{self, FromIterator};
+
+/// Elements of the arch set grouped by the IDs of their handles.
+type Buckets<T> = BTreeMap<usize, BTreeSet<T>>;
+
+/// Iterator over the elements of the arch set.
+pub type ArchSetIter<'a, T> = iter::Flatten<btree_map::Values<'a, usize,
+        BTreeSet<T>>>;
+
+/// Iterator that moves the elements out of the arch set.
+pub type ArchSetIntoIter<T> = iter::Flatten<btree_map::IntoValues<usize,
+        BTreeSet<T>>>;
 
 pub type ChannelSet = BTreeSet<Channel>;
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub struct ChannelArchSet<C: ChannelArchitecture>(BTreeSet<C>);
+pub struct ChannelArchSet<C: ChannelArchitecture>(Buckets<C>, usize);
 
 pub type ServiceSet = BTreeSet<Service>;
 
+/// Set of services. Services can be looked up by the IDs of their
+/// handles.
+///
+/// ```
+/// # use kobzar_ccs_usr::local::*;
+/// # use kobzar_ccs_usr::meta::*;
+/// # use std::sync::Arc;
+/// let mut implementer = MyImplementer::new("org.example.impl").unwrap();
+/// implementer.add_service("first", |_: &Channel| {});
+/// implementer.add_service("second", |_: &Channel| {});
+/// let object = MyObject::new(Arc::new(implementer));
+///
+/// let services = object.services();
+/// let first = services.iter()
+///         .find(|srv| srv.handle().name() == "first")
+///         .unwrap();
+/// assert!(services.contains_id(first.handle().id()));
+/// assert_eq!(services.ids().len(), 2);
+/// assert!(services.is_subset(services));
+/// assert_eq!(services.intersection(services).count(), 2);
+/// ```
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub struct ServiceArchSet<S: ServiceArchitecture>(BTreeSet<S>);
+pub struct ServiceArchSet<S: ServiceArchitecture>(Buckets<S>, usize);
 
 pub type ObjectSet = BTreeSet<Object>;
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub struct ObjectArchSet<O: ObjectArchitecture>(BTreeSet<O>);
+pub struct ObjectArchSet<O: ObjectArchitecture>(Buckets<O>, usize);
 
 pub type InterfaceSet = BTreeSet<Interface>;
 
-impl<T> Default for ServiceArchSet<T>
-        where T: ServiceArchitecture {
+/// Implement the collection API of the set of architecture-dependent
+/// elements. Elements are kept in the buckets keyed by the IDs of their
+/// handles, so they are looked up by the IDs in logarithmic time.
+/// Second field is the count of the elements.
+macro_rules! arch_set {
+    ($set: ident, $arch: ident, $name: expr) => {
+
+impl<T> Default for $set<T>
+        where T: $arch {
 
     fn default() -> Self {
-        ServiceArchSet(BTreeSet::new())
+        $set(BTreeMap::new(), 0)
     }
 }
 
-impl<T> Default for ChannelArchSet<T>
-        where T: ChannelArchitecture {
+impl<T> $set<T>
+        where T: $arch {
 
-    fn default() -> Self {
-        ChannelArchSet(BTreeSet::new())
+    /// Create an empty set.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    #[doc = concat!("Add the ", $name, " to the set. Returns false if it \
+            already was present.")]
+    pub fn insert(&mut self, value: T) -> bool {
+        let bucket = self.0.entry(value.handle().id()).or_default();
+        let inserted = bucket.insert(value);
+        if inserted {
+            self.1 += 1;
+        }
+        inserted
+    }
+
+    #[doc = concat!("Remove the ", $name, " from the set. Returns false \
+            if it was not present.")]
+    pub fn remove(&mut self, value: &T) -> bool {
+        let id = value.handle().id();
+        let removed = match self.0.get_mut(&id) {
+            Some(bucket) => bucket.remove(value),
+            None => false,
+        };
+        if removed {
+            self.1 -= 1;
+            if self.0[&id].is_empty() {
+                self.0.remove(&id);
+            }
+        }
+        removed
+    }
+
+    #[doc = concat!("Whether the set contains the ", $name, ".")]
+    pub fn contains(&self, value: &T) -> bool {
+        self.0.get(&value.handle().id())
+                .is_some_and(|bucket| bucket.contains(value))
+    }
+
+    #[doc = concat!("Find the ", $name, " by the ID of its handle.")]
+    pub fn get(&self, id: usize) -> Option<&T> {
+        self.0.get(&id).and_then(|bucket| bucket.iter().next())
+    }
+
+    #[doc = concat!("Whether the set contains the ", $name, " with \
+            given handle ID.")]
+    pub fn contains_id(&self, id: usize) -> bool {
+        self.0.contains_key(&id)
+    }
+
+    #[doc = concat!("Remove and return the ", $name, " with given \
+            handle ID. First one is taken if there are more of them.")]
+    pub fn take(&mut self, id: usize) -> Option<T> {
+        let (taken, empty) = {
+            let bucket = self.0.get_mut(&id)?;
+            (bucket.pop_first(), bucket.is_empty())
+        };
+        if empty {
+            self.0.remove(&id);
+        }
+        self.1 -= 1;
+        taken
+    }
+
+    #[doc = concat!("IDs of the handles of all ", $name, "s in the set.")]
+    pub fn ids(&self) -> BTreeSet<usize> {
+        self.0.keys().cloned().collect()
+    }
+
+    #[doc = concat!("Count of ", $name, "s in the set.")]
+    pub fn len(&self) -> usize {
+        self.1
+    }
+
+    /// Whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.1 == 0
+    }
+
+    #[doc = concat!("Iterator over the ", $name, "s of the set ordered \
+            by their handle IDs.")]
+    pub fn iter(&self) -> ArchSetIter<'_, T> {
+        self.0.values().flatten()
+    }
+
+    #[doc = concat!("Iterator over the ", $name, "s that are in this set \
+            or in other one.")]
+    pub fn union<'a>(&'a self, other: &'a Self)
+            -> impl Iterator<Item = &'a T> + 'a {
+        self.iter().chain(other.difference(self))
+    }
+
+    #[doc = concat!("Iterator over the ", $name, "s that are both in this \
+            set and in other one.")]
+    pub fn intersection<'a>(&'a self, other: &'a Self)
+            -> impl Iterator<Item = &'a T> + 'a {
+        self.iter().filter(move |value| other.contains(value))
+    }
+
+    #[doc = concat!("Iterator over the ", $name, "s that are in this set \
+            but not in other one.")]
+    pub fn difference<'a>(&'a self, other: &'a Self)
+            -> impl Iterator<Item = &'a T> + 'a {
+        self.iter().filter(move |value| !other.contains(value))
+    }
+
+    #[doc = concat!("Iterator over the ", $name, "s that are in only one \
+            of the sets.")]
+    pub fn symmetric_difference<'a>(&'a self, other: &'a Self)
+            -> impl Iterator<Item = &'a T> + 'a {
+        self.difference(other).chain(other.difference(self))
+    }
+
+    /// Whether all elements of this set are in other one.
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.len() <= other.len()
+                && self.iter().all(|value| other.contains(value))
+    }
+
+    /// Whether all elements of other set are in this one.
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Whether the sets have no elements in common.
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.intersection(other).next().is_none()
     }
 }
 
-impl<T> Default for ObjectArchSet<T>
-        where T: ObjectArchitecture {
+impl<T> FromIterator<T> for $set<T>
+        where T: $arch {
 
-    fn default() -> Self {
-        ObjectArchSet(BTreeSet::new())
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = $set::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T> Extend<T> for $set<T>
+        where T: $arch {
+
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<T> IntoIterator for $set<T>
+        where T: $arch {
+
+    type Item = T;
+
+    type IntoIter = ArchSetIntoIter<T>;
+
+    fn into_iter(self) -> ArchSetIntoIter<T> {
+        self.0.into_values().flatten()
     }
 }
+
+impl<'a, T> IntoIterator for &'a $set<T>
+        where T: $arch {
+
+    type Item = &'a T;
+
+    type IntoIter = ArchSetIter<'a, T>;
+
+    fn into_iter(self) -> ArchSetIter<'a, T> {
+        self.iter()
+    }
+}
+
+    }
+}
+
+arch_set!(ServiceArchSet, ServiceArchitecture, "service");
+arch_set!(ChannelArchSet, ChannelArchitecture, "channel");
+arch_set!(ObjectArchSet, ObjectArchitecture, "object");
@@ -0,0 +1,49 @@
+/// Access of the thread to the granted memory region.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Access {
+
+    /// Region can only be read.
+    ReadOnly,
+
+    /// Region can be read and written.
+    ReadWrite,
+}
+
+/// Handle of the memory region granted to other threads. It is sent
+/// along with the message over the channel and receivers use it
+/// to access the region without copying.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Grant {
+
+    /// Unique ID of the grant.
+    id: usize,
+
+    /// Size of the region in bytes.
+    size: usize,
+
+    /// Access given to the receivers.
+    access: Access,
+}
+
+impl Grant {
+
+    /// Create new grant handle.
+    pub fn new(id: usize, size: usize, access: Access) -> Self {
+        Grant { id, size, access }
+    }
+
+    /// Unique ID of the grant.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Size of the region in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Access given to the receivers.
+    pub fn access(&self) -> Access {
+        self.access
+    }
+}
@@ -0,0 +1,42 @@
+/// ID of the element of the network tagged with its generation.
+///
+/// IDs of destroyed elements may be reused and the generation tells
+/// the elements with the same ID apart. Handle which generation is not
+/// the current one of its ID is stale: it refers to the element that
+/// was destroyed and must not act on the new element with the same ID.
+///
+/// ```
+/// # use kobzar_ccs_usr::meta::Id;
+/// let first = Id::new(5, 0);
+/// let reused = Id::new(5, 1);
+///
+/// assert_eq!(first.index(), reused.index());
+/// assert_ne!(first, reused);
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Id {
+
+    /// Index which is unique among the live elements of the same kind.
+    index: usize,
+
+    /// Generation of the index. It grows each time the index is reused.
+    generation: usize,
+}
+
+impl Id {
+
+    /// Create ID with given index and generation.
+    pub fn new(index: usize, generation: usize) -> Self {
+        Id { index, generation }
+    }
+
+    /// Index which is unique among the live elements of the same kind.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Generation of the index.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+}
@@ -1,14 +1,14 @@
 use super::*;
-use std::rc::Rc;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::cmp::Ordering;
 
 /// Interface defines some service that must be implemented by the
 /// object to support some group of functionality.
+#[derive(Clone)]
 pub struct Interface {
 
     /// Vendor of this interface.
-    vendor_path: Rc<Path>,
+    vendor_path: Path,
 
     /// Version of this interface.
     version: Version,
@@ -16,16 +16,69 @@ pub struct Interface {
     /// Set of service names this interface requires to be implemented.
     services: BTreeSet<String>,
 
+    /// Signatures of the methods by the names of the services
+    /// and the methods.
+    methods: BTreeMap<(String, String), MethodSignature>,
+
     /// Interfaces that must be implemented in order to implement
     /// this interface.
-    dependencies: BTreeSet<Rc<Interface>>,
+    dependencies: BTreeSet<Shared<Interface>>,
+}
+
+/// Signature of the method of the interface service. Types
+/// of the request and the response are given by their names.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MethodSignature {
+
+    /// Name of the request type.
+    request: String,
+
+    /// Name of the response type.
+    response: String,
 }
 
 impl Interface {
 
+    /// Create new interface definition.
+    pub fn new(vendor_path: Path, version: Version,
+            services: BTreeSet<String>,
+            dependencies: BTreeSet<Shared<Interface>>) -> Self {
+        Interface {
+            vendor_path,
+            version,
+            services,
+            methods: Default::default(),
+            dependencies,
+        }
+    }
+
+    /// Declare the method of the service. False is returned if
+    /// the interface does not declare the service or the method
+    /// was declared already.
+    pub fn add_method(&mut self, service: &str, name: &str,
+            signature: MethodSignature) -> bool {
+        if !self.services.contains(service) {
+            return false;
+        }
+
+        let key = (service.to_string(), name.to_string());
+        if self.methods.contains_key(&key) {
+            return false;
+        }
+        self.methods.insert(key, signature);
+        true
+    }
+
+    /// Signature of the method of the service. None if the method
+    /// is not declared.
+    pub fn method(&self, service: &str, name: &str)
+            -> Option<&MethodSignature> {
+        self.methods.get(&(service.to_string(), name.to_string()))
+    }
+
     /// Vendor of this interface.
     pub fn vendor(&self) -> &Path {
-        self.vendor_path.as_ref()
+        &self.vendor_path
     }
 
     /// Version of this interface definition.
@@ -41,9 +94,22 @@ impl Interface {
 
     /// Interfaces that must be implemented by the object first in order to
     /// implement this interface.
-    pub fn dependencies(&self) -> &BTreeSet<Rc<Interface>> {
+    pub fn dependencies(&self) -> &BTreeSet<Shared<Interface>> {
         &self.dependencies
     }
+
+    /// All interfaces this interface depends on, either directly or
+    /// through the dependencies of its dependencies.
+    pub fn all_dependencies(&self) -> BTreeSet<Shared<Interface>> {
+        let mut all = BTreeSet::new();
+        let mut stack: Vec<&Shared<Interface>> = self.dependencies.iter().collect();
+        while let Some(dep) = stack.pop() {
+            if all.insert(dep.clone()) {
+                stack.extend(dep.dependencies.iter());
+            }
+        }
+        all
+    }
 }
 
 impl PartialEq for Interface {
@@ -65,7 +131,12 @@ impl PartialEq for Interface {
         }
 
         // Check services.
-        if self.services() == other.services() {
+        if self.services() != other.services() {
+            return false;
+        }
+
+        // Check methods.
+        if self.methods != other.methods {
             return false;
         }
 
@@ -75,6 +146,28 @@ impl PartialEq for Interface {
 
 impl Eq for Interface {}
 
+impl MethodSignature {
+
+    /// Create the signature from the names of the request
+    /// and the response types.
+    pub fn new(request: &str, response: &str) -> Self {
+        MethodSignature {
+            request: request.to_string(),
+            response: response.to_string(),
+        }
+    }
+
+    /// Name of the request type.
+    pub fn request(&self) -> &str {
+        &self.request
+    }
+
+    /// Name of the response type.
+    pub fn response(&self) -> &str {
+        &self.response
+    }
+}
+
 impl PartialOrd for Interface {
 
     fn partial_cmp(&self, other: &Interface) -> Option<Ordering> {
@@ -0,0 +1,122 @@
+use super::{Descriptor, Grant, Path};
+
+/// Message that is transfered over the channel.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Message {
+
+    /// Raw data of the message.
+    data: Vec<u8>,
+
+    /// Memory regions granted to the receivers of the message.
+    grants: Vec<Grant>,
+
+    /// Channel descriptors passed to the receiver of the message.
+    descriptors: Vec<Descriptor>,
+
+    /// Topic of the message published over publish-subscribe channel.
+    /// It is kept in the string form so the message can be passed
+    /// to other threads.
+    topic: Option<String>,
+
+    /// Sequence number of the message sent over reliable channel.
+    sequence: Option<u64>,
+}
+
+impl Message {
+
+    /// Create new message with given data.
+    pub fn new(data: Vec<u8>) -> Self {
+        Message {
+            data,
+            grants: Default::default(),
+            descriptors: Default::default(),
+            topic: None,
+            sequence: None,
+        }
+    }
+
+    /// Attach the memory region grant to the message.
+    pub fn with_grant(mut self, grant: Grant) -> Self {
+        self.grants.push(grant);
+        self
+    }
+
+    /// Attach the channel descriptor of the sender to the message.
+    /// Descriptor is moved to the handle table of the receiver when
+    /// the message is sent. Duplicate the descriptor to keep the access
+    /// to the channel.
+    pub fn with_descriptor(mut self, descriptor: Descriptor) -> Self {
+        self.descriptors.push(descriptor);
+        self
+    }
+
+    /// Set the topic of the message. Subscribers of the publish-subscribe
+    /// channel receive only the messages which topics match their
+    /// patterns.
+    pub fn with_topic(mut self, topic: Path) -> Self {
+        self.topic = Some(topic.to_string());
+        self
+    }
+
+    /// Set the sequence number of the message. Reliable channel numbers
+    /// the messages itself so the sender sets it only to send again
+    /// the message which may have been lost. Receivers that already got
+    /// the message with this number drop the copy. Numbers above the next
+    /// one of the sender are rejected.
+    pub fn with_sequence(mut self, sequence: u64) -> Self {
+        self.sequence = Some(sequence);
+        self
+    }
+
+    /// Data of the message.
+    pub fn data(&self) -> &[u8] {
+        self.data.as_slice()
+    }
+
+    /// Memory regions granted to the receivers of the message.
+    pub fn grants(&self) -> &[Grant] {
+        &self.grants
+    }
+
+    /// Channel descriptors passed with the message. Descriptors
+    /// of the received message belong to the handle table of the receiver.
+    pub fn descriptors(&self) -> &[Descriptor] {
+        &self.descriptors
+    }
+
+    /// Topic of the message if any.
+    pub fn topic(&self) -> Option<Path> {
+        self.topic.as_ref().and_then(|topic| Path::parse(topic))
+    }
+
+    /// Sequence number of the message if it was sent over reliable
+    /// channel.
+    pub fn sequence(&self) -> Option<u64> {
+        self.sequence
+    }
+
+    /// Replace passed descriptors. Used by the master to translate them
+    /// to the handle table of the receiver.
+    pub fn set_descriptors(&mut self, descriptors: Vec<Descriptor>) {
+        self.descriptors = descriptors;
+    }
+
+    /// Take the data out of the message.
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+impl From<Vec<u8>> for Message {
+
+    fn from(data: Vec<u8>) -> Message {
+        Message::new(data)
+    }
+}
+
+impl<'a> From<&'a [u8]> for Message {
+
+    fn from(data: &'a [u8]) -> Message {
+        Message::new(data.to_vec())
+    }
+}
@@ -1,9 +1,17 @@
 //! Module which gives handles to access elements of the network.
 
+/// Reference counting of the handles.
+mod shared;
+pub use self::shared::*;
+
 /// Implementation and declaration for Version struct.
 mod version;
 pub use self::version::*;
 
+/// IDs of the elements tagged with their generations.
+mod id;
+pub use self::id::*;
+
 /// Declaration and implementation for Path struct.
 mod path;
 pub use self::path::*;
@@ -27,6 +35,18 @@ pub use self::service::*;
 mod channel;
 pub use self::channel::*;
 
+/// Messages that are sent over the channels.
+mod message;
+pub use self::message::*;
+
+/// Topics of the published messages.
+mod topic;
+pub use self::topic::*;
+
+/// Memory regions granted to other threads.
+mod grant;
+pub use self::grant::*;
+
 /// All threading structs and functions.
 mod thread;
 pub use self::thread::*;
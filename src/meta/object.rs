@@ -1,5 +1,4 @@
 use super::*;
-use std::rc::Rc;
 
 /// Handle of the object. Allows to access object's data in system
 /// CCS controller.
@@ -11,23 +10,94 @@ pub struct Object {
     /// They can be distinguished by ID which always is unique among the
     /// local network. The last node of the path is the name of this
     /// object.
-    path: Rc<Path>,
+    path: Path,
 
     /// Object ID in some network.
-    id: usize,
+    id: Id,
 }
 
+/// State of the object lifecycle.
+///
+/// Object gets loaded and initialized when it is created. After that
+/// it is stopped until master starts it. Running object can be
+/// stopped and started again. Object fails when any of its lifecycle
+/// hooks fails. Only stopped or failed objects can be destroyed.
+///
+/// ```
+/// # use kobzar_ccs_usr::meta::ObjectState::*;
+/// assert!(Stopped.can_switch(Running));
+/// assert!(!Stopped.can_switch(Stopping));
+/// assert!(Running.can_switch(Failed));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ObjectState {
+
+    /// Object is being loaded from implementer source.
+    Loading,
+
+    /// Object is loaded and is being initialized.
+    Initializing,
+
+    /// Object services are accessible.
+    Running,
+
+    /// Object services are being stopped and their channels get closed.
+    Stopping,
+
+    /// Object is initialized but its services are not accessible.
+    Stopped,
+
+    /// Some of the lifecycle hooks has failed.
+    Failed,
+}
+
+/// Error which appears when managing the objects.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ObjectError {
+
+    /// Object is not loaded or there is no implementer of such object.
+    NotFound,
+
+    /// Object is already loaded.
+    AlreadyLoaded,
+
+    /// Object cannot switch from its current state to the requested one.
+    InvalidTransition {
+
+        /// Current state of the object.
+        from: ObjectState,
+
+        /// State that was requested.
+        to: ObjectState,
+    },
+
+    /// Lifecycle hook of the object has failed.
+    HookFailed,
+
+    /// Threads of the object did not stop before the deadline.
+    DeadlineExceeded,
+
+    /// Handle of the object or service is stale. See
+    /// [Id](struct.Id.html).
+    StaleHandle,
+}
+
+pub type ObjectResult<T> = Result<T, ObjectError>;
+
 /// Architecture-dependent object implementation.
 pub trait ObjectArchitecture: Ord {
 
     /// Service architecture part.
     type S: ServiceArchitecture;
 
+    /// The handle for Object covered by this wrap.
+    fn handle(&self) -> &Object;
+
     /// Service iterator over all accessible for this thread local services.
-    fn service_iter(&self) -> ServiceIterator;
+    fn service_iter(&self, mode: IterMode) -> ServiceIterator<'_>;
 
     /// Object iterator over all accessible for current subobject.
-    fn object_iter(&self) -> ObjectIterator;
+    fn object_iter(&self, mode: IterMode) -> ObjectIterator<'_>;
 
     /// Set of services accessible for current thread.
     fn services(&self) -> &ServiceArchSet<Self::S>;
@@ -35,24 +105,80 @@ pub trait ObjectArchitecture: Ord {
     /// Set of objects accessible for current thread.
     fn objects(&self) -> &ObjectArchSet<Self>
             where Self: Sized;
+
+    /// Hook which is called when the object gets created. Error moves
+    /// the object to failed state.
+    fn on_initialize(&self) -> ObjectResult<()> {
+        Ok(())
+    }
+
+    /// Hook which is called before the services of the object get
+    /// accessible. Error moves the object to failed state.
+    fn on_start(&self) -> ObjectResult<()> {
+        Ok(())
+    }
+
+    /// Hook which is called after all threads of the object services
+    /// were stopped and their channels were closed.
+    fn on_stop(&self) {
+    }
+}
+
+impl ObjectState {
+
+    /// Whether object can switch from this state to given one.
+    pub fn can_switch(self, to: ObjectState) -> bool {
+        use self::ObjectState::*;
+
+        match (self, to) {
+            (Loading, Initializing) => true,
+            (Initializing, Stopped) => true,
+            (Stopped, Running) => true,
+            (Running, Stopping) => true,
+            (Stopping, Stopped) => true,
+            (Failed, _) => false,
+            (_, Failed) => true,
+            _ => false,
+        }
+    }
+
+    /// Check whether object can switch to given state and return
+    /// that state or the error.
+    pub fn switch(self, to: ObjectState) -> ObjectResult<ObjectState> {
+        if self.can_switch(to) {
+            Ok(to)
+        } else {
+            Err(ObjectError::InvalidTransition { from: self, to })
+        }
+    }
 }
 
 impl Object {
 
+    /// Create new object handle with given path and ID.
+    pub fn new(path: Path, id: Id) -> Self {
+        Object { path, id }
+    }
+
     /// Object full path including the name of this object.
     pub fn path(&self) -> &Path {
-        self.path.as_ref()
+        &self.path
     }
 
     /// The name of this object.
     pub fn name(&self) -> &str {
-        let path = self.path.as_ref();
+        let path = &self.path;
         path.name()
     }
 
     /// ID that uniquely identifies this object. See
     /// [id field](struct.Object.html#structfield.id).
     pub fn id(&self) -> usize {
+        self.id.index()
+    }
+
+    /// ID tagged with its generation. See [Id](struct.Id.html).
+    pub fn tagged_id(&self) -> Id {
         self.id
     }
 }
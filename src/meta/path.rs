@@ -1,7 +1,13 @@
-use super::PathNodeIterator;
+use super::{PathNodeIterator, Shared, WeakShared};
 use std::collections::BTreeMap;
 use std::cmp::Ordering;
-use std::rc::{Rc, Weak};
+use std::fmt;
+use std::sync::atomic::{self, AtomicUsize};
+
+#[cfg(not(feature = "sync"))]
+use std::cell::RefCell;
+#[cfg(feature = "sync")]
+use std::sync::{Mutex, PoisonError};
 
 /// The name of the path node.
 /// It stores only the valid name of the node. Before assigning
@@ -12,31 +18,48 @@ use std::rc::{Rc, Weak};
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
 struct Name(String);
 
-/// The name of the path node. Used in maps (like
-/// std::collections::BTreeMap) to order the elements but avoid clonning
-/// actual names. The instance is expected to live less than the data
-/// it refers to. Usually it is achieved by deleting map entry
-/// first and then releasing the child it pointed to.
-#[derive(Clone, Copy)]
-struct WeakName(*const Name);
+/// Node of the path. Nodes never change after they are created, so
+/// path handles walk and compare them without any locking.
+struct Node {
+
+    /// Unique ID of the node. IDs are never reused.
+    id: usize,
+
+    /// Name of the node.
+    name: Shared<str>,
 
-/// Path to some interface or object.
-pub struct Path {
+    /// Parent node. Root nodes have no parent.
+    parent: Option<Shared<Node>>,
 
-    /// Name of current path node.
-    name: Name,
+    /// Count of the nodes above this one. Root nodes have zero depth.
+    depth: usize,
+}
 
-    /// Parent node if any. Root node never has a parent. All
-    /// other nodes are initialized with some parent node.
-    parent: Option<Rc<Path>>,
+/// Interned table of the nodes by the IDs of their parents and their
+/// names. Each path is stored only once, so equal paths always share
+/// the node. Table is used only when the node gets created or freed.
+type Table = BTreeMap<(Option<usize>, Shared<str>), WeakShared<Node>>;
 
-    /// Self weak reference. Used to pass self reference to children.
-    selfref: Weak<Path>,
+/// Table of all alive nodes.
+#[cfg(feature = "sync")]
+static TABLE: Mutex<Table> = Mutex::new(BTreeMap::new());
 
-    /// Map of children.
-    children: BTreeMap<WeakName, Rc<Path>>,
+#[cfg(not(feature = "sync"))]
+thread_local! {
+    /// Table of the alive nodes of this thread. Without `sync` feature
+    /// paths are bound to the thread that created them.
+    static TABLE: RefCell<Table> = const { RefCell::new(BTreeMap::new()) };
 }
 
+/// Counter used to generate IDs of the nodes.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// Path to some interface or object. The path is a handle of the shared
+/// immutable node interned in the table. Paths are compared by their
+/// nodes and ordered by node names without any allocations.
+#[derive(Clone)]
+pub struct Path(Shared<Node>);
+
 impl Name {
 
     /// Create new Name from string. The Name will not be created if
@@ -72,22 +95,18 @@ impl Name {
     fn is_allowed_char(c: char) -> bool {
         Self::is_allowed_first_char(c)
         ||
-        (c >= '0' && c <= '9')
+        c.is_ascii_digit()
     }
 
     /// Whether passed character can be used in the beginning of path node
     /// name.
     fn is_allowed_first_char(c: char) -> bool {
-        (c >= 'A' && c <= 'Z')
+        c.is_ascii_uppercase()
         ||
-        (c >= 'a' && c <= 'z')
+        c.is_ascii_lowercase()
         ||
         c == '_'
     }
-
-    pub fn weak(&self) -> WeakName {
-        unsafe { WeakName::from(self) }
-    }
 }
 
 impl AsRef<str> for Name {
@@ -97,175 +116,179 @@ impl AsRef<str> for Name {
     }
 }
 
-impl WeakName {
+impl Node {
 
-    /// Create new WeakName reference to given name.
-    ///
-    /// # Safety
-    /// Name must outlive WeakName instance. Otherwise, the struct will
-    /// refer to unexisting data.
-    pub unsafe fn from(name: &Name) -> Self {
-        WeakName(name as  _)
+    /// Call the function with the table of the nodes. Table is never
+    /// left in inconsistent state so poisoned lock is used as it is.
+    #[cfg(feature = "sync")]
+    fn with_table<F, R>(f: F) -> Option<R>
+            where F: FnOnce(&mut Table) -> R {
+        Some(f(&mut TABLE.lock().unwrap_or_else(PoisonError::into_inner)))
     }
-}
-
-impl AsRef<Name> for WeakName {
 
-    fn as_ref(&self) -> &Name {
-        unsafe { &*self.0 }
+    /// Call the function with the table of the nodes. None is returned
+    /// if the table is already destroyed because the thread exits.
+    #[cfg(not(feature = "sync"))]
+    fn with_table<F, R>(f: F) -> Option<R>
+            where F: FnOnce(&mut Table) -> R {
+        TABLE.try_with(|table| f(&mut table.borrow_mut())).ok()
     }
-}
 
-impl AsRef<str> for WeakName {
+    /// Find the child of given node or the root node with given name.
+    /// New node is created if there is no such one yet.
+    fn intern(parent: Option<&Shared<Node>>, name: Name) -> Shared<Node> {
+        let key = (parent.map(|node| node.id), Shared::from(name.0));
+        Self::with_table(|table| {
+            let found = table.get(&key).and_then(WeakShared::upgrade);
+            if let Some(node) = found {
+                return node;
+            }
 
-    fn as_ref(&self) -> &str {
-        unsafe { (&*self.0).as_ref() }
+            let node = Shared::new(Node {
+                id: NEXT_ID.fetch_add(1, atomic::Ordering::Relaxed),
+                name: key.1.clone(),
+                parent: parent.cloned(),
+                depth: parent.map_or(0, |node| node.depth + 1),
+            });
+            table.insert(key, Shared::downgrade(&node));
+            node
+        }).expect("table of the paths is destroyed")
     }
-}
-
-impl PartialEq for WeakName {
 
-    fn eq(&self, other: &WeakName) -> bool {
-        let s0: &str = self.as_ref();
-        let s1: &str = other.as_ref();
-
-        s0 == s1
+    /// Ancestor of the node at given depth.
+    fn ancestor(mut node: &Node, depth: usize) -> &Node {
+        while node.depth > depth {
+            node = node.parent.as_ref().unwrap();
+        }
+        node
     }
-}
 
-impl Eq for WeakName {}
-
-impl PartialOrd for WeakName {
+    /// Compare node names of the paths starting from the root.
+    fn compare(a: &Node, b: &Node) -> Ordering {
+        // Shorter path goes first if the longer one starts with it.
+        let mut x = Self::ancestor(a, b.depth);
+        let mut y = Self::ancestor(b, a.depth);
+        if x.id == y.id {
+            return a.depth.cmp(&b.depth);
+        }
 
-    fn partial_cmp(&self, other: &WeakName) -> Option<Ordering> {
-        Some(self.cmp(other))
+        // Find the first nodes that differ. They have the same parent
+        // so their names differ.
+        loop {
+            let (px, py) = (x.parent.as_ref(), y.parent.as_ref());
+            if px.map(|node| node.id) == py.map(|node| node.id) {
+                return x.name.cmp(&y.name);
+            }
+            x = px.unwrap();
+            y = py.unwrap();
+        }
     }
 }
 
-impl Ord for WeakName {
-
-    fn cmp(&self, other: &WeakName) -> Ordering {
-        let s0: &str = self.as_ref();
-        let s1: &str = other.as_ref();
-
-        s0.cmp(s1)
+impl Drop for Node {
+
+    fn drop(&mut self) {
+        // Other node with the same path may have taken the place
+        // in the table already.
+        let key = (self.parent.as_ref().map(|node| node.id),
+                self.name.clone());
+        Self::with_table(|table| {
+            let own = table.get(&key)
+                    .is_some_and(|weak| ::std::ptr::eq(weak.as_ptr(), self));
+            if own {
+                table.remove(&key);
+            }
+        });
     }
 }
 
 impl Path {
 
-    /// Generate Rc for new path node and automatically get it's self
-    /// reference.
-    fn path_rc_selfref(name: Name, parent: Option<Rc<Path>>)
-            -> Rc<Self> {
-        let mut path = Path {
-            name,
-            parent,
-            selfref: Weak::new(),
-            children: Default::default(),
-        };
-
-        let mut rc = Rc::new(path);
+    /// Separator of the path nodes in the string form of the path.
+    pub const SEPARATOR: char = '.';
 
-        Rc::get_mut(&mut rc).unwrap().selfref = Rc::downgrade(&rc);
-        rc
+    /// Create node with name only. No parent nor children.
+    pub fn new(name: &str) -> Option<Path> {
+        let name = Name::try_new(name)?;
+        Some(Path(Node::intern(None, name)))
     }
 
-    /// Create node with name only. No parent nor children.
-    pub fn new(name: &str) -> Option<Rc<Path>> {
-        let name = Name::try_new(name);
-        if let Some(v) = name {
-            Some(Self::path_rc_selfref(v, None))
-        } else {
-            None
+    /// Create the path from its string form where node names are
+    /// separated by [SEPARATOR](#associatedconstant.SEPARATOR).
+    /// None is returned if any of the names is invalid.
+    ///
+    /// ```
+    /// # use kobzar_ccs_usr::meta::Path;
+    /// let path = Path::parse("org.example.service").unwrap();
+    /// assert_eq!(path.name(), "service");
+    /// assert_eq!(path.to_string(), "org.example.service");
+    ///
+    /// assert!(Path::parse("org..service").is_none());
+    /// ```
+    pub fn parse(s: &str) -> Option<Path> {
+        let mut names = s.split(Self::SEPARATOR);
+        let mut path = Self::new(names.next().unwrap())?;
+        for name in names {
+            path = path.try_new(name)?;
         }
+        Some(path)
     }
 
     /// Try creating new Path with given name. This node is treated
     /// as path's parent. The passed name is validated and in case
     /// it is invalid None will be returned. Otherwise, the path is
     /// returned and node is registered as child in current one.
-    pub fn try_new(&mut self, name: &str) -> Option<Rc<Path>> {
-        // Create new path without this parent reference.
-        let path = Self::new(name);
-        if path.is_none() {
-            // Name is invalid.
-            return None;
-        }
-        let mut path = path.unwrap();
-
-        // Set this parent.
-        Rc::get_mut(&mut path).unwrap().parent
-                = Some(self.selfref.upgrade().unwrap());
-
-        // Add new path to current node children.
-        let weakname = unsafe { WeakName::from(&path.as_ref().name) };
-        self.children.insert(weakname, path.clone());
+    /// If the child with such name already exists it gets returned
+    /// instead of creating new one.
+    pub fn try_new(&self, name: &str) -> Option<Path> {
+        let name = Name::try_new(name)?;
+        Some(Path(Node::intern(Some(&self.0), name)))
+    }
 
-        Some(path)
+    /// ID of the path node. Equal paths that exist at the same time
+    /// have the same ID. IDs are never reused, so the path created again
+    /// after all handles of the previous one were dropped gets new ID.
+    pub fn id(&self) -> usize {
+        self.0.id
     }
 
     /// This node name.
     pub fn name(&self) -> &str {
-        self.name.as_ref()
+        &self.0.name
     }
 
     /// Parent node for current node.
-    pub fn parent(&self) -> &Option<Rc<Path>> {
-        &self.parent
+    pub fn parent(&self) -> Option<Path> {
+        self.0.parent.clone().map(Path)
     }
 
     /// Iterator over path nodes starting from current node.
     pub fn iter(&self) -> PathNodeIterator {
-        PathNodeIterator::new(self.selfref.clone().upgrade().unwrap())
+        PathNodeIterator::new(self.clone())
     }
-}
-
-impl PartialEq for Path {
-
-    fn eq(&self, other: &Path) -> bool {
-        // Check names.
-        if self.name != other.name {
-            return false;
-        }
 
-        // Check parents.
-        if self.parent != other.parent {
+    /// Whether this path starts with all the nodes of given path.
+    /// Path always starts with itself.
+    pub fn starts_with(&self, other: &Path) -> bool {
+        if other.0.depth > self.0.depth {
             return false;
         }
+        Node::ancestor(&self.0, other.0.depth).id == other.0.id
+    }
 
-        // Check paths.
-        let mut selfiter = self.iter();
-        let mut otheriter = other.iter();
-        loop {
-            let selfnext = selfiter.next();
-            let othernext = otheriter.next();
-
-            // Check whether iterator finished.
-            if selfnext.is_none() {
-                // Whether other iterator finished too.
-                if othernext.is_none() {
-                    // Path nodes are equal.
-                    break;
-                } else {
-                    // Other iterator still have nodes so
-                    // the paths are not equal.
-                    return false;
-                }
-            }
+    /// Nodes of the path beginning from the root one.
+    fn nodes(&self) -> Vec<Path> {
+        let mut vec: Vec<Path> = self.iter().collect();
+        vec.reverse();
+        vec
+    }
+}
 
-            // Whether current values are equal.
-            if selfnext.unwrap() != othernext.unwrap() {
-                return false;
-            }
-        }
+impl PartialEq for Path {
 
-        // Check children.
-        if self.children == other.children {
-            return true;
-        } else {
-            return false;
-        }
+    fn eq(&self, other: &Path) -> bool {
+        // Each path is stored only once.
+        self.0.id == other.0.id
     }
 }
 
@@ -281,8 +304,30 @@ impl PartialOrd for Path {
 impl Ord for Path {
 
     fn cmp(&self, other: &Path) -> Ordering {
-        let mut iter = self.iter();
-        unimplemented!()
+        if self.0.id == other.0.id {
+            return Ordering::Equal;
+        }
+        Node::compare(&self.0, &other.0)
+    }
+}
+
+impl fmt::Display for Path {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, node) in self.nodes().iter().enumerate() {
+            if i != 0 {
+                write!(f, "{}", Self::SEPARATOR)?;
+            }
+            write!(f, "{}", node.name())?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Path {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Path({})", self)
     }
 }
 
@@ -314,4 +359,64 @@ mod test {
         let result = Name::try_new("Cannot contain spaces");
         assert!(result.is_none())
     }
+
+    #[test]
+    fn path_order() {
+        let a = Path::parse("org.a").unwrap();
+        let b = Path::parse("org.b").unwrap();
+        let long = Path::parse("org.a.b").unwrap();
+
+        assert!(a < b);
+        assert!(a < long);
+        assert!(long < b);
+    }
+
+    #[test]
+    fn path_child_reuse() {
+        let root = Path::new("org").unwrap();
+        let first = root.try_new("child").unwrap();
+        let second = root.try_new("child").unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(first.id(), second.id());
+        assert!(second.starts_with(&root));
+        assert!(!root.starts_with(&second));
+    }
+
+    #[test]
+    fn path_order_by_names() {
+        let a = Path::parse("order_test.b.a").unwrap();
+        let b = Path::parse("order_test.a.b").unwrap();
+        let other = Path::parse("order_test2").unwrap();
+
+        // Paths created later still go in order of their names.
+        assert!(b < a);
+        assert!(a < other);
+        assert_eq!(a.cmp(&a.clone()), Ordering::Equal);
+    }
+
+    #[test]
+    fn path_nodes_freed() {
+        let path = Path::parse("freed_test.a.b").unwrap();
+        let parent = path.parent().unwrap();
+        drop(path);
+
+        // Parent keeps only itself and its ancestors alive.
+        let key = (None, Shared::from("freed_test"));
+        let interned = |key| Node::with_table(|table| table.contains_key(key))
+                .unwrap();
+        assert!(interned(&key));
+        assert!(parent.try_new("b").is_some());
+        drop(parent);
+        assert!(!interned(&key));
+    }
+
+    #[test]
+    fn path_id_not_reused() {
+        let path = Path::parse("id_test.a").unwrap();
+        let id = path.id();
+        assert_eq!(Path::parse("id_test.a").unwrap().id(), id);
+        drop(path);
+        assert_ne!(Path::parse("id_test.a").unwrap().id(), id);
+    }
 }
@@ -1,5 +1,4 @@
 use super::*;
-use std::rc::Rc;
 
 /// Handle of the service. Allows to access some service in
 /// the network of selected object.
@@ -7,15 +6,15 @@ use std::rc::Rc;
 pub struct Service {
 
     /// The object in which this service lives.
-    object: Rc<Object>,
+    object: Shared<Object>,
 
     /// Path to this service. Should extend the path of origin object.
     /// This is a hard error not to extend the object path.
-    path: Rc<Path>,
+    path: Path,
 
     /// ID that definitely identifies the service. This ID is guaranteed to
     /// be unique in the map of services of local network.
-    id: usize,
+    id: Id,
 }
 
 /// Entry point of service. When service gets started, this entry point
@@ -28,6 +27,14 @@ pub trait ServiceEntry {
     fn start(&self, channel: &Channel);
 }
 
+impl<F> ServiceEntry for F
+        where F: Fn(&Channel) {
+
+    fn start(&self, channel: &Channel) {
+        self(channel)
+    }
+}
+
 /// Architecture-dependent Service part.
 pub trait ServiceArchitecture: Ord {
 
@@ -45,6 +52,13 @@ pub trait ServiceArchitecture: Ord {
 
 impl Service {
 
+    /// Create new service handle. The service path is created as a child
+    /// of the object path. None is returned if the name is invalid.
+    pub fn new(object: Shared<Object>, name: &str, id: Id) -> Option<Self> {
+        let path = object.path().try_new(name)?;
+        Some(Service { object, path, id })
+    }
+
     /// Object where this service is located.
     pub fn object(&self) -> &Object {
         &self.object
@@ -57,11 +71,16 @@ impl Service {
 
     /// ID that uniquely identifies this service inside the object.
     pub fn id(&self) -> usize {
+        self.id.index()
+    }
+
+    /// ID tagged with its generation. See [Id](struct.Id.html).
+    pub fn tagged_id(&self) -> Id {
         self.id
     }
 
     /// Path to this service.
     pub fn path(&self) -> &Path {
-        self.path.as_ref()
+        &self.path
     }
 }
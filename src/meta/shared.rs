@@ -0,0 +1,101 @@
+//! Reference counting used by the handles.
+//!
+//! By default handles use `Rc` and `RefCell` and are bound to the thread
+//! that created them. With `sync` feature they use `Arc` and `RwLock`
+//! so handles are `Send` and `Sync` and can be used by the master
+//! of multi-threaded runtime.
+
+#[cfg(not(feature = "sync"))]
+use std::cell::RefCell;
+#[cfg(feature = "sync")]
+use std::sync::{PoisonError, RwLock};
+
+/// Shared pointer of the handles.
+#[cfg(not(feature = "sync"))]
+pub use std::rc::Rc as Shared;
+
+/// Shared pointer of the handles.
+#[cfg(feature = "sync")]
+pub use std::sync::Arc as Shared;
+
+/// Weak pointer of the handles.
+#[cfg(not(feature = "sync"))]
+pub use std::rc::Weak as WeakShared;
+
+/// Weak pointer of the handles.
+#[cfg(feature = "sync")]
+pub use std::sync::Weak as WeakShared;
+
+/// Cell with interior mutability that can be shared along with
+/// the handles.
+#[derive(Default, Debug)]
+pub struct SharedCell<T> {
+
+    /// Value of the cell.
+    #[cfg(not(feature = "sync"))]
+    value: RefCell<T>,
+
+    /// Value of the cell.
+    #[cfg(feature = "sync")]
+    value: RwLock<T>,
+}
+
+impl<T> SharedCell<T> {
+
+    /// Create the cell with given value.
+    pub fn new(value: T) -> Self {
+        SharedCell { value: value.into() }
+    }
+
+    /// Call the function with the value. Many readers can access
+    /// the value at once.
+    #[cfg(not(feature = "sync"))]
+    pub fn read<F, R>(&self, f: F) -> R
+            where F: FnOnce(&T) -> R {
+        f(&self.value.borrow())
+    }
+
+    /// Call the function with the value. Many readers can access
+    /// the value at once.
+    #[cfg(feature = "sync")]
+    pub fn read<F, R>(&self, f: F) -> R
+            where F: FnOnce(&T) -> R {
+        f(&self.value.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Call the function with the mutable value. Function must not
+    /// access the cell again.
+    #[cfg(not(feature = "sync"))]
+    pub fn write<F, R>(&self, f: F) -> R
+            where F: FnOnce(&mut T) -> R {
+        f(&mut self.value.borrow_mut())
+    }
+
+    /// Call the function with the mutable value. Function must not
+    /// access the cell again.
+    #[cfg(feature = "sync")]
+    pub fn write<F, R>(&self, f: F) -> R
+            where F: FnOnce(&mut T) -> R {
+        f(&mut self.value.write().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+#[cfg(all(test, feature = "sync"))]
+mod test {
+
+    use super::super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn handles_are_sync() {
+        assert_send_sync::<Path>();
+        assert_send_sync::<Object>();
+        assert_send_sync::<Service>();
+        assert_send_sync::<Channel>();
+        assert_send_sync::<Thread>();
+        assert_send_sync::<Interface>();
+        assert_send_sync::<Message>();
+        assert_send_sync::<HandleCollection<Service>>();
+    }
+}
@@ -1,22 +1,156 @@
 use super::*;
-use std::rc::Rc;
+use std::collections::BTreeSet;
+use std::time::Instant;
+
+/// Channels the waiting thread is blocked on. Channels are given
+/// by their IDs.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum WaitTarget {
+
+    /// Thread wakes up when event appears in any of the channels.
+    Any(BTreeSet<usize>),
+
+    /// Thread wakes up when events appear in all of the channels.
+    All(BTreeSet<usize>),
+}
 
 /// Thread state.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub enum ThreadState {
 
     /// Just running as usual.
     Active,
 
-    /// Waiting for channel event.
-    Wait,
+    /// Waiting for channel events.
+    Wait {
+
+        /// Channels the thread is waiting on.
+        target: WaitTarget,
+
+        /// Time when thread stops waiting even if no events appear.
+        deadline: Option<Instant>,
+    },
+
+    /// Thread was suspended and does not run until it is resumed.
+    Suspended,
 
     /// Thread finished execution and is dead.
     Dead,
+
+    /// Entry point of the thread panicked. Contains the panic message.
+    Faulted(String),
+}
+
+impl ThreadState {
+
+    /// Whether the thread has finished execution either normally
+    /// or by fault.
+    pub fn is_finished(&self) -> bool {
+        matches!(*self, ThreadState::Dead | ThreadState::Faulted(_))
+    }
+
+    /// Channels the thread is waiting on if it is waiting.
+    pub fn wait_target(&self) -> Option<&WaitTarget> {
+        match *self {
+            ThreadState::Wait { ref target, .. } => Some(target),
+            _ => None,
+        }
+    }
+
+    /// Time when the waiting thread stops waiting. None if thread is not
+    /// waiting or waits without deadline.
+    pub fn deadline(&self) -> Option<Instant> {
+        match *self {
+            ThreadState::Wait { deadline, .. } => deadline,
+            _ => None,
+        }
+    }
+}
+
+impl WaitTarget {
+
+    /// Wait target of single channel.
+    pub fn single(channel: usize) -> Self {
+        WaitTarget::Any(Some(channel).into_iter().collect())
+    }
+
+    /// IDs of the channels the thread is waiting on.
+    pub fn channels(&self) -> &BTreeSet<usize> {
+        match *self {
+            WaitTarget::Any(ref set) => set,
+            WaitTarget::All(ref set) => set,
+        }
+    }
+}
+
+/// Error which appears when managing the threads.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ThreadError {
+
+    /// There is no thread with such ID.
+    NotFound,
+
+    /// System failed to start new thread.
+    SpawnFailed,
+
+    /// Thread did not finish before the timeout.
+    TimedOut,
+
+    /// Thread cannot apply this operation to itself.
+    OwnThread,
+
+    /// Thread has already finished.
+    Finished,
+
+    /// Handle of the thread is stale. See [Id](struct.Id.html).
+    StaleHandle,
+}
+
+pub type ThreadResult<T> = Result<T, ThreadError>;
+
+/// Error which appears when allocating the memory.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MemoryError {
+
+    /// Allocation would exceed the memory quota of the thread
+    /// or its object.
+    QuotaExceeded,
+
+    /// Region is not granted to the thread.
+    NotGranted,
+
+    /// Grant of the region was revoked.
+    Revoked,
+
+    /// Thread does not have the access needed for the operation.
+    AccessDenied,
+}
+
+pub type MemoryResult<T> = Result<T, MemoryError>;
+
+/// What happens to the thread that exceeds its memory quota.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum QuotaPolicy {
+
+    /// Allocating operation fails.
+    Fail,
+
+    /// Allocating operation fails and the thread gets cancelled.
+    /// It faults when it exits.
+    Fault,
 }
 
 /// Architecture-dependent implementation of memory.
 pub trait MemoryArchitecture {
+
+    /// Count of bytes that are used now.
+    fn usage(&self) -> usize;
+
+    /// Highest count of bytes that were used at once.
+    fn peak(&self) -> usize;
+
+    /// Count of bytes that can be used. None if memory is not limited.
+    fn limit(&self) -> Option<usize>;
 }
 
 /// Architecture-dependent implementation of Thread.
@@ -37,6 +171,32 @@ pub trait ThreadArchitecture {
 
     /// Set of all connected channels.
     fn connected_channels(&self) -> ChannelArchSet<Self::C>;
+
+    /// Whether the thread was requested to cancel. Cancellation is
+    /// cooperative so the thread should check it and finish.
+    fn is_cancelled(&self) -> bool;
+
+    /// Descriptors of the handle table of this thread. Thread is
+    /// a member of the channel as long as it has any descriptor of it.
+    fn descriptors(&self) -> Vec<Descriptor>;
+
+    /// Channel the descriptor refers to. Descriptors of the channels
+    /// the thread got disconnected from report it until they are closed.
+    fn channel(&self, descriptor: Descriptor) -> ChannelResult<Self::C>;
+
+    /// Create new descriptor that refers to the same channel.
+    fn duplicate(&self, descriptor: Descriptor) -> ChannelResult<Descriptor>;
+
+    /// Remove the descriptor from the handle table. Thread leaves
+    /// the channel when its last descriptor gets closed.
+    fn close(&self, descriptor: Descriptor) -> ChannelResult<()>;
+
+    /// Move the descriptor to the handle table of other thread. Other
+    /// thread joins the channel if it is not a member yet. When the last
+    /// descriptor of this thread is transfered, undelivered messages go
+    /// to the other thread. Descriptor of the other thread is returned.
+    fn transfer(&self, descriptor: Descriptor, to: &Thread)
+            -> ChannelResult<Descriptor>;
 }
 
 /// Thread that is performing in the system.
@@ -44,16 +204,26 @@ pub trait ThreadArchitecture {
 pub struct Thread {
 
     /// Unique ID of this thread in some Object.
-    id: usize,
+    id: Id,
 
     /// Origin service instance of which this thread is.
-    origin: Rc<Service>,
+    origin: Shared<Service>,
 }
 
 impl Thread {
 
+    /// Create new thread handle.
+    pub fn new(origin: Shared<Service>, id: Id) -> Self {
+        Thread { id, origin }
+    }
+
     /// Unique ID of this thread in some Object.
     pub fn id(&self) -> usize {
+        self.id.index()
+    }
+
+    /// ID tagged with its generation. See [Id](struct.Id.html).
+    pub fn tagged_id(&self) -> Id {
         self.id
     }
 
@@ -0,0 +1,74 @@
+use super::Path;
+
+/// Pattern of the topic paths the subscriber is interested in. Nodes
+/// of the pattern are separated like the nodes of the path. Node `*`
+/// matches any single node and the last node `**` matches any nodes
+/// that are left, including none.
+///
+/// ```
+/// # use kobzar_ccs_usr::meta::{Path, TopicPattern};
+/// let config = TopicPattern::parse("config.*.changed").unwrap();
+/// let telemetry = TopicPattern::parse("telemetry.**").unwrap();
+///
+/// let path = Path::parse("config.net.changed").unwrap();
+/// assert!(config.matches(&path));
+/// assert!(!telemetry.matches(&path));
+///
+/// assert!(telemetry.matches(&Path::parse("telemetry").unwrap()));
+/// assert!(TopicPattern::parse("a.**.b").is_none());
+/// ```
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct TopicPattern {
+
+    /// Nodes of the pattern beginning from the root one.
+    nodes: Vec<String>,
+}
+
+impl TopicPattern {
+
+    /// Node that matches any single node of the path.
+    pub const ANY_NODE: &'static str = "*";
+
+    /// Last node that matches all the rest nodes of the path.
+    pub const ANY_REST: &'static str = "**";
+
+    /// Create the pattern from its string form. None is returned
+    /// if some node is neither valid name nor wildcard or if
+    /// [ANY_REST](#associatedconstant.ANY_REST) is not the last node.
+    pub fn parse(s: &str) -> Option<Self> {
+        let nodes: Vec<String> = s.split(Path::SEPARATOR)
+                .map(String::from)
+                .collect();
+        let last = nodes.len() - 1;
+        for (i, node) in nodes.iter().enumerate() {
+            let valid = match node.as_str() {
+                Self::ANY_NODE => true,
+                Self::ANY_REST => i == last,
+                name => Path::new(name).is_some(),
+            };
+            if !valid {
+                return None;
+            }
+        }
+        Some(TopicPattern { nodes })
+    }
+
+    /// Whether the path matches the pattern.
+    pub fn matches(&self, path: &Path) -> bool {
+        let mut names: Vec<Path> = path.iter().collect();
+        names.reverse();
+
+        for (i, node) in self.nodes.iter().enumerate() {
+            match node.as_str() {
+                Self::ANY_REST => return true,
+                Self::ANY_NODE if i < names.len() => (),
+                name => {
+                    if names.get(i).map(|path| path.name()) != Some(name) {
+                        return false;
+                    }
+                },
+            }
+        }
+        names.len() == self.nodes.len()
+    }
+}
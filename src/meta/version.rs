@@ -78,10 +78,10 @@ impl From<(usize, usize, usize)> for Version {
     }
 }
 
-impl Into<(usize, usize, usize)> for Version {
+impl From<Version> for (usize, usize, usize) {
 
-    fn into(self) -> (usize, usize, usize) {
-        (self.major, self.minor, self.patch)
+    fn from(version: Version) -> (usize, usize, usize) {
+        (version.major, version.minor, version.patch)
     }
 }
 
@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use super::meta::{ChannelArchitecture, ChannelError, Message};
+use super::frame::Frame;
+use super::*;
+
+/// Correlation ID of the next call of any client. Replies are delivered
+/// to all members of the channel, so IDs are unique in the process
+/// and each client takes only the replies to its own calls.
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Client that calls the methods of the service on the other side
+/// of the channel. Many calls can be in flight at once and their
+/// replies can be awaited in any order.
+///
+/// ```
+/// # use kobzar_ccs_usr::local::*;
+/// # use kobzar_ccs_usr::master::*;
+/// # use kobzar_ccs_usr::meta::*;
+/// # use kobzar_ccs_usr::rpc::*;
+/// # use std::collections::BTreeSet;
+/// fn interface() -> Interface {
+///     let mut services = BTreeSet::new();
+///     services.insert("text".to_string());
+///     let vendor = Path::parse("org.example.text").unwrap();
+///     let mut interface = Interface::new(vendor, Version::new(1, 0, 0),
+///             services, Default::default());
+///     Method::<String, String>::declare(&mut interface, "text", "upper")
+///             .unwrap();
+///     interface
+/// }
+///
+/// let mut master = MyMaster::new();
+/// let entry = MyServiceEntry::new(|chan: &Channel| {
+///     let chan = MyMaster::current().unwrap().channel(chan).unwrap();
+///     let upper = Method::<String, String>::new(&interface(), "text",
+///             "upper").unwrap();
+///     let mut server = RpcServer::new(&chan);
+///     server.handle(&upper, |s: String| Ok(s.to_uppercase()));
+///     server.serve().unwrap();
+/// });
+/// let (_, chan) = master.spawn_thread(entry).unwrap();
+///
+/// let upper = Method::<String, String>::new(&interface(), "text", "upper")
+///         .unwrap();
+/// let mut client = RpcClient::new(&chan);
+/// let first = client.start(&upper, &"a".to_string(), None).unwrap();
+/// let second = client.start(&upper, &"b".to_string(), None).unwrap();
+/// assert_eq!(client.in_flight(), 2);
+/// assert_eq!(client.wait(second), Ok("B".to_string()));
+/// assert_eq!(client.wait(first), Ok("A".to_string()));
+/// ```
+pub struct RpcClient<'a, C: ChannelArchitecture + 'a> {
+
+    /// Channel to the server.
+    channel: &'a C,
+
+    /// Deadlines of the calls in flight by their correlation IDs.
+    calls: BTreeMap<u64, Option<Instant>>,
+
+    /// Replies that arrived while other call was awaited.
+    replies: BTreeMap<u64, RpcResult<Vec<u8>>>,
+}
+
+/// Call in flight. It is used to wait for the response or to cancel
+/// the call.
+#[derive(Debug)]
+pub struct Call<Resp: Codec> {
+
+    /// Correlation ID of the call.
+    id: u64,
+
+    /// Type of the response.
+    response: PhantomData<fn() -> Resp>,
+}
+
+impl<'a, C: ChannelArchitecture> RpcClient<'a, C> {
+
+    /// Create the client that calls the methods over given channel.
+    pub fn new(channel: &'a C) -> Self {
+        RpcClient {
+            channel,
+            calls: Default::default(),
+            replies: Default::default(),
+        }
+    }
+
+    /// Send the request and return without waiting for the response.
+    /// Call gets cancelled if the response does not arrive before
+    /// the timeout.
+    pub fn start<Req, Resp>(&mut self, method: &Method<Req, Resp>,
+            request: &Req, timeout: Option<Duration>)
+            -> RpcResult<Call<Resp>>
+            where Req: Codec, Resp: Codec {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let frame = Frame::Request {
+            id,
+            method: method.name().to_string(),
+            payload: request.encode(),
+        };
+        self.channel.send(Message::new(frame.encode()?))?;
+
+        self.calls.insert(id, timeout.map(|timeout| Instant::now() + timeout));
+        Ok(Call { id, response: PhantomData })
+    }
+
+    /// Wait for the response of the call. Replies to other calls that
+    /// arrive meanwhile are kept until they are awaited.
+    pub fn wait<Resp: Codec>(&mut self, call: Call<Resp>)
+            -> RpcResult<Resp> {
+        loop {
+            if let Some(reply) = self.replies.remove(&call.id) {
+                self.calls.remove(&call.id);
+                let data = reply?;
+                return Resp::decode(&data).ok_or(RpcError::BadMessage);
+            }
+
+            let deadline = match self.calls.get(&call.id) {
+                Some(&deadline) => deadline,
+                None => return Err(RpcError::UnknownCall),
+            };
+            let msg = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if deadline <= now {
+                        self.cancel(call)?;
+                        return Err(RpcError::TimedOut);
+                    }
+                    self.channel.receive_timeout(deadline - now)?
+                },
+                None => Some(self.channel.receive()?),
+            };
+            if let Some(msg) = msg {
+                self.accept(&msg);
+            }
+        }
+    }
+
+    /// Call the method and wait for the response.
+    pub fn call<Req, Resp>(&mut self, method: &Method<Req, Resp>,
+            request: &Req, timeout: Option<Duration>) -> RpcResult<Resp>
+            where Req: Codec, Resp: Codec {
+        let call = self.start(method, request, timeout)?;
+        self.wait(call)
+    }
+
+    /// Cancel the call. Server does not handle it if it has not started
+    /// yet. Late response gets dropped.
+    pub fn cancel<Resp: Codec>(&mut self, call: Call<Resp>)
+            -> RpcResult<()> {
+        if self.calls.remove(&call.id).is_none() {
+            return Err(RpcError::UnknownCall);
+        }
+        self.replies.remove(&call.id);
+
+        let frame = Frame::Cancel { id: call.id };
+        match self.channel.send(Message::new(frame.encode()?)) {
+            // Server has gone so it will not reply anyway.
+            Err(ChannelError::Disconnected) => Ok(()),
+            other => other.map_err(RpcError::from),
+        }
+    }
+
+    /// Count of the calls which responses were not awaited yet.
+    pub fn in_flight(&self) -> usize {
+        self.calls.len()
+    }
+
+    /// Keep the reply to the call in flight. Other messages are dropped.
+    fn accept(&mut self, msg: &Message) {
+        let (id, reply) = match Frame::decode(msg.data()) {
+            Some(Frame::Response { id, payload }) => (id, Ok(payload)),
+            Some(Frame::Error { id, error }) => (id, Err(error)),
+            _ => return,
+        };
+        if self.calls.contains_key(&id) {
+            self.replies.insert(id, reply);
+        }
+    }
+}
+
+impl<Resp: Codec> Call<Resp> {
+
+    /// Correlation ID of the call.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
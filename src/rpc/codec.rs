@@ -0,0 +1,88 @@
+/// Value that can be sent as the request or the response of the method.
+pub trait Codec: Sized {
+
+    /// Encode the value to the bytes of the message.
+    fn encode(&self) -> Vec<u8>;
+
+    /// Decode the value from the bytes of the message. None is returned
+    /// if the bytes are not valid.
+    fn decode(data: &[u8]) -> Option<Self>;
+}
+
+impl Codec for () {
+
+    fn encode(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn decode(data: &[u8]) -> Option<()> {
+        if data.is_empty() {
+            Some(())
+        } else {
+            None
+        }
+    }
+}
+
+impl Codec for Vec<u8> {
+
+    fn encode(&self) -> Vec<u8> {
+        self.clone()
+    }
+
+    fn decode(data: &[u8]) -> Option<Vec<u8>> {
+        Some(data.to_vec())
+    }
+}
+
+impl Codec for String {
+
+    fn encode(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    fn decode(data: &[u8]) -> Option<String> {
+        String::from_utf8(data.to_vec()).ok()
+    }
+}
+
+impl Codec for bool {
+
+    fn encode(&self) -> Vec<u8> {
+        vec![*self as u8]
+    }
+
+    fn decode(data: &[u8]) -> Option<bool> {
+        match *data {
+            [0] => Some(false),
+            [1] => Some(true),
+            _ => None,
+        }
+    }
+}
+
+/// Implement the codec of the integer. Integers are encoded in little
+/// endian byte order.
+macro_rules! int_codec {
+    ($($int: ty),*) => {$(
+
+impl Codec for $int {
+
+    fn encode(&self) -> Vec<u8> {
+        self.to_le_bytes().to_vec()
+    }
+
+    fn decode(data: &[u8]) -> Option<$int> {
+        let mut bytes = [0; ::std::mem::size_of::<$int>()];
+        if data.len() != bytes.len() {
+            return None;
+        }
+        bytes.copy_from_slice(data);
+        Some(<$int>::from_le_bytes(bytes))
+    }
+}
+
+    )*}
+}
+
+int_codec!(u8, u16, u32, u64, i8, i16, i32, i64);
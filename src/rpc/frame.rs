@@ -0,0 +1,183 @@
+use std::convert::TryFrom;
+
+use super::{Codec, RpcError, RpcResult};
+
+/// Frame of the RPC message. First byte of the message is the kind
+/// of the frame and the correlation ID of the call follows it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(super) enum Frame {
+
+    /// Call of the method.
+    Request {
+
+        /// Correlation ID of the call.
+        id: u64,
+
+        /// Full name of the called method.
+        method: String,
+
+        /// Encoded request.
+        payload: Vec<u8>,
+    },
+
+    /// Successful result of the call.
+    Response {
+
+        /// Correlation ID of the call.
+        id: u64,
+
+        /// Encoded response.
+        payload: Vec<u8>,
+    },
+
+    /// Failed call.
+    Error {
+
+        /// Correlation ID of the call.
+        id: u64,
+
+        /// Reason of the failure.
+        error: RpcError,
+    },
+
+    /// Call is not needed anymore. Server drops it if it was not
+    /// handled yet.
+    Cancel {
+
+        /// Correlation ID of the call.
+        id: u64,
+    },
+}
+
+const REQUEST: u8 = 0;
+const RESPONSE: u8 = 1;
+const ERROR: u8 = 2;
+const CANCEL: u8 = 3;
+
+const UNKNOWN_METHOD: u8 = 0;
+const BAD_MESSAGE: u8 = 1;
+const REMOTE: u8 = 2;
+
+/// Longest full name of the method the request frame can carry.
+pub(super) const MAX_METHOD_LEN: usize = u16::MAX as usize;
+
+impl Frame {
+
+    /// Correlation ID of the call this frame belongs to.
+    pub fn id(&self) -> u64 {
+        match *self {
+            Frame::Request { id, .. } => id,
+            Frame::Response { id, .. } => id,
+            Frame::Error { id, .. } => id,
+            Frame::Cancel { id } => id,
+        }
+    }
+
+    /// Encode the frame to the data of the message. Error is returned
+    /// if the name of the method is too long.
+    pub fn encode(&self) -> RpcResult<Vec<u8>> {
+        let kind = match *self {
+            Frame::Request { .. } => REQUEST,
+            Frame::Response { .. } => RESPONSE,
+            Frame::Error { .. } => ERROR,
+            Frame::Cancel { .. } => CANCEL,
+        };
+        let mut data = vec![kind];
+        data.extend(self.id().encode());
+
+        match *self {
+            Frame::Request { ref method, ref payload, .. } => {
+                let len = u16::try_from(method.len())
+                        .map_err(|_| RpcError::BadMessage)?;
+                data.extend(len.encode());
+                data.extend(method.as_bytes());
+                data.extend(payload);
+            },
+            Frame::Response { ref payload, .. } => data.extend(payload),
+            Frame::Error { ref error, .. } => match *error {
+                RpcError::UnknownMethod => data.push(UNKNOWN_METHOD),
+                RpcError::Remote(ref reason) => {
+                    data.push(REMOTE);
+                    data.extend(reason.as_bytes());
+                },
+                _ => data.push(BAD_MESSAGE),
+            },
+            Frame::Cancel { .. } => (),
+        }
+        Ok(data)
+    }
+
+    /// Decode the frame from the data of the message. None is returned
+    /// if the data is not a valid frame.
+    pub fn decode(data: &[u8]) -> Option<Frame> {
+        if data.len() < 9 {
+            return None;
+        }
+        let id = u64::decode(&data[1..9])?;
+        let rest = &data[9..];
+
+        match data[0] {
+            REQUEST => {
+                if rest.len() < 2 {
+                    return None;
+                }
+                let len = u16::decode(&rest[..2])? as usize;
+                let rest = &rest[2..];
+                if rest.len() < len {
+                    return None;
+                }
+                Some(Frame::Request {
+                    id,
+                    method: String::decode(&rest[..len])?,
+                    payload: rest[len..].to_vec(),
+                })
+            },
+            RESPONSE => Some(Frame::Response { id, payload: rest.to_vec() }),
+            ERROR => {
+                let error = match *rest.first()? {
+                    UNKNOWN_METHOD => RpcError::UnknownMethod,
+                    BAD_MESSAGE => RpcError::BadMessage,
+                    REMOTE => RpcError::Remote(String::decode(&rest[1..])?),
+                    _ => return None,
+                };
+                Some(Frame::Error { id, error })
+            },
+            CANCEL if rest.is_empty() => Some(Frame::Cancel { id }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn frame_roundtrip() {
+        let frames = vec![
+            Frame::Request {
+                id: 1,
+                method: "echo.upper".to_string(),
+                payload: vec![1, 2],
+            },
+            Frame::Response { id: 2, payload: vec![] },
+            Frame::Error { id: 3, error: RpcError::UnknownMethod },
+            Frame::Error { id: 4, error: RpcError::Remote("no".into()) },
+            Frame::Cancel { id: u64::MAX },
+        ];
+        for frame in frames {
+            assert_eq!(Frame::decode(&frame.encode().unwrap()), Some(frame));
+        }
+
+        let long = Frame::Request {
+            id: 5,
+            method: "x".repeat(MAX_METHOD_LEN + 1),
+            payload: vec![],
+        };
+        assert_eq!(long.encode(), Err(RpcError::BadMessage));
+
+        assert_eq!(Frame::decode(&[REQUEST, 1]), None);
+        assert_eq!(Frame::decode(&[7; 9]), None);
+    }
+}
@@ -0,0 +1,81 @@
+use std::any;
+use std::marker::PhantomData;
+
+use super::meta::{Interface, MethodSignature, Path};
+use super::frame::MAX_METHOD_LEN;
+use super::Codec;
+
+/// Method of the service declared by the interface. Request and
+/// response types of the method are checked when it is called.
+pub struct Method<Req: Codec, Resp: Codec> {
+
+    /// Full name of the method. Name of the service and name
+    /// of the method are separated by
+    /// [path separator](../meta/struct.Path.html#associatedconstant.SEPARATOR).
+    name: String,
+
+    /// Types of the request and the response.
+    types: PhantomData<fn(Req) -> Resp>,
+}
+
+impl<Req: Codec, Resp: Codec> Method<Req, Resp> {
+
+    /// Declare the method of the service in the interface. None
+    /// is returned if the interface does not declare the service,
+    /// the method is declared already or its name is invalid.
+    pub fn declare(interface: &mut Interface, service: &str, name: &str)
+            -> Option<Self> {
+        let method = Self::named(service, name)?;
+        if !interface.add_method(service, name, Self::signature()) {
+            return None;
+        }
+        Some(method)
+    }
+
+    /// Method of the service declared by the interface. None
+    /// is returned if the interface does not declare the method
+    /// with these request and response types.
+    pub fn new(interface: &Interface, service: &str, name: &str)
+            -> Option<Self> {
+        if interface.method(service, name) != Some(&Self::signature()) {
+            return None;
+        }
+        Self::named(service, name)
+    }
+
+    /// Full name of the method which includes the name of the service.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Signature of the method with these request and response types.
+    fn signature() -> MethodSignature {
+        MethodSignature::new(any::type_name::<Req>(),
+                any::type_name::<Resp>())
+    }
+
+    /// Method with given names. None is returned if the name is invalid
+    /// or the full name does not fit the request frame.
+    fn named(service: &str, name: &str) -> Option<Self> {
+        Path::new(name)?;
+        let name = format!("{}{}{}", service, Path::SEPARATOR, name);
+        if name.len() > MAX_METHOD_LEN {
+            return None;
+        }
+
+        Some(Method {
+            name,
+            types: PhantomData,
+        })
+    }
+}
+
+impl<Req: Codec, Resp: Codec> Clone for Method<Req, Resp> {
+
+    fn clone(&self) -> Self {
+        Method {
+            name: self.name.clone(),
+            types: PhantomData,
+        }
+    }
+}
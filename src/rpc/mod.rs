@@ -0,0 +1,59 @@
+//! Request and response layer over the channel messages. Each request
+//! carries the correlation ID so many calls can be in flight over
+//! the same channel. Methods are declared for the services of the
+//! [interface](../meta/struct.Interface.html) and have typed requests
+//! and responses.
+
+use super::meta;
+
+/// Encoding of the requests and the responses.
+mod codec;
+pub use self::codec::*;
+
+/// Methods of the interface services.
+mod method;
+pub use self::method::*;
+
+/// Frames of the RPC messages.
+mod frame;
+
+/// Client that calls the methods.
+mod client;
+pub use self::client::*;
+
+/// Server that handles the calls.
+mod server;
+pub use self::server::*;
+
+/// Error of the remote call.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum RpcError {
+
+    /// Channel operation failed.
+    Channel(meta::ChannelError),
+
+    /// Reply did not arrive before the timeout of the call. The call
+    /// gets cancelled.
+    TimedOut,
+
+    /// Call is not in flight. It was already finished or cancelled.
+    UnknownCall,
+
+    /// Server does not handle the called method.
+    UnknownMethod,
+
+    /// Request or response could not be decoded.
+    BadMessage,
+
+    /// Handler of the method failed with given reason.
+    Remote(String),
+}
+
+pub type RpcResult<T> = Result<T, RpcError>;
+
+impl From<meta::ChannelError> for RpcError {
+
+    fn from(err: meta::ChannelError) -> Self {
+        RpcError::Channel(err)
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use super::meta::{ChannelArchitecture, ChannelError, Message};
+use super::frame::Frame;
+use super::*;
+
+/// Handler of the method that takes encoded request and returns encoded
+/// response.
+type Handler<'a> = Box<dyn Fn(&[u8]) -> RpcResult<Vec<u8>> + 'a>;
+
+/// Server that handles the calls that arrive over the channel. Requests
+/// are handled in order of their arrival. Requests that were cancelled
+/// before the server got to them are dropped.
+pub struct RpcServer<'a, C: ChannelArchitecture + 'a> {
+
+    /// Channel to the clients.
+    channel: &'a C,
+
+    /// Handlers by the full names of the methods.
+    handlers: BTreeMap<String, Handler<'a>>,
+
+    /// Requests that arrived and were not handled yet. Correlation ID,
+    /// name of the method and the request are kept.
+    queue: VecDeque<(u64, String, Vec<u8>)>,
+}
+
+impl<'a, C: ChannelArchitecture> RpcServer<'a, C> {
+
+    /// Create the server without handlers.
+    pub fn new(channel: &'a C) -> Self {
+        RpcServer {
+            channel,
+            handlers: Default::default(),
+            queue: Default::default(),
+        }
+    }
+
+    /// Set the handler of the method. Error returned by the handler
+    /// is sent to the client as
+    /// [Remote](enum.RpcError.html#variant.Remote) error.
+    pub fn handle<Req, Resp, F>(&mut self, method: &Method<Req, Resp>, f: F)
+            where Req: Codec, Resp: Codec,
+                  F: Fn(Req) -> Result<Resp, String> + 'a {
+        let handler = move |data: &[u8]| {
+            let request = Req::decode(data).ok_or(RpcError::BadMessage)?;
+            f(request).map(|response| response.encode())
+                    .map_err(RpcError::Remote)
+        };
+        self.handlers.insert(method.name().to_string(), Box::new(handler));
+    }
+
+    /// Wait for the next request, handle it and send the reply.
+    pub fn serve_one(&mut self) -> RpcResult<()> {
+        if self.queue.is_empty() {
+            let msg = self.channel.receive()?;
+            self.accept(&msg);
+        }
+
+        // Later messages may cancel queued requests.
+        while let Some(msg) = self.channel.try_receive()? {
+            self.accept(&msg);
+        }
+
+        let (id, method, request) = match self.queue.pop_front() {
+            Some(next) => next,
+            None => return Ok(()),
+        };
+        let frame = match self.handlers.get(&method) {
+            Some(handler) => match handler(&request) {
+                Ok(payload) => Frame::Response { id, payload },
+                Err(error) => Frame::Error { id, error },
+            },
+            None => Frame::Error { id, error: RpcError::UnknownMethod },
+        };
+        self.channel.send(Message::new(frame.encode()?))?;
+        Ok(())
+    }
+
+    /// Serve the requests until all clients leave the channel. Notices
+    /// of the channel are skipped.
+    pub fn serve(&mut self) -> RpcResult<()> {
+        loop {
+            match self.serve_one() {
+                Ok(()) => (),
+                Err(RpcError::Channel(ChannelError::Disconnected)) => {
+                    return Ok(());
+                },
+                Err(RpcError::Channel(ChannelError::Notice(_))) => (),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Queue the request or drop the cancelled one. Other messages
+    /// are dropped.
+    fn accept(&mut self, msg: &Message) {
+        match Frame::decode(msg.data()) {
+            Some(Frame::Request { id, method, payload }) => {
+                self.queue.push_back((id, method, payload));
+            },
+            Some(Frame::Cancel { id }) => {
+                self.queue.retain(|&(queued, _, _)| queued != id);
+            },
+            _ => (),
+        }
+    }
+}
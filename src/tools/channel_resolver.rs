@@ -0,0 +1,80 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use meta::WaitTarget;
+
+/// Threads that wait forever. The first map contains wait targets
+/// of the threads that wait without deadline. The second one contains
+/// member threads of each channel. All threads that are not in the
+/// first map are considered to be able to send messages.
+///
+/// Waiting thread can be woken up by other members of the channel it
+/// waits on if they are not stuck themselves. Thread also wakes up
+/// when the channel gets closed or it is the only member left because
+/// it gets disconnection error then.
+///
+/// ```
+/// # use kobzar_ccs_usr::tools::deadlocked;
+/// # use kobzar_ccs_usr::meta::WaitTarget;
+/// # use std::collections::{BTreeMap, BTreeSet};
+/// let mut members = BTreeMap::new();
+/// members.insert(10, vec![1, 2].into_iter().collect());
+/// members.insert(20, vec![2, 3].into_iter().collect());
+///
+/// // Threads 1 and 2 wait for each other.
+/// let mut waiting = BTreeMap::new();
+/// waiting.insert(1, WaitTarget::single(10));
+/// waiting.insert(2, WaitTarget::single(10));
+/// let stuck: BTreeSet<usize> = vec![1, 2].into_iter().collect();
+/// assert_eq!(deadlocked(&waiting, &members), stuck);
+///
+/// // Thread 2 can be woken up by thread 3 that is running.
+/// waiting.insert(2, WaitTarget::Any(vec![10, 20].into_iter().collect()));
+/// assert!(deadlocked(&waiting, &members).is_empty());
+///
+/// // Thread 2 needs events from both channels but thread 1 is stuck.
+/// waiting.insert(2, WaitTarget::All(vec![10, 20].into_iter().collect()));
+/// assert_eq!(deadlocked(&waiting, &members), stuck);
+/// ```
+pub fn deadlocked(waiting: &BTreeMap<usize, WaitTarget>,
+        members: &BTreeMap<usize, BTreeSet<usize>>) -> BTreeSet<usize> {
+    let mut stuck: BTreeSet<usize> = waiting.keys().cloned().collect();
+    loop {
+        let woken: Vec<usize> = stuck.iter()
+                .filter(|&&thread| {
+                    wakes(thread, &waiting[&thread], members, &stuck)
+                })
+                .cloned()
+                .collect();
+        if woken.is_empty() {
+            return stuck;
+        }
+
+        for thread in woken {
+            stuck.remove(&thread);
+        }
+    }
+}
+
+/// Whether the thread can be woken up by the threads that are not stuck.
+fn wakes(thread: usize, target: &WaitTarget,
+        members: &BTreeMap<usize, BTreeSet<usize>>,
+        stuck: &BTreeSet<usize>) -> bool {
+    let closed = |channel: &usize| match members.get(channel) {
+        Some(set) => set.iter().all(|&id| id == thread),
+        None => true,
+    };
+    let signaled = |channel: &usize| match members.get(channel) {
+        Some(set) => set.iter().any(|id| *id != thread && !stuck.contains(id)),
+        None => false,
+    };
+
+    let channels = target.channels();
+    match *target {
+        WaitTarget::Any(_) => {
+            channels.iter().any(|c| closed(c) || signaled(c))
+        },
+        WaitTarget::All(_) => {
+            channels.iter().any(closed) || channels.iter().all(signaled)
+        },
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Allocator of IDs that get reused after they are released. Each ID
+/// has a generation which grows every time the ID gets released so
+/// handles of the destroyed elements can be told apart from handles
+/// of the new elements with the same ID.
+///
+/// ```
+/// # use kobzar_ccs_usr::tools::IdAllocator;
+/// let mut ids = IdAllocator::new();
+/// let (first, generation) = ids.allocate();
+/// assert!(ids.is_current(first, generation));
+///
+/// assert!(ids.release(first));
+/// assert!(!ids.is_current(first, generation));
+/// assert!(ids.is_known(first));
+///
+/// // Released ID is reused with the next generation.
+/// assert_eq!(ids.allocate(), (first, generation + 1));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct IdAllocator {
+
+    /// Generation of each known ID and whether the ID is in use.
+    slots: BTreeMap<usize, (usize, bool)>,
+
+    /// Released IDs that get allocated before the new ones.
+    free: BTreeSet<usize>,
+}
+
+impl IdAllocator {
+
+    /// Create allocator without any IDs.
+    pub const fn new() -> Self {
+        IdAllocator {
+            slots: BTreeMap::new(),
+            free: BTreeSet::new(),
+        }
+    }
+
+    /// Allocate the lowest free ID. ID and its generation are returned.
+    /// IDs start from 1.
+    pub fn allocate(&mut self) -> (usize, usize) {
+        let id = match self.free.iter().next() {
+            Some(&id) => id,
+            None => self.slots.keys().next_back().map_or(1, |&id| id + 1),
+        };
+        (id, self.acquire(id).unwrap())
+    }
+
+    /// Mark the ID given from outside of the allocator as used.
+    /// Generation of the ID is returned. None is returned if the ID is
+    /// already in use.
+    pub fn acquire(&mut self, id: usize) -> Option<usize> {
+        let slot = self.slots.entry(id).or_insert((0, false));
+        if slot.1 {
+            return None;
+        }
+
+        slot.1 = true;
+        self.free.remove(&id);
+        Some(slot.0)
+    }
+
+    /// Release the ID so it can be used again with the next generation.
+    /// Returns false if the ID was not in use.
+    pub fn release(&mut self, id: usize) -> bool {
+        match self.slots.get_mut(&id) {
+            Some(slot) if slot.1 => {
+                *slot = (slot.0 + 1, false);
+                self.free.insert(id);
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Generation of the ID in use. None if the ID is not in use.
+    pub fn generation(&self, id: usize) -> Option<usize> {
+        self.slots.get(&id)
+                .filter(|slot| slot.1)
+                .map(|slot| slot.0)
+    }
+
+    /// Whether the ID with given generation is in use.
+    pub fn is_current(&self, id: usize, generation: usize) -> bool {
+        self.generation(id) == Some(generation)
+    }
+
+    /// Whether the ID was ever used. Handle with known ID that is not
+    /// current is stale.
+    pub fn is_known(&self, id: usize) -> bool {
+        self.slots.contains_key(&id)
+    }
+}
@@ -11,4 +11,10 @@
 mod channel_resolver;
 pub use self::channel_resolver::*;
 
+/// Finds the order in which dependent elements must be stopped.
+mod stop_order;
+pub use self::stop_order::*;
 
+/// Allocates IDs that are reused with new generations.
+mod id_allocator;
+pub use self::id_allocator::*;
@@ -0,0 +1,66 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Order in which the nodes must be stopped so that each node gets
+/// stopped before all the nodes it depends on. The map contains
+/// dependencies of each node. Dependencies which are not the keys of
+/// the map are ignored.
+///
+/// Nodes that form dependency cycles cannot be ordered. They are put
+/// to the end of the list in their natural order.
+///
+/// ```
+/// # use kobzar_ccs_usr::tools::stop_order;
+/// # use std::collections::{BTreeMap, BTreeSet};
+/// let mut deps = BTreeMap::new();
+/// deps.insert("storage", BTreeSet::new());
+/// deps.insert("player", vec!["storage", "audio"].into_iter().collect());
+/// deps.insert("audio", BTreeSet::new());
+///
+/// let order = stop_order(&deps);
+/// assert_eq!(order, vec!["player", "audio", "storage"]);
+/// ```
+pub fn stop_order<K>(deps: &BTreeMap<K, BTreeSet<K>>) -> Vec<K>
+        where K: Ord + Clone {
+    // Count the nodes that depend on each node.
+    let mut dependents: BTreeMap<&K, usize> = deps.keys()
+            .map(|k| (k, 0))
+            .collect();
+    for node_deps in deps.values() {
+        for dep in node_deps {
+            if let Some(count) = dependents.get_mut(dep) {
+                *count += 1;
+            }
+        }
+    }
+
+    let mut order = Vec::with_capacity(deps.len());
+    let mut done = BTreeSet::new();
+    loop {
+        // Nodes that nothing depends on anymore can be stopped.
+        let ready: Vec<&K> = dependents.iter()
+                .filter(|&(k, &count)| count == 0 && !done.contains(*k))
+                .map(|(&k, _)| k)
+                .collect();
+        if ready.is_empty() {
+            break;
+        }
+
+        for node in ready {
+            done.insert(node);
+            order.push(node.clone());
+            for dep in &deps[node] {
+                if let Some(count) = dependents.get_mut(dep) {
+                    *count -= 1;
+                }
+            }
+        }
+    }
+
+    // Append the nodes of the cycles.
+    for node in deps.keys() {
+        if !done.contains(node) {
+            order.push(node.clone());
+        }
+    }
+    order
+}